@@ -0,0 +1,179 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+#![feature(min_specialization)]
+
+/// mintable PSP22 token with pathological behaviour selected with `set_quirk`, used to check how lenders handle bad assets.
+/// Balances are kept as shares of `rebase_index_e6`, without a quirk the index stays 10^6 and the token behaves like `psp22_mintable`.
+/// Transfers do not call `before_received` of the recipient.
+#[openbrush::contract]
+pub mod adversarial_psp22 {
+    use flash_loan::raw_call::{EncodedArgs, RawOutput};
+    use ink_env::call::{build_call, Call, ExecutionInput, Selector};
+    use ink_env::{CallFlags, DefaultEnvironment};
+    use ink_prelude::{string::String, vec::Vec};
+    use ink_storage::traits::{PackedLayout, SpreadAllocate, SpreadLayout};
+    use openbrush::{
+        contracts::psp22::extensions::{metadata::*, mintable::*},
+        traits::{Flush, Storage},
+    };
+
+    const E6: u128 = 1_000_000;
+
+    #[derive(
+        Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode, SpreadLayout, PackedLayout,
+    )]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+    )]
+    pub enum TokenQuirk {
+        /// `fee_e6` / 10^6 of each transfer is burnt, the recipient gets the rest
+        FeeOnTransfer { fee_e6: u128 },
+        /// the index grows by `rebase_e6` / 10^6 after each transfer, so all balances grow
+        Rebasing { rebase_e6: u128 },
+        /// balance_of returns `reported` for every account
+        LyingBalance { reported: Balance },
+        /// transfers return Ok without moving funds
+        NoOpTransfer,
+        /// calls `callee` with `selector` and SCALE encoded `input` before moving funds. The result is ignored,
+        /// the output of the last successful call can be read with `reentry_output`. The call is not repeated when the token is reentered.
+        Reentrant {
+            callee: AccountId,
+            selector: [u8; 4],
+            input: Vec<u8>,
+        },
+        /// like `Reentrant`, but only for transfers to `callee`, e.g. when a lender pulls back a repayment
+        ReentrantOnReceive {
+            callee: AccountId,
+            selector: [u8; 4],
+            input: Vec<u8>,
+        },
+    }
+
+    #[ink(storage)]
+    #[derive(Default, SpreadAllocate, Storage)]
+    pub struct AdversarialPSP22 {
+        #[storage_field]
+        psp22: psp22::Data,
+        #[storage_field]
+        metadata: metadata::Data,
+
+        quirk: Option<TokenQuirk>,
+        rebase_index_e6: u128,
+        /// set while the callback of `Reentrant` is executed
+        reentered: bool,
+        reentry_output: Option<Vec<u8>>,
+    }
+
+    impl PSP22 for AdversarialPSP22 {
+        #[ink(message)]
+        fn balance_of(&self, owner: AccountId) -> Balance {
+            match self.quirk {
+                Some(TokenQuirk::LyingBalance { reported }) => reported,
+                _ => self._balance_of(&owner) * self.rebase_index_e6 / E6,
+            }
+        }
+    }
+
+    impl psp22::Internal for AdversarialPSP22 {
+        fn _transfer_from_to(
+            &mut self,
+            from: AccountId,
+            to: AccountId,
+            amount: Balance,
+            _data: Vec<u8>,
+        ) -> Result<(), PSP22Error> {
+            let reentry = match self.quirk.clone() {
+                Some(TokenQuirk::NoOpTransfer) => return Ok(()),
+                Some(TokenQuirk::Reentrant {
+                    callee,
+                    selector,
+                    input,
+                }) => Some((callee, selector, input)),
+                Some(TokenQuirk::ReentrantOnReceive {
+                    callee,
+                    selector,
+                    input,
+                }) if to == callee => Some((callee, selector, input)),
+                _ => None,
+            };
+            match reentry {
+                Some((callee, selector, input)) if !self.reentered => {
+                    self.reentered = true;
+                    self.flush();
+                    let output = build_call::<DefaultEnvironment>()
+                        .call_type(Call::new().callee(callee))
+                        .exec_input(
+                            ExecutionInput::new(Selector::new(selector))
+                                .push_arg(EncodedArgs(input)),
+                        )
+                        .returns::<RawOutput>()
+                        .call_flags(CallFlags::default().set_allow_reentry(true))
+                        .fire();
+                    self.load();
+                    if let Ok(output) = output {
+                        self.reentry_output = Some(output.0);
+                    }
+                    self.reentered = false;
+                }
+                _ => {}
+            }
+
+            let shares = amount * E6 / self.rebase_index_e6;
+            let from_shares = self._balance_of(&from);
+            if from_shares < shares {
+                return Err(PSP22Error::InsufficientBalance);
+            }
+            let received = match self.quirk {
+                Some(TokenQuirk::FeeOnTransfer { fee_e6 }) => shares - shares * fee_e6 / E6,
+                _ => shares,
+            };
+            self.psp22.balances.insert(&from, &(from_shares - shares));
+            let to_shares = self._balance_of(&to);
+            self.psp22.balances.insert(&to, &(to_shares + received));
+            self.psp22.supply -= shares - received;
+
+            if let Some(TokenQuirk::Rebasing { rebase_e6 }) = self.quirk {
+                self.rebase_index_e6 = self.rebase_index_e6 * (E6 + rebase_e6) / E6;
+            }
+            Ok(())
+        }
+    }
+
+    impl PSP22Metadata for AdversarialPSP22 {}
+
+    impl PSP22Mintable for AdversarialPSP22 {}
+
+    impl AdversarialPSP22 {
+        #[ink(constructor)]
+        pub fn new(name: Option<String>, symbol: Option<String>, decimal: u8) -> Self {
+            ink_lang::codegen::initialize_contract(|instance: &mut Self| {
+                instance.metadata.name = name;
+                instance.metadata.symbol = symbol;
+                instance.metadata.decimals = decimal;
+                instance.rebase_index_e6 = E6;
+            })
+        }
+
+        /// None makes the token well behaved
+        #[ink(message)]
+        pub fn set_quirk(&mut self, quirk: Option<TokenQuirk>) {
+            self.quirk = quirk;
+        }
+
+        #[ink(message)]
+        pub fn quirk(&self) -> Option<TokenQuirk> {
+            self.quirk.clone()
+        }
+
+        #[ink(message)]
+        pub fn rebase_index_e6(&self) -> u128 {
+            self.rebase_index_e6
+        }
+
+        /// SCALE encoded output of the last successful call made by `Reentrant`
+        #[ink(message)]
+        pub fn reentry_output(&self) -> Option<Vec<u8>> {
+            self.reentry_output.clone()
+        }
+    }
+}
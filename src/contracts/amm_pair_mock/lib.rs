@@ -0,0 +1,168 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+#![feature(min_specialization)]
+
+/// constant product (x * y = k) AMM pair of two PSP22 tokens. Used as a market in arbitrage tests, it has no LP token.
+#[openbrush::contract]
+pub mod amm_pair_mock {
+    use flash_loan::traits::amm_pair::*;
+    use ink_env::CallFlags;
+    use ink_lang::codegen::{EmitEvent, Env};
+    use ink_prelude::vec::Vec;
+    use ink_storage::traits::SpreadAllocate;
+    use openbrush::contracts::traits::psp22::PSP22Ref;
+    use openbrush::traits::Storage;
+    use primitive_types::U256;
+
+    const E6: u128 = 1_000_000;
+
+    #[ink(storage)]
+    #[derive(Default, SpreadAllocate, Storage)]
+    pub struct AmmPairMock {
+        token_0: AccountId,
+        token_1: AccountId,
+        reserve_0: Balance,
+        reserve_1: Balance,
+        /// swap fee multiplied by 10^6, taken from the input amount
+        fee_e6: u128,
+    }
+
+    #[ink(event)]
+    pub struct Swap {
+        #[ink(topic)]
+        sender: AccountId,
+        #[ink(topic)]
+        token_in: AccountId,
+        amount_in: Balance,
+        amount_out: Balance,
+        to: AccountId,
+    }
+
+    impl AmmPair for AmmPairMock {
+        #[ink(message)]
+        fn tokens(&self) -> (AccountId, AccountId) {
+            (self.token_0, self.token_1)
+        }
+
+        #[ink(message)]
+        fn get_reserves(&self) -> (Balance, Balance) {
+            (self.reserve_0, self.reserve_1)
+        }
+
+        #[ink(message)]
+        fn get_amount_out(
+            &self,
+            token_in: AccountId,
+            amount_in: Balance,
+        ) -> Result<Balance, AmmPairError> {
+            let (reserve_in, reserve_out) = self._reserves_of(&token_in)?;
+            if reserve_in == 0 || reserve_out == 0 {
+                return Err(AmmPairError::InsufficientLiquidity);
+            }
+            let amount_in_with_fee = U256::from(amount_in) * U256::from(E6 - self.fee_e6);
+            let amount_out = amount_in_with_fee * U256::from(reserve_out)
+                / (U256::from(reserve_in) * U256::from(E6) + amount_in_with_fee);
+            Ok(amount_out.as_u128())
+        }
+
+        #[ink(message)]
+        fn add_liquidity(
+            &mut self,
+            amount_0: Balance,
+            amount_1: Balance,
+        ) -> Result<(), AmmPairError> {
+            let caller = self.env().caller();
+            let this = self.env().account_id();
+            self._transfer_from(self.token_0, caller, this, amount_0)?;
+            self._transfer_from(self.token_1, caller, this, amount_1)?;
+            self.reserve_0 += amount_0;
+            self.reserve_1 += amount_1;
+            Ok(())
+        }
+
+        #[ink(message)]
+        fn swap(
+            &mut self,
+            token_in: AccountId,
+            amount_in: Balance,
+            min_amount_out: Balance,
+            to: AccountId,
+        ) -> Result<Balance, AmmPairError> {
+            let amount_out = self.get_amount_out(token_in, amount_in)?;
+            if amount_out < min_amount_out {
+                return Err(AmmPairError::InsufficientOutputAmount);
+            }
+            let caller = self.env().caller();
+            let this = self.env().account_id();
+            let token_out = if token_in == self.token_0 {
+                self.reserve_0 += amount_in;
+                self.reserve_1 -= amount_out;
+                self.token_1
+            } else {
+                self.reserve_1 += amount_in;
+                self.reserve_0 -= amount_out;
+                self.token_0
+            };
+            self._transfer_from(token_in, caller, this, amount_in)?;
+            self._transfer(token_out, to, amount_out)?;
+
+            self.env().emit_event(Swap {
+                sender: caller,
+                token_in,
+                amount_in,
+                amount_out,
+                to,
+            });
+            Ok(amount_out)
+        }
+    }
+
+    impl AmmPairMock {
+        #[ink(constructor)]
+        pub fn new(token_0: AccountId, token_1: AccountId, fee_e6: u128) -> Self {
+            ink_lang::codegen::initialize_contract(|instance: &mut Self| {
+                instance.token_0 = token_0;
+                instance.token_1 = token_1;
+                instance.fee_e6 = fee_e6;
+            })
+        }
+
+        /// returns (reserve_in, reserve_out) for `token_in`
+        fn _reserves_of(&self, token_in: &AccountId) -> Result<(Balance, Balance), AmmPairError> {
+            if *token_in == self.token_0 {
+                Ok((self.reserve_0, self.reserve_1))
+            } else if *token_in == self.token_1 {
+                Ok((self.reserve_1, self.reserve_0))
+            } else {
+                Err(AmmPairError::UnknownToken)
+            }
+        }
+
+        // tokens call before_received on the pair, so reentry has to be allowed
+        fn _transfer_from(
+            &self,
+            token: AccountId,
+            from: AccountId,
+            to: AccountId,
+            amount: Balance,
+        ) -> Result<(), AmmPairError> {
+            PSP22Ref::transfer_from_builder(&token, from, to, amount, Vec::<u8>::new())
+                .call_flags(CallFlags::default().set_allow_reentry(true))
+                .fire()
+                .unwrap()?;
+            Ok(())
+        }
+
+        fn _transfer(
+            &self,
+            token: AccountId,
+            to: AccountId,
+            amount: Balance,
+        ) -> Result<(), AmmPairError> {
+            PSP22Ref::transfer_builder(&token, to, amount, Vec::<u8>::new())
+                .call_flags(CallFlags::default().set_allow_reentry(true))
+                .fire()
+                .unwrap()?;
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,140 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+#![feature(min_specialization)]
+
+/// receiver that buys the other token of `buy_pair` with the borrowed asset, sells it on `sell_pair` and repays the loan from the proceeds.
+/// `data` is SCALE encoded `ArbitrageParams`, it can be built with `encode_params` query.
+/// Only flash loans initiated by the owner are accepted. The profit stays in the contract and can be withdrawn by the owner.
+#[openbrush::contract]
+pub mod arbitrage_receiver {
+    use flash_loan::impls::flash_loan_receiver::*;
+    use flash_loan::traits::amm_pair::{AmmPairError, AmmPairRef};
+    use flash_loan::traits::flash_loan::LoanId;
+    use ink_env::CallFlags;
+    use ink_lang::codegen::{EmitEvent, Env};
+    use ink_prelude::{format, string::String, vec::Vec};
+    use ink_storage::traits::SpreadAllocate;
+    use openbrush::contracts::ownable::*;
+    use openbrush::contracts::traits::psp22::PSP22Ref;
+    use openbrush::traits::Storage;
+    use scale::{Decode, Encode};
+
+    #[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct ArbitrageParams {
+        /// pair where the other token is bought with the borrowed asset
+        pub buy_pair: AccountId,
+        /// pair where the other token is sold back for the borrowed asset
+        pub sell_pair: AccountId,
+        /// the operation fails if the profit after fee is less
+        pub min_profit: Balance,
+    }
+
+    #[ink(storage)]
+    #[derive(Default, SpreadAllocate, Storage)]
+    pub struct ArbitrageReceiver {
+        #[storage_field]
+        ownable: ownable::Data,
+        #[storage_field]
+        receiver: flash_loan_receiver::Data,
+    }
+
+    #[ink(event)]
+    pub struct Arbitrage {
+        #[ink(topic)]
+        asset: AccountId,
+        amount: Balance,
+        profit: Balance,
+    }
+
+    impl Ownable for ArbitrageReceiver {}
+
+    impl FlashLoanReceiver for ArbitrageReceiver {}
+
+    impl FlashLoanReceiverAdmin for ArbitrageReceiver {}
+
+    impl FlashLoanReceiverInternal for ArbitrageReceiver {
+        fn _is_authorized_initiator(&self, initiator: &AccountId) -> bool {
+            *initiator == self.owner()
+        }
+
+        fn _on_flash_loan(
+            &mut self,
+            _loan_id: LoanId,
+            assets: &Vec<AccountId>,
+            amounts: &Vec<Balance>,
+            fees: &Vec<Balance>,
+            data: &Vec<u8>,
+        ) -> Result<(), FlashLoanReceiverError> {
+            if assets.len() != 1 {
+                return Err(FlashLoanReceiverError::Custom(String::from(
+                    "Exactly one asset expected",
+                )));
+            }
+            let params = ArbitrageParams::decode(&mut &data[..]).map_err(|_| {
+                FlashLoanReceiverError::Custom(String::from("Invalid arbitrage params"))
+            })?;
+            let (asset, amount, fee) = (assets[0], amounts[0], fees[0]);
+            let this = self.env().account_id();
+            let balance_before = PSP22Ref::balance_of(&asset, this);
+
+            let (token_0, token_1) = AmmPairRef::tokens(&params.buy_pair);
+            let mid_token = if token_0 == asset { token_1 } else { token_0 };
+            let bought = self._swap(&params.buy_pair, asset, amount)?;
+            self._swap(&params.sell_pair, mid_token, bought)?;
+
+            let balance_after = PSP22Ref::balance_of(&asset, this);
+            let profit = balance_after
+                .checked_sub(balance_before)
+                .and_then(|gain| gain.checked_sub(fee))
+                .unwrap_or_default();
+            if balance_after < balance_before + fee + params.min_profit {
+                return Err(FlashLoanReceiverError::Custom(format!(
+                    "Profit {} is less than {}",
+                    profit, params.min_profit
+                )));
+            }
+
+            self.env().emit_event(Arbitrage {
+                asset,
+                amount,
+                profit,
+            });
+            Ok(())
+        }
+    }
+
+    impl ArbitrageReceiver {
+        #[ink(constructor)]
+        pub fn new() -> Self {
+            ink_lang::codegen::initialize_contract(|instance: &mut Self| {
+                let caller = instance.env().caller();
+                instance._init_with_owner(caller);
+            })
+        }
+
+        /// returns `params` encoded as `data` for flash_loan
+        #[ink(message)]
+        pub fn encode_params(&self, params: ArbitrageParams) -> Vec<u8> {
+            params.encode()
+        }
+
+        /// swaps `amount_in` of `token_in` on `pair`. Returns the received amount.
+        fn _swap(
+            &mut self,
+            pair: &AccountId,
+            token_in: AccountId,
+            amount_in: Balance,
+        ) -> Result<Balance, FlashLoanReceiverError> {
+            let this = self.env().account_id();
+            PSP22Ref::approve(&token_in, *pair, amount_in)?;
+            // the pair transfers the output to this contract, so reentry has to be allowed
+            AmmPairRef::swap_builder(pair, token_in, amount_in, 0, this)
+                .call_flags(CallFlags::default().set_allow_reentry(true))
+                .fire()
+                .unwrap()
+                .map_err(|error: AmmPairError| {
+                    FlashLoanReceiverError::Custom(format!("Swap failed: {:?}", error))
+                })
+        }
+    }
+}
@@ -0,0 +1,82 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+#![feature(min_specialization)]
+
+/// lender of assets other than PSP22 tokens (native currency, PSP34, PSP37 and pallet-assets), each asset account is mapped to the asset it stands for with `set_asset_spec`.
+/// Example of wiring `FlashLoanInternal::_loanable_asset`, charges no fees.
+#[openbrush::contract]
+pub mod asset_lender_mock {
+    use flash_loan::impls::{flash_loan::*, loanable_asset::Asset};
+    use flash_loan::traits::{flash_loan::*, loanable_asset::AssetSpec};
+    use ink_lang::codegen::{EmitEvent, Env};
+    use ink_storage::traits::SpreadAllocate;
+    use openbrush::storage::Mapping;
+    use openbrush::traits::Storage;
+
+    #[ink(storage)]
+    #[derive(Default, SpreadAllocate, Storage)]
+    pub struct AssetLenderMock {
+        #[storage_field]
+        flash_loan: flash_loan::impls::flash_loan::Data,
+
+        /// assets without a spec are PSP22 tokens
+        specs: Mapping<AccountId, AssetSpec>,
+    }
+
+    #[ink(event)]
+    pub struct FlashLoanEvent {
+        #[ink(topic)]
+        loan_id: LoanId,
+        #[ink(topic)]
+        receiver: AccountId,
+        #[ink(topic)]
+        asset: AccountId,
+        amount: Balance,
+        fee: Balance,
+    }
+
+    impl FlashLoanEventEmit for AssetLenderMock {
+        fn _emit_flash_loan_event(
+            &mut self,
+            loan_id: LoanId,
+            receiver: AccountId,
+            asset: AccountId,
+            amount: Balance,
+            fee: Balance,
+        ) {
+            self.env().emit_event(FlashLoanEvent {
+                loan_id,
+                receiver,
+                asset,
+                amount,
+                fee,
+            });
+        }
+    }
+
+    impl FlashLoanInternal for AssetLenderMock {
+        fn _loanable_asset(&self, asset: &AccountId) -> Result<Asset, FlashLoanError> {
+            Ok(Asset::from_spec(
+                *asset,
+                self.specs.get(asset).unwrap_or(AssetSpec::PSP22),
+            ))
+        }
+    }
+
+    impl FlashLoan for AssetLenderMock {}
+
+    impl AssetLenderMock {
+        #[ink(constructor)]
+        pub fn new() -> Self {
+            ink_lang::codegen::initialize_contract(|_instance: &mut Self| {})
+        }
+
+        #[ink(message)]
+        pub fn set_asset_spec(&mut self, asset: AccountId, spec: AssetSpec) {
+            self.specs.insert(&asset, &spec);
+        }
+
+        /// native currency lent by the contract is transferred with this message
+        #[ink(message, payable)]
+        pub fn fund(&mut self) {}
+    }
+}
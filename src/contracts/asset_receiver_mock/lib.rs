@@ -0,0 +1,86 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+#![feature(min_specialization)]
+
+/// receiver repaying assets other than PSP22 tokens, each asset account is mapped to the asset it stands for with `set_asset_spec`
+#[openbrush::contract]
+pub mod asset_receiver_mock {
+    use flash_loan::impls::pallet_assets::PalletAssets;
+    use flash_loan::traits::{
+        flash_loan::LoanId, flash_loan_receiver::*, loanable_asset::AssetSpec,
+    };
+    use ink_lang::codegen::Env;
+    use ink_prelude::{string::String, vec::Vec};
+    use ink_storage::traits::SpreadAllocate;
+    use openbrush::contracts::traits::{psp22::PSP22Ref, psp34::PSP34Ref, psp35::PSP35Ref};
+    use openbrush::storage::Mapping;
+    use openbrush::traits::Storage;
+
+    #[ink(storage)]
+    #[derive(Default, SpreadAllocate, Storage)]
+    pub struct AssetReceiverMock {
+        /// assets without a spec are PSP22 tokens
+        specs: Mapping<AccountId, AssetSpec>,
+        /// the loan is kept instead of being repaid
+        keep_loan: bool,
+    }
+
+    impl FlashLoanReceiver for AssetReceiverMock {
+        /// repays `amount + fee` of every asset, fees have to be held by the receiver
+        #[ink(message)]
+        fn execute_operation(
+            &mut self,
+            _loan_id: LoanId,
+            _initiator: AccountId,
+            assets: Vec<AccountId>,
+            amounts: Vec<u128>,
+            fees: Vec<u128>,
+            _data: Vec<u8>,
+        ) -> Result<(), FlashLoanReceiverError> {
+            if self.keep_loan {
+                return Ok(());
+            }
+            let lender = self.env().caller();
+            for i in 0..assets.len() {
+                let to_repay = amounts[i] + fees[i];
+                match self.specs.get(&assets[i]).unwrap_or(AssetSpec::PSP22) {
+                    AssetSpec::PSP22 => PSP22Ref::approve(&assets[i], lender, to_repay)?,
+                    AssetSpec::Native => self.env().transfer(lender, to_repay).map_err(|_| {
+                        FlashLoanReceiverError::Custom(String::from("NativeTransferFailed"))
+                    })?,
+                    AssetSpec::PSP34(id) => PSP34Ref::approve(&assets[i], lender, Some(id), true)
+                        .map_err(|_| {
+                        FlashLoanReceiverError::Custom(String::from("PSP34Error"))
+                    })?,
+                    AssetSpec::PSP37(id) => {
+                        PSP35Ref::approve(&assets[i], lender, Some(id), to_repay).map_err(|_| {
+                            FlashLoanReceiverError::Custom(String::from("PSP37Error"))
+                        })?
+                    }
+                    AssetSpec::PalletAssets(asset_id) => {
+                        PalletAssets::approve_transfer(asset_id, lender, to_repay).map_err(
+                            |_| FlashLoanReceiverError::Custom(String::from("PalletAssetsError")),
+                        )?
+                    }
+                }
+            }
+            Ok(())
+        }
+    }
+
+    impl AssetReceiverMock {
+        #[ink(constructor)]
+        pub fn new() -> Self {
+            ink_lang::codegen::initialize_contract(|_instance: &mut Self| {})
+        }
+
+        #[ink(message)]
+        pub fn set_asset_spec(&mut self, asset: AccountId, spec: AssetSpec) {
+            self.specs.insert(&asset, &spec);
+        }
+
+        #[ink(message)]
+        pub fn set_keep_loan(&mut self, keep_loan: bool) {
+            self.keep_loan = keep_loan;
+        }
+    }
+}
@@ -0,0 +1,57 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+#![feature(min_specialization)]
+
+/// fee oracle quoting proportional fees set with `set_fee_e6`, used to check how lenders handle fee oracles
+#[openbrush::contract]
+pub mod fee_oracle_mock {
+    use flash_loan::traits::fee_oracle::*;
+    use ink_prelude::{string::String, vec::Vec};
+    use ink_storage::traits::SpreadAllocate;
+    use openbrush::traits::Storage;
+
+    #[ink(storage)]
+    #[derive(Default, SpreadAllocate, Storage)]
+    pub struct FeeOracleMock {
+        fee_e6: u128,
+        /// quote_fees returns an error if set
+        fail: bool,
+    }
+
+    impl FeeOracle for FeeOracleMock {
+        #[ink(message)]
+        fn quote_fees(
+            &self,
+            _caller: AccountId,
+            _receiver: AccountId,
+            _assets: Vec<AccountId>,
+            amounts: Vec<Balance>,
+        ) -> Result<Vec<Balance>, FeeOracleError> {
+            if self.fail {
+                return Err(FeeOracleError::Custom(String::from("Failed")));
+            }
+            Ok(amounts
+                .iter()
+                .map(|amount| amount * self.fee_e6 / 1_000_000)
+                .collect())
+        }
+    }
+
+    impl FeeOracleMock {
+        #[ink(constructor)]
+        pub fn new(fee_e6: u128) -> Self {
+            ink_lang::codegen::initialize_contract(|instance: &mut Self| {
+                instance.fee_e6 = fee_e6;
+            })
+        }
+
+        #[ink(message)]
+        pub fn set_fee_e6(&mut self, fee_e6: u128) {
+            self.fee_e6 = fee_e6;
+        }
+
+        #[ink(message)]
+        pub fn set_fail(&mut self, fail: bool) {
+            self.fail = fail;
+        }
+    }
+}
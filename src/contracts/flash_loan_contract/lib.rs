@@ -5,15 +5,23 @@ use openbrush::contracts::{ownable::OwnableError, psp22::PSP22Error};
 
 #[openbrush::contract]
 pub mod flash_loan_contract {
+    use flash_loan::impls::asset_registry::*;
+    use flash_loan::impls::circuit_breaker::*;
+    use flash_loan::impls::fee_oracle::*;
     use flash_loan::impls::flash_loan::*;
+    use flash_loan::impls::flash_loan_policy::*;
+    use flash_loan::impls::loan_stats::*;
+    use flash_loan::impls::reconciliation::*;
+    use flash_loan::impls::voucher::*;
+    use flash_loan::storage::{EnumerableMap, EnumerableSet};
     use flash_loan::traits::flash_loan::{FlashLoanError, *};
+    use flash_loan::traits::loanable_asset::TokenQuirkPolicy;
     use ink_lang::codegen::{EmitEvent, Env};
     use ink_prelude::{string::*, vec, vec::Vec};
     use ink_storage::traits::SpreadAllocate;
     use openbrush::contracts::ownable::*;
     use openbrush::contracts::psp22::{PSP22Error, *};
     use openbrush::modifiers;
-    use openbrush::storage::Mapping;
     use openbrush::traits::Storage;
 
     #[ink(storage)]
@@ -21,15 +29,31 @@ pub mod flash_loan_contract {
     pub struct FlashLoanContract {
         #[storage_field]
         ownable: ownable::Data,
+        #[storage_field]
+        registry: asset_registry::Data,
+        #[storage_field]
+        flash_loan: flash_loan::impls::flash_loan::Data,
+        #[storage_field]
+        breaker: circuit_breaker::Data,
+        #[storage_field]
+        reconciliation: reconciliation::Data,
+        #[storage_field]
+        stats: loan_stats::Data,
+        #[storage_field]
+        policy: flash_loan_policy::Data,
+        #[storage_field]
+        fee_oracle: fee_oracle::Data,
+        #[storage_field]
+        vouchers: voucher::Data,
 
-        fees_e6: Mapping<AccountId, u128>,
-        allowed: Mapping<AccountId, bool>,
-        free: Mapping<AccountId, bool>,
-        earned: Mapping<AccountId, Balance>,
+        free: EnumerableSet<AccountId>,
+        earned: EnumerableMap<AccountId, Balance>,
     }
 
     #[ink(event)]
     pub struct FlashLoanEvent {
+        #[ink(topic)]
+        loan_id: LoanId,
         #[ink(topic)]
         receiver: AccountId,
         #[ink(topic)]
@@ -38,17 +62,196 @@ pub mod flash_loan_contract {
         fee: Balance,
     }
 
+    #[ink(event)]
+    pub struct AssetListed {
+        #[ink(topic)]
+        asset: AccountId,
+        info: AssetInfo,
+    }
+
+    #[ink(event)]
+    pub struct AssetDelisted {
+        #[ink(topic)]
+        asset: AccountId,
+    }
+
+    #[ink(event)]
+    pub struct AssetPaused {
+        #[ink(topic)]
+        asset: AccountId,
+        anomalies: Balance,
+    }
+
+    #[ink(event)]
+    pub struct AssetUnpaused {
+        #[ink(topic)]
+        asset: AccountId,
+    }
+
+    #[ink(event)]
+    pub struct Deposited {
+        #[ink(topic)]
+        asset: AccountId,
+        #[ink(topic)]
+        from: AccountId,
+        amount: Balance,
+    }
+
+    #[ink(event)]
+    pub struct Skimmed {
+        #[ink(topic)]
+        asset: AccountId,
+        #[ink(topic)]
+        treasury: AccountId,
+        amount: Balance,
+    }
+
+    #[ink(event)]
+    pub struct Rescued {
+        #[ink(topic)]
+        token: AccountId,
+        #[ink(topic)]
+        to: AccountId,
+        amount: Balance,
+    }
+
+    #[ink(event)]
+    pub struct PolicyContractSet {
+        #[ink(topic)]
+        policy_contract: Option<AccountId>,
+        gas_limit: u64,
+    }
+
+    #[ink(event)]
+    pub struct FeeOracleSet {
+        config: Option<FeeOracleConfig>,
+    }
+
     impl Ownable for FlashLoanContract {}
 
+    impl AssetRegistry for FlashLoanContract {}
+
+    impl AssetRegistryEvents for FlashLoanContract {
+        fn _emit_asset_listed_event(&self, asset: AccountId, info: AssetInfo) {
+            self.env().emit_event(AssetListed { asset, info });
+        }
+
+        fn _emit_asset_delisted_event(&self, asset: AccountId) {
+            self.env().emit_event(AssetDelisted { asset });
+        }
+    }
+
+    impl CircuitBreaker for FlashLoanContract {}
+
+    impl CircuitBreakerEvents for FlashLoanContract {
+        fn _emit_asset_paused_event(&self, asset: AccountId, anomalies: Balance) {
+            self.env().emit_event(AssetPaused { asset, anomalies });
+        }
+
+        fn _emit_asset_unpaused_event(&self, asset: AccountId) {
+            self.env().emit_event(AssetUnpaused { asset });
+        }
+    }
+
+    impl FeeOracleSettings for FlashLoanContract {}
+
+    impl FeeOracleEvents for FlashLoanContract {
+        fn _emit_fee_oracle_set_event(&self, config: Option<FeeOracleConfig>) {
+            self.env().emit_event(FeeOracleSet { config });
+        }
+    }
+
+    impl LoanStats for FlashLoanContract {}
+
+    impl PolicyChain for FlashLoanContract {}
+
+    impl FlashLoanPolicyInternal for FlashLoanContract {
+        /// the caller of an active voucher is allowlisted for its loan
+        fn _is_allowlisted(&self, account: &AccountId) -> bool {
+            self.policy.allowlist.contains(account)
+                || (*account == self.env().caller() && self._voucher_allows())
+        }
+
+        /// the registry cap applies together with the one set with `set_cap`
+        fn _cap(&self, asset: &AccountId) -> Option<Balance> {
+            let max_loan = self
+                .registry
+                .assets
+                .get(asset)
+                .and_then(|info| info.config.max_loan);
+            match (self.policy.caps.get(asset), max_loan) {
+                (Some(cap), Some(max_loan)) => Some(cap.min(max_loan)),
+                (cap, max_loan) => cap.or(max_loan),
+            }
+        }
+
+        /// assets paused by the circuit breaker are paused too
+        fn _is_paused(&self, asset: &AccountId) -> bool {
+            self.policy.loans_paused || self._ensure_not_paused(asset).is_err()
+        }
+    }
+
+    impl PolicyChainEvents for FlashLoanContract {
+        fn _emit_policy_contract_set_event(
+            &self,
+            policy_contract: Option<AccountId>,
+            gas_limit: u64,
+        ) {
+            self.env().emit_event(PolicyContractSet {
+                policy_contract,
+                gas_limit,
+            });
+        }
+    }
+
+    impl Reconciliation for FlashLoanContract {}
+
+    impl Vouchers for FlashLoanContract {}
+
+    impl ReconciliationEvents for FlashLoanContract {
+        fn _emit_deposited_event(&self, asset: AccountId, from: AccountId, amount: Balance) {
+            self.env().emit_event(Deposited {
+                asset,
+                from,
+                amount,
+            });
+        }
+
+        fn _emit_skimmed_event(&self, asset: AccountId, treasury: AccountId, amount: Balance) {
+            self.env().emit_event(Skimmed {
+                asset,
+                treasury,
+                amount,
+            });
+        }
+
+        fn _emit_rescued_event(&self, token: AccountId, to: AccountId, amount: Balance) {
+            self.env().emit_event(Rescued { token, to, amount });
+        }
+    }
+
+    impl ReconciliationInternal for FlashLoanContract {
+        /// assets listed in the registry can not be rescued
+        fn _can_rescue(&self, token: &AccountId) -> bool {
+            !self.registry.assets.contains(token)
+        }
+
+        fn _on_balance_changed(&mut self, asset: &AccountId) {
+            self._sync_balance(asset);
+        }
+    }
+
     impl FlashLoanEventEmit for FlashLoanContract {
         fn _emit_flash_loan_event(
             &mut self,
+            loan_id: LoanId,
             receiver: AccountId,
             asset: AccountId,
             amount: Balance,
             fee: Balance,
         ) {
             self.env().emit_event(FlashLoanEvent {
+                loan_id,
                 receiver,
                 asset,
                 amount,
@@ -58,17 +261,39 @@ pub mod flash_loan_contract {
     }
 
     impl FlashLoanInternal for FlashLoanContract {
-        fn _before_flash_loan(
+        /// a drop of a listed asset found by the check pauses it. An error would revert the pause, so the loan
+        /// is halted instead. During a nested loan the lent amounts would be counted as a drop
+        fn _halt_loan(&mut self, assets: &Vec<AccountId>) -> bool {
+            if self.is_in_flash_loan() {
+                return false;
+            }
+            let mut halted = false;
+            for asset in assets.iter() {
+                if self._supported_asset(asset, 0).is_err() {
+                    continue;
+                }
+                let paused = self._ensure_not_paused(asset).is_err();
+                self._check_balance(asset);
+                halted |= !paused && self._ensure_not_paused(asset).is_err();
+            }
+            halted
+        }
+
+        /// fees of free receivers are not quoted, so the registry is checked here. Allowlist, caps and pauses are
+        /// checked by the policy chain
+        fn _pre_check_loan(&mut self, loan: &LoanRequest) -> Result<(), FlashLoanError> {
+            for asset in loan.assets.iter() {
+                self._supported_asset(asset, 0)?;
+            }
+            self._run_pre_checks(loan)
+        }
+
+        fn _post_check_loan(
             &mut self,
-            receiver: &AccountId,
-            assets: &Vec<AccountId>,
-            amounts: &Vec<Balance>,
-            data: &mut Vec<u8>,
+            loan: &LoanRequest,
+            fees: &Vec<Balance>,
         ) -> Result<(), FlashLoanError> {
-            if !self.allowed.get(&self.env().caller()).unwrap_or_default() {
-                return Err(FlashLoanError::Custom(String::from("NotAllowed")));
-            }
-            Ok(())
+            self._run_post_checks(loan, fees)
         }
 
         fn _calculate_fees(
@@ -78,30 +303,77 @@ pub mod flash_loan_contract {
             amounts: &Vec<Balance>,
             data: &mut Vec<u8>,
         ) -> Result<Vec<Balance>, FlashLoanError> {
-            let mut fees: Vec<Balance> = vec![0; assets.len()];
-            if self.free.get(receiver).unwrap_or_default() {
+            if self.free.contains(receiver) {
+                return Ok(vec![0; assets.len()]);
+            }
+            if let Some(fees) = self._voucher_fees(amounts) {
                 return Ok(fees);
             }
+            if let Some(fees) = self._oracle_fees(receiver, assets, amounts) {
+                return Ok(fees);
+            }
+            FlashLoanInternal::_quote_fees(self, assets, amounts)
+        }
+
+        fn _quote_fees(
+            &self,
+            assets: &Vec<AccountId>,
+            amounts: &Vec<Balance>,
+        ) -> Result<Vec<Balance>, FlashLoanError> {
+            let mut fees: Vec<Balance> = vec![0; assets.len()];
             for i in 0..assets.len() {
-                let fee =
-                    amounts[i] * self.fees_e6.get(&assets[i]).unwrap_or_default() / 1_000_000_u128;
-                fees[i] = fee;
+                fees[i] = self._registry_fee(&assets[i], amounts[i])?;
             }
             Ok(fees)
         }
 
+        fn _max_flash_loan(&self, asset: &AccountId) -> Balance {
+            if self._supported_asset(asset, 0).is_err()
+                || FlashLoanPolicyInternal::_is_paused(self, asset)
+            {
+                return 0;
+            }
+            self._asset_liquidity(asset)
+                .min(FlashLoanPolicyInternal::_cap(self, asset).unwrap_or(Balance::MAX))
+        }
+
+        fn _token_quirk_policy(&self, asset: &AccountId) -> TokenQuirkPolicy {
+            self.registry
+                .assets
+                .get(asset)
+                .map(|info| info.config.quirk_policy)
+                .unwrap_or(TokenQuirkPolicy::Reject)
+        }
+
         fn _after_flash_loan(
             &mut self,
+            _loan_id: LoanId,
             receiver: &AccountId,
             assets: &Vec<AccountId>,
             amounts: &Vec<Balance>,
             fees: &Vec<Balance>,
             data: &Vec<u8>,
         ) -> Result<(), FlashLoanError> {
-            for i in 0..assets.len() {
-                let earned = self.earned.get(&assets[i]).unwrap_or_default();
-                self.earned.insert(&assets[i], &(earned + fees[i]));
+            if !self.is_in_flash_loan() {
+                for asset in assets.iter() {
+                    self._sync_balance(asset);
+                }
             }
+            self._record_flash_loan(&self.env().caller(), receiver, assets, amounts, fees);
+            Ok(())
+        }
+
+        /// the fee is accounted once the asset is pulled back, `fee` is the one that arrived like in receipts and stats
+        fn _after_repay_asset(
+            &mut self,
+            _index: usize,
+            asset: &AccountId,
+            _amount: Balance,
+            fee: Balance,
+        ) -> Result<(), FlashLoanError> {
+            let earned = self.earned.get(asset).unwrap_or_default();
+            self.earned.insert(asset, &(earned + fee));
+            self._track_fee(asset, fee);
             Ok(())
         }
     }
@@ -109,6 +381,9 @@ pub mod flash_loan_contract {
 
     impl FlashLoanContract {
         #[ink(constructor)]
+        /// assets in `fees_e6` are listed with proportional fees, `allowed` and `free` accounts are allowlisted,
+        /// loans to `free` receivers are charged no fee.
+        /// The policy chain is set to [Pause, Allowlist, Caps]
         pub fn new(
             fees_e6: Vec<(AccountId, u128)>,
             allowed: Vec<AccountId>,
@@ -118,36 +393,66 @@ pub mod flash_loan_contract {
                 let caller = instance.env().caller();
                 instance._init_with_owner(caller);
 
-                for asset_and_fee in fees_e6 {
-                    instance.fees_e6.insert(&asset_and_fee.0, &asset_and_fee.1);
+                for (asset, fee_e6) in fees_e6 {
+                    let info = instance._list_asset(
+                        &asset,
+                        AssetConfig {
+                            enabled: true,
+                            fee_model: FeeModel::Proportional { fee_e6 },
+                            max_loan: None,
+                            quirk_policy: TokenQuirkPolicy::Reject,
+                        },
+                    );
+                    instance._emit_asset_listed_event(asset, info);
                 }
 
                 for account in allowed {
-                    instance.allowed.insert(&account, &true);
+                    instance.policy.allowlist.insert(&account);
                 }
 
+                // free accounts are allowed too and their loans are not charged
                 for account in free {
-                    instance.allowed.insert(&account, &true);
+                    instance.policy.allowlist.insert(&account);
+                    instance.free.insert(&account);
                 }
+
+                instance._set_policies(&vec![
+                    PolicyModule::Pause,
+                    PolicyModule::Allowlist,
+                    PolicyModule::Caps,
+                ]);
             })
         }
 
         #[ink(message)]
-        #[modifiers(only_owner)]
+        #[modifiers(only_owner, when_not_in_flash_loan)]
         pub fn withdraw(
             &mut self,
             token: AccountId,
             to: AccountId,
             amount: Option<Balance>,
         ) -> Result<(), FlashLoanContractError> {
-            let balance = PSP22Ref::balance_of(&token, self.env().caller());
-            if amount.is_some() {
-                PSP22Ref::transfer(&token, to, amount.unwrap(), vec![])?;
-            } else {
-                PSP22Ref::transfer(&token, to, balance, vec![])?;
-            }
+            let amount = match amount {
+                Some(amount) => amount,
+                None => PSP22Ref::balance_of(&token, self.env().account_id()),
+            };
+            PSP22Ref::transfer(&token, to, amount, vec![])?;
+            self._track_withdrawal(&token, amount);
+            self._sync_balance(&token);
             Ok(())
         }
+
+        /// returns up to `limit` receivers that pay no fees starting from index `offset`
+        #[ink(message)]
+        pub fn list_free(&self, offset: u32, limit: u32) -> Vec<AccountId> {
+            self.free.page(offset, limit)
+        }
+
+        /// returns up to `limit` assets with fees earned on them starting from index `offset`
+        #[ink(message)]
+        pub fn list_earned(&self, offset: u32, limit: u32) -> Vec<(AccountId, Balance)> {
+            self.earned.page(offset, limit)
+        }
     }
     #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
     #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
@@ -155,6 +460,7 @@ pub mod flash_loan_contract {
         Custom(String),
         PSP22Error(PSP22Error),
         OwnableError(OwnableError),
+        FlashLoanError(FlashLoanError),
     }
 
     impl From<PSP22Error> for FlashLoanContractError {
@@ -168,4 +474,10 @@ pub mod flash_loan_contract {
             FlashLoanContractError::OwnableError(error)
         }
     }
+
+    impl From<FlashLoanError> for FlashLoanContractError {
+        fn from(error: FlashLoanError) -> Self {
+            FlashLoanContractError::FlashLoanError(error)
+        }
+    }
 }
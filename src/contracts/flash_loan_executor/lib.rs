@@ -0,0 +1,158 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+#![feature(min_specialization)]
+
+/// receiver that executes a list of cross contract calls passed in `data` of the flash loan and then repays it.
+/// `data` is SCALE encoded `ExecutorEnvelope`, it can be built with `encode_envelope` query.
+/// Only flash loans initiated by the owner are accepted.
+#[openbrush::contract]
+pub mod flash_loan_executor {
+    use flash_loan::impls::flash_loan_receiver::*;
+    use flash_loan::raw_call::{EncodedArgs, RawOutput};
+    use flash_loan::traits::flash_loan::LoanId;
+    use ink_env::call::{build_call, Call, ExecutionInput, Selector};
+    use ink_env::{CallFlags, DefaultEnvironment};
+    use ink_prelude::{format, string::String, vec::Vec};
+    use ink_storage::traits::SpreadAllocate;
+    use openbrush::contracts::ownable::*;
+    use openbrush::contracts::traits::psp22::PSP22Ref;
+    use openbrush::traits::Storage;
+    use scale::{Decode, Encode};
+
+    /// versioned list of calls
+    #[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum ExecutorEnvelope {
+        V1(Vec<ExecutorCall>),
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct ExecutorCall {
+        pub callee: AccountId,
+        pub selector: [u8; 4],
+        /// SCALE encoded arguments of the message
+        pub input: Vec<u8>,
+        pub transferred_value: Balance,
+        /// 0 means all remaining gas
+        pub gas_limit: u64,
+        /// if true the message is expected to return Result and the call fails when it returns Err
+        pub check_result: bool,
+        /// checked after the call
+        pub balance_deltas: Vec<BalanceDelta>,
+    }
+
+    /// the PSP22 `asset` balance of the executor must change at least by `min_delta` during the call
+    #[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct BalanceDelta {
+        pub asset: AccountId,
+        pub min_delta: i128,
+    }
+
+    #[ink(storage)]
+    #[derive(Default, SpreadAllocate, Storage)]
+    pub struct FlashLoanExecutor {
+        #[storage_field]
+        ownable: ownable::Data,
+        #[storage_field]
+        receiver: flash_loan_receiver::Data,
+    }
+
+    impl Ownable for FlashLoanExecutor {}
+
+    impl FlashLoanReceiver for FlashLoanExecutor {}
+
+    impl FlashLoanReceiverAdmin for FlashLoanExecutor {}
+
+    impl FlashLoanReceiverInternal for FlashLoanExecutor {
+        fn _is_authorized_initiator(&self, initiator: &AccountId) -> bool {
+            *initiator == self.owner()
+        }
+
+        fn _on_flash_loan(
+            &mut self,
+            _loan_id: LoanId,
+            _assets: &Vec<AccountId>,
+            _amounts: &Vec<Balance>,
+            _fees: &Vec<Balance>,
+            data: &Vec<u8>,
+        ) -> Result<(), FlashLoanReceiverError> {
+            let calls = match ExecutorEnvelope::decode(&mut &data[..]) {
+                Ok(ExecutorEnvelope::V1(calls)) => calls,
+                Err(_) => {
+                    return Err(FlashLoanReceiverError::Custom(String::from(
+                        "Unsupported envelope",
+                    )))
+                }
+            };
+            for (step, call) in calls.iter().enumerate() {
+                self._execute_call(step, call)?;
+            }
+            Ok(())
+        }
+    }
+
+    impl FlashLoanExecutor {
+        #[ink(constructor)]
+        pub fn new() -> Self {
+            ink_lang::codegen::initialize_contract(|instance: &mut Self| {
+                let caller = instance.env().caller();
+                instance._init_with_owner(caller);
+            })
+        }
+
+        /// returns `calls` encoded as `data` for flash_loan
+        #[ink(message)]
+        pub fn encode_envelope(&self, calls: Vec<ExecutorCall>) -> Vec<u8> {
+            ExecutorEnvelope::V1(calls).encode()
+        }
+
+        fn _execute_call(
+            &mut self,
+            step: usize,
+            call: &ExecutorCall,
+        ) -> Result<(), FlashLoanReceiverError> {
+            let this = self.env().account_id();
+            let balances_before: Vec<Balance> = call
+                .balance_deltas
+                .iter()
+                .map(|delta| PSP22Ref::balance_of(&delta.asset, this))
+                .collect();
+
+            let output = build_call::<DefaultEnvironment>()
+                .call_type(
+                    Call::new()
+                        .callee(call.callee)
+                        .gas_limit(call.gas_limit)
+                        .transferred_value(call.transferred_value),
+                )
+                .exec_input(
+                    ExecutionInput::new(Selector::new(call.selector))
+                        .push_arg(EncodedArgs(call.input.clone())),
+                )
+                .returns::<RawOutput>()
+                .call_flags(CallFlags::default().set_allow_reentry(true))
+                .fire()
+                .map_err(|_| FlashLoanReceiverError::Custom(format!("Step {} failed", step)))?;
+            // Result::Err is encoded with 1 as the first byte
+            if call.check_result && output.0.first() == Some(&1) {
+                return Err(FlashLoanReceiverError::Custom(format!(
+                    "Step {} returned Err",
+                    step
+                )));
+            }
+
+            for (i, delta) in call.balance_deltas.iter().enumerate() {
+                let balance_after = PSP22Ref::balance_of(&delta.asset, this);
+                let change = balance_after as i128 - balances_before[i] as i128;
+                if change < delta.min_delta {
+                    return Err(FlashLoanReceiverError::Custom(format!(
+                        "Step {} balance delta {} is less than {}",
+                        step, change, delta.min_delta
+                    )));
+                }
+            }
+            Ok(())
+        }
+    }
+}
@@ -3,7 +3,11 @@
 
 #[openbrush::contract]
 pub mod flash_loan_receiver_mock {
-    use flash_loan::traits::flash_loan_receiver::{FlashLoanReceiverError, *};
+    use flash_loan::traits::{
+        flash_loan::LoanId,
+        flash_loan_receiver::{FlashLoanReceiverError, *},
+        wrapped_native::WrappedNativeRef,
+    };
     use ink_lang::codegen::{EmitEvent, Env};
     use ink_prelude::{format, vec::Vec};
     use ink_storage::traits::SpreadAllocate;
@@ -37,12 +41,15 @@ pub mod flash_loan_receiver_mock {
         fail_execute_operation: bool,
         simulate_balance_to_cover_fee: bool,
         custom_amount_to_approve: Option<Balance>,
+        unwrap_and_rewrap: bool,
     }
     impl FlashLoanReceiver for FlashLoanReceiverMock {
         #[ink(message)]
         #[allow(unused_variables)]
         fn execute_operation(
             &mut self,
+            loan_id: LoanId,
+            initiator: AccountId,
             assets: Vec<AccountId>,
             amounts: Vec<u128>,
             fees: Vec<u128>,
@@ -65,6 +72,20 @@ pub mod flash_loan_receiver_mock {
                     )));
                 }
 
+                if self.unwrap_and_rewrap {
+                    if WrappedNativeRef::withdraw(&assets[i], amounts[i]).is_err() {
+                        return Err(FlashLoanReceiverError::Custom(format!("Can't unwrap")));
+                    }
+                    if WrappedNativeRef::deposit_builder(&assets[i])
+                        .transferred_value(amounts[i])
+                        .fire()
+                        .unwrap()
+                        .is_err()
+                    {
+                        return Err(FlashLoanReceiverError::Custom(format!("Can't rewrap")));
+                    }
+                }
+
                 if self.simulate_balance_to_cover_fee {
                     if PSP22MintableRef::mint(&assets[i], self.env().account_id(), fees[i]).is_err()
                     {
@@ -101,6 +122,7 @@ pub mod flash_loan_receiver_mock {
                 instance.custom_amount_to_approve = None;
                 instance.fail_execute_operation = false;
                 instance.simulate_balance_to_cover_fee = true;
+                instance.unwrap_and_rewrap = false;
             })
         }
 
@@ -118,5 +140,11 @@ pub mod flash_loan_receiver_mock {
         pub fn set_simulate_balance_to_cover_fee(&mut self, simulate_balance_to_cover_fee: bool) {
             self.simulate_balance_to_cover_fee = simulate_balance_to_cover_fee;
         }
+
+        /// assets are treated as wrapped native tokens - each borrowed amount is withdrawn and deposited back
+        #[ink(message)]
+        pub fn set_unwrap_and_rewrap(&mut self, unwrap_and_rewrap: bool) {
+            self.unwrap_and_rewrap = unwrap_and_rewrap;
+        }
     }
 }
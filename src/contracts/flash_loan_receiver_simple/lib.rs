@@ -0,0 +1,93 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+#![feature(min_specialization)]
+
+/// a very simple usage example of the default FlashLoanReceiver. Fees are paid from the balance of the contract.
+#[openbrush::contract]
+pub mod flash_loan_receiver_simple {
+    use flash_loan::impls::flash_loan_receiver::*;
+    use flash_loan::traits::flash_loan::LoanId;
+    use ink_lang::codegen::{EmitEvent, Env};
+    use ink_prelude::vec::Vec;
+    use ink_storage::traits::SpreadAllocate;
+    use openbrush::contracts::ownable::*;
+    use openbrush::modifiers;
+    use openbrush::traits::Storage;
+
+    #[ink(storage)]
+    #[derive(Default, SpreadAllocate, Storage)]
+    pub struct FlashLoanReceiverSimple {
+        #[storage_field]
+        ownable: ownable::Data,
+        #[storage_field]
+        receiver: flash_loan_receiver::Data,
+    }
+
+    #[ink(event)]
+    pub struct FlashLoanReceived {
+        #[ink(topic)]
+        lender: AccountId,
+        #[ink(topic)]
+        loan_id: LoanId,
+        assets: Vec<AccountId>,
+        amounts: Vec<Balance>,
+        fees: Vec<Balance>,
+    }
+
+    impl Ownable for FlashLoanReceiverSimple {}
+
+    impl FlashLoanReceiver for FlashLoanReceiverSimple {}
+
+    impl FlashLoanReceiverInternal for FlashLoanReceiverSimple {
+        fn _on_flash_loan(
+            &mut self,
+            loan_id: LoanId,
+            assets: &Vec<AccountId>,
+            amounts: &Vec<Balance>,
+            fees: &Vec<Balance>,
+            _data: &Vec<u8>,
+        ) -> Result<(), FlashLoanReceiverError> {
+            self.env().emit_event(FlashLoanReceived {
+                lender: self.env().caller(),
+                loan_id,
+                assets: assets.clone(),
+                amounts: amounts.clone(),
+                fees: fees.clone(),
+            });
+            Ok(())
+        }
+    }
+
+    impl FlashLoanReceiverSimple {
+        #[ink(constructor)]
+        pub fn new() -> Self {
+            ink_lang::codegen::initialize_contract(|instance: &mut Self| {
+                let caller = instance.env().caller();
+                instance._init_with_owner(caller);
+                instance._set_authorized_initiator(&caller, true);
+            })
+        }
+
+        /// sets `lender` as trusted with repayment `mode`, None removes it
+        #[ink(message)]
+        #[modifiers(only_owner)]
+        pub fn set_trusted_lender(
+            &mut self,
+            lender: AccountId,
+            mode: Option<RepaymentMode>,
+        ) -> Result<(), OwnableError> {
+            self._set_trusted_lender(&lender, mode);
+            Ok(())
+        }
+
+        #[ink(message)]
+        #[modifiers(only_owner)]
+        pub fn set_authorized_initiator(
+            &mut self,
+            initiator: AccountId,
+            authorized: bool,
+        ) -> Result<(), OwnableError> {
+            self._set_authorized_initiator(&initiator, authorized);
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,442 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+#![feature(min_specialization)]
+
+/// splits a flash loan across registered lenders.
+/// A loan is taken as nested flash loans - the router borrows from the first lender, in its `execute_operation`
+/// borrows from the next one and so on. When all parts are borrowed the whole loan is sent to the receiver,
+/// then every lender is repaid with its own fee while nested calls return.
+#[openbrush::contract]
+pub mod flash_loan_router {
+    use flash_loan::impls::loanable_asset::PSP22Asset;
+    use flash_loan::traits::{
+        flash_loan::{FlashLoanError, *},
+        flash_loan_receiver::{FlashLoanReceiverError, *},
+        loanable_asset::LoanableAsset,
+    };
+    use ink_env::CallFlags;
+    use ink_lang::codegen::{EmitEvent, Env};
+    use ink_prelude::{format, string::String, vec, vec::Vec};
+    use ink_storage::traits::{PackedLayout, SpreadAllocate, SpreadLayout};
+    use openbrush::contracts::ownable::*;
+    use openbrush::contracts::traits::psp22::PSP22Ref;
+    use openbrush::modifiers;
+    use openbrush::traits::{Flush, Storage};
+
+    /// part of a loan taken from a single lender. Order in lists coresponds to order of list of the assets.
+    #[derive(
+        Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode, SpreadLayout, PackedLayout,
+    )]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+    )]
+    pub struct RouteLeg {
+        pub lender: AccountId,
+        pub amounts: Vec<Balance>,
+        pub fees: Vec<Balance>,
+    }
+
+    /// flash loan that is being executed by the router
+    #[derive(
+        Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode, SpreadLayout, PackedLayout,
+    )]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+    )]
+    pub struct RouterLoan {
+        loan_id: LoanId,
+        initiator: AccountId,
+        receiver: AccountId,
+        assets: Vec<AccountId>,
+        amounts: Vec<Balance>,
+        /// fees charged by lenders that already lent their part
+        fees: Vec<Balance>,
+        route: Vec<RouteLeg>,
+        /// index of the leg that will be borrowed next
+        next_leg: u32,
+        /// true once the loan was sent to the receiver
+        executing: bool,
+        data: Vec<u8>,
+    }
+
+    #[ink(storage)]
+    #[derive(Default, SpreadAllocate, Storage)]
+    pub struct FlashLoanRouter {
+        #[storage_field]
+        ownable: ownable::Data,
+
+        lenders: Vec<AccountId>,
+        loan: Option<RouterLoan>,
+        last_loan_id: LoanId,
+    }
+
+    #[ink(event)]
+    pub struct FlashLoanEvent {
+        #[ink(topic)]
+        loan_id: LoanId,
+        #[ink(topic)]
+        receiver: AccountId,
+        #[ink(topic)]
+        asset: AccountId,
+        amount: Balance,
+        fee: Balance,
+    }
+
+    impl Ownable for FlashLoanRouter {}
+
+    impl FlashLoan for FlashLoanRouter {
+        #[ink(message)]
+        fn flash_loan(
+            &mut self,
+            receiver: AccountId,
+            assets: Vec<AccountId>,
+            amounts: Vec<Balance>,
+            data: Vec<u8>,
+        ) -> Result<(), FlashLoanError> {
+            if !(assets.len() == amounts.len()) {
+                return Err(FlashLoanError::Parameters);
+            }
+            if self.loan.is_some() {
+                return Err(FlashLoanError::FlashLoanInProgress);
+            }
+            let route = self._route(&assets, &amounts)?;
+            self.last_loan_id += 1;
+            self.loan = Some(RouterLoan {
+                loan_id: self.last_loan_id,
+                initiator: self.env().caller(),
+                receiver,
+                assets: assets.clone(),
+                amounts: amounts.clone(),
+                fees: vec![0; assets.len()],
+                route,
+                next_leg: 0,
+                executing: false,
+                data,
+            });
+            let result = self._borrow_next_leg();
+            let loan = self.loan.take();
+            result?;
+            if let Some(loan) = loan {
+                for i in 0..assets.len() {
+                    self.env().emit_event(FlashLoanEvent {
+                        loan_id: loan.loan_id,
+                        receiver,
+                        asset: assets[i],
+                        amount: amounts[i],
+                        fee: loan.fees[i],
+                    });
+                }
+            }
+            Ok(())
+        }
+
+        #[ink(message)]
+        fn is_in_flash_loan(&self) -> bool {
+            self.loan.is_some()
+        }
+
+        /// amount of `asset` borrowed by the loan in progress
+        #[ink(message)]
+        fn outstanding(&self, asset: AccountId) -> Balance {
+            match &self.loan {
+                Some(loan) => (0..loan.assets.len())
+                    .filter(|i| loan.assets[*i] == asset)
+                    .map(|i| loan.amounts[i])
+                    .sum(),
+                None => 0,
+            }
+        }
+
+        /// the router does not keep receipts, lenders of the legs do
+        #[ink(message)]
+        fn loan_receipt(&self, _loan_id: LoanId) -> Option<LoanReceipt> {
+            None
+        }
+
+        /// sum of amounts available at registered lenders
+        #[ink(message)]
+        fn max_flash_loan(&self, asset: AccountId) -> Balance {
+            self.lenders.iter().fold(0, |sum, lender| {
+                sum.saturating_add(
+                    FlashLoanRef::max_flash_loan_builder(lender, asset)
+                        .fire()
+                        .unwrap_or_default(),
+                )
+            })
+        }
+
+        /// fees of the route returned by `quote_route`
+        #[ink(message)]
+        fn quote_fees(
+            &self,
+            assets: Vec<AccountId>,
+            amounts: Vec<Balance>,
+        ) -> Result<Vec<Balance>, FlashLoanError> {
+            let route = self.quote_route(assets.clone(), amounts)?;
+            let mut fees: Vec<Balance> = vec![0; assets.len()];
+            for leg in route.iter() {
+                for i in 0..assets.len() {
+                    fees[i] = fees[i]
+                        .checked_add(leg.fees[i])
+                        .ok_or(FlashLoanError::Overflow)?;
+                }
+            }
+            Ok(fees)
+        }
+    }
+
+    impl FlashLoanReceiver for FlashLoanRouter {
+        /// is called by the lender of the current leg. Borrows the next leg and approves the lender to take back its part.
+        #[ink(message)]
+        #[allow(unused_variables)]
+        fn execute_operation(
+            &mut self,
+            loan_id: LoanId,
+            initiator: AccountId,
+            assets: Vec<AccountId>,
+            amounts: Vec<u128>,
+            fees: Vec<u128>,
+            data: Vec<u8>,
+        ) -> Result<(), FlashLoanReceiverError> {
+            let lender = self.env().caller();
+            let mut loan = match self.loan.clone() {
+                Some(loan) if !loan.executing && loan.next_leg > 0 => loan,
+                _ => return Err(FlashLoanReceiverError::Custom(String::from("NoLoan"))),
+            };
+            if initiator != self.env().account_id() {
+                return Err(FlashLoanReceiverError::UnauthorizedInitiator);
+            }
+            if loan.route[(loan.next_leg - 1) as usize].lender != lender {
+                return Err(FlashLoanReceiverError::Custom(String::from(
+                    "UnexpectedLender",
+                )));
+            }
+            for i in 0..assets.len() {
+                match loan.assets.iter().position(|asset| *asset == assets[i]) {
+                    Some(j) => {
+                        loan.fees[j] = loan.fees[j]
+                            .checked_add(fees[i])
+                            .ok_or(FlashLoanReceiverError::Custom(String::from("Overflow")))?
+                    }
+                    None => {
+                        return Err(FlashLoanReceiverError::Custom(String::from(
+                            "UnexpectedAsset",
+                        )))
+                    }
+                }
+            }
+            self.loan = Some(loan);
+
+            self._borrow_next_leg()
+                .map_err(|error| FlashLoanReceiverError::Custom(format!("{:?}", error)))?;
+
+            for i in 0..assets.len() {
+                let to_repay = amounts[i]
+                    .checked_add(fees[i])
+                    .ok_or(FlashLoanReceiverError::Custom(String::from("Overflow")))?;
+                if PSP22Ref::approve(&assets[i], lender, to_repay).is_err() {
+                    return Err(FlashLoanReceiverError::Custom(String::from(
+                        "Can't approve",
+                    )));
+                }
+            }
+            Ok(())
+        }
+    }
+
+    impl FlashLoanRouter {
+        #[ink(constructor)]
+        pub fn new(lenders: Vec<AccountId>) -> Self {
+            ink_lang::codegen::initialize_contract(|instance: &mut Self| {
+                let caller = instance.env().caller();
+                instance._init_with_owner(caller);
+                instance.lenders = lenders;
+            })
+        }
+
+        /// returns a split of the loan among lenders. Assets can not be listed more than once.
+        /// For each asset lenders are used in order of the fee per lent unit, each up to its `max_flash_loan`.
+        /// The split is the cheapest one only if fees are proportional to amounts. Fees of lenders with flat fees
+        /// (e.g. `FeeModel::Flat`) are compared per unit of the amount they can lend, so a cheaper split may exist.
+        #[ink(message)]
+        pub fn quote_route(
+            &self,
+            assets: Vec<AccountId>,
+            amounts: Vec<Balance>,
+        ) -> Result<Vec<RouteLeg>, FlashLoanError> {
+            if !(assets.len() == amounts.len()) {
+                return Err(FlashLoanError::Parameters);
+            }
+            self._route(&assets, &amounts)
+        }
+
+        #[ink(message)]
+        pub fn lenders(&self) -> Vec<AccountId> {
+            self.lenders.clone()
+        }
+
+        #[ink(message)]
+        #[modifiers(only_owner)]
+        pub fn add_lender(&mut self, lender: AccountId) -> Result<(), OwnableError> {
+            if !self.lenders.contains(&lender) {
+                self.lenders.push(lender);
+            }
+            Ok(())
+        }
+
+        #[ink(message)]
+        #[modifiers(only_owner)]
+        pub fn remove_lender(&mut self, lender: AccountId) -> Result<(), OwnableError> {
+            self.lenders.retain(|registered| *registered != lender);
+            Ok(())
+        }
+
+        fn _route(
+            &self,
+            assets: &Vec<AccountId>,
+            amounts: &Vec<Balance>,
+        ) -> Result<Vec<RouteLeg>, FlashLoanError> {
+            // fees and approvals of legs are matched with the loan by asset
+            if (1..assets.len()).any(|i| assets[..i].contains(&assets[i])) {
+                return Err(FlashLoanError::Parameters);
+            }
+            let mut route: Vec<RouteLeg> = Vec::new();
+            for i in 0..assets.len() {
+                // (lender, available amount, fee for the available amount)
+                let mut offers: Vec<(AccountId, Balance, Balance)> = Vec::new();
+                for lender in self.lenders.iter() {
+                    let available = FlashLoanRef::max_flash_loan_builder(lender, assets[i])
+                        .fire()
+                        .unwrap_or_default()
+                        .min(amounts[i]);
+                    if available == 0 {
+                        continue;
+                    }
+                    if let Ok(Ok(fees)) =
+                        FlashLoanRef::quote_fees_builder(lender, vec![assets[i]], vec![available])
+                            .fire()
+                    {
+                        offers.push((*lender, available, fees[0]));
+                    }
+                }
+                // fee_a / available_a < fee_b / available_b
+                offers.sort_by(|a, b| a.2.saturating_mul(b.1).cmp(&b.2.saturating_mul(a.1)));
+
+                let mut remaining = amounts[i];
+                for (lender, available, _) in offers {
+                    if remaining == 0 {
+                        break;
+                    }
+                    let amount = available.min(remaining);
+                    remaining -= amount;
+                    let leg = match route.iter().position(|leg| leg.lender == lender) {
+                        Some(leg) => leg,
+                        None => {
+                            route.push(RouteLeg {
+                                lender,
+                                amounts: vec![0; assets.len()],
+                                fees: vec![0; assets.len()],
+                            });
+                            route.len() - 1
+                        }
+                    };
+                    route[leg].amounts[i] += amount;
+                }
+                if remaining > 0 {
+                    return Err(FlashLoanError::Custom(String::from(
+                        "InsufficientLiquidity",
+                    )));
+                }
+            }
+
+            for leg in route.iter_mut() {
+                let (leg_assets, leg_amounts) = Self::_leg_assets(assets, &leg.amounts);
+                let fees = FlashLoanRef::quote_fees_builder(&leg.lender, leg_assets, leg_amounts)
+                    .fire()
+                    .unwrap()?;
+                let mut k = 0;
+                for i in 0..assets.len() {
+                    if leg.amounts[i] > 0 {
+                        leg.fees[i] = fees[k];
+                        k += 1;
+                    }
+                }
+            }
+            Ok(route)
+        }
+
+        /// returns assets and amounts of the leg without zero amounts
+        fn _leg_assets(
+            assets: &Vec<AccountId>,
+            amounts: &Vec<Balance>,
+        ) -> (Vec<AccountId>, Vec<Balance>) {
+            let mut leg_assets: Vec<AccountId> = Vec::new();
+            let mut leg_amounts: Vec<Balance> = Vec::new();
+            for i in 0..assets.len() {
+                if amounts[i] > 0 {
+                    leg_assets.push(assets[i]);
+                    leg_amounts.push(amounts[i]);
+                }
+            }
+            (leg_assets, leg_amounts)
+        }
+
+        /// borrows the next leg of the loan or, if all legs are borrowed, executes the loan with the receiver
+        fn _borrow_next_leg(&mut self) -> Result<(), FlashLoanError> {
+            let mut loan = match self.loan.clone() {
+                Some(loan) => loan,
+                None => return Err(FlashLoanError::Custom(String::from("NoLoan"))),
+            };
+            let router = self.env().account_id();
+
+            if (loan.next_leg as usize) < loan.route.len() {
+                let leg = loan.route[loan.next_leg as usize].clone();
+                loan.next_leg += 1;
+                self.loan = Some(loan.clone());
+                let (leg_assets, leg_amounts) = Self::_leg_assets(&loan.assets, &leg.amounts);
+                // the lender calls back execute_operation which reads and changes the loan
+                self.flush();
+                FlashLoanRef::flash_loan_builder(
+                    &leg.lender,
+                    router,
+                    leg_assets,
+                    leg_amounts,
+                    Vec::<u8>::new(),
+                )
+                .call_flags(CallFlags::default().set_allow_reentry(true))
+                .fire()
+                .unwrap()?;
+                self.load();
+                return Ok(());
+            }
+
+            loan.executing = true;
+            self.loan = Some(loan.clone());
+            for i in 0..loan.assets.len() {
+                PSP22Asset::new(loan.assets[i]).send(loan.receiver, loan.amounts[i])?;
+            }
+            self.flush();
+            FlashLoanReceiverRef::execute_operation_builder(
+                &loan.receiver,
+                loan.loan_id,
+                loan.initiator,
+                loan.assets.clone(),
+                loan.amounts.clone(),
+                loan.fees.clone(),
+                loan.data.clone(),
+            )
+            .call_flags(CallFlags::default().set_allow_reentry(true))
+            .fire()
+            .unwrap()?;
+            self.load();
+            for i in 0..loan.assets.len() {
+                let to_repay = loan.amounts[i]
+                    .checked_add(loan.fees[i])
+                    .ok_or(FlashLoanError::Overflow)?;
+                PSP22Asset::new(loan.assets[i]).pull_back(loan.receiver, to_repay)?;
+            }
+            Ok(())
+        }
+    }
+}
@@ -13,10 +13,15 @@ pub mod flash_loan_contract {
 
     #[ink(storage)]
     #[derive(Default, SpreadAllocate, Storage)]
-    pub struct FlashLoanSimple {}
+    pub struct FlashLoanSimple {
+        #[storage_field]
+        flash_loan: flash_loan::impls::flash_loan::Data,
+    }
 
     #[ink(event)]
     pub struct FlashLoanEvent {
+        #[ink(topic)]
+        loan_id: LoanId,
         #[ink(topic)]
         receiver: AccountId,
         #[ink(topic)]
@@ -28,12 +33,14 @@ pub mod flash_loan_contract {
     impl FlashLoanEventEmit for FlashLoanSimple {
         fn _emit_flash_loan_event(
             &mut self,
+            loan_id: LoanId,
             receiver: AccountId,
             asset: AccountId,
             amount: Balance,
             fee: Balance,
         ) {
             self.env().emit_event(FlashLoanEvent {
+                loan_id,
                 receiver,
                 asset,
                 amount,
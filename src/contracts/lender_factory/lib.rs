@@ -0,0 +1,197 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+#![feature(min_specialization)]
+
+/// instantiates FlashLoanContract lenders from registered code hashes and keeps a registry of them.
+#[openbrush::contract]
+pub mod lender_factory {
+    use flash_loan::storage::{EnumerableIndex, EnumerableMap};
+    use flash_loan_contract::flash_loan_contract::FlashLoanContractRef;
+    use ink_env::DefaultEnvironment;
+    use ink_lang::codegen::EmitEvent;
+    use ink_lang::EnvAccess;
+    use ink_lang::ToAccountId;
+    use ink_prelude::{string::String, vec::Vec};
+    use ink_storage::traits::{PackedLayout, SpreadAllocate, SpreadLayout};
+    use openbrush::contracts::ownable::*;
+    use openbrush::modifiers;
+    use openbrush::traits::Storage;
+
+    #[derive(
+        Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode, SpreadLayout, PackedLayout,
+    )]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+    )]
+    pub struct LenderInfo {
+        /// account that the ownership of the lender was transferred to on deployment
+        pub owner: AccountId,
+        pub version: u32,
+        /// assets with fees set on deployment
+        pub assets: Vec<AccountId>,
+    }
+
+    #[ink(storage)]
+    #[derive(Default, SpreadAllocate, Storage)]
+    pub struct LenderFactory {
+        #[storage_field]
+        ownable: ownable::Data,
+
+        /// code hashes of FlashLoanContract, version is the index
+        code_hashes: Vec<Hash>,
+        lenders: EnumerableMap<AccountId, LenderInfo>,
+        lenders_by_asset: EnumerableIndex<AccountId, AccountId>,
+        lenders_by_owner: EnumerableIndex<AccountId, AccountId>,
+        lenders_by_version: EnumerableIndex<u32, AccountId>,
+        deployed: u64,
+    }
+
+    #[ink(event)]
+    pub struct LenderDeployed {
+        #[ink(topic)]
+        lender: AccountId,
+        #[ink(topic)]
+        owner: AccountId,
+        version: u32,
+    }
+
+    impl Ownable for LenderFactory {}
+
+    impl LenderFactory {
+        #[ink(constructor)]
+        pub fn new(code_hash: Hash) -> Self {
+            ink_lang::codegen::initialize_contract(|instance: &mut Self| {
+                let caller = instance.env().caller();
+                instance._init_with_owner(caller);
+                instance.code_hashes.push(code_hash);
+            })
+        }
+
+        /// registers a new version of the lender code. Returns the version.
+        #[ink(message)]
+        #[modifiers(only_owner)]
+        pub fn add_version(&mut self, code_hash: Hash) -> Result<u32, LenderFactoryError> {
+            self.code_hashes.push(code_hash);
+            Ok(self.latest_version())
+        }
+
+        #[ink(message)]
+        pub fn latest_version(&self) -> u32 {
+            (self.code_hashes.len() as u32).saturating_sub(1)
+        }
+
+        #[ink(message)]
+        pub fn code_hash(&self, version: u32) -> Option<Hash> {
+            self.code_hashes.get(version as usize).copied()
+        }
+
+        /// instantiates FlashLoanContract of `version` and transfers its ownership to the caller.
+        ///
+        ///  * `fees_e6`, `allowed`, `free` - constructor arguments of FlashLoanContract
+        #[ink(message)]
+        pub fn deploy_lender(
+            &mut self,
+            version: u32,
+            fees_e6: Vec<(AccountId, u128)>,
+            allowed: Vec<AccountId>,
+            free: Vec<AccountId>,
+        ) -> Result<AccountId, LenderFactoryError> {
+            let code_hash = self
+                .code_hash(version)
+                .ok_or(LenderFactoryError::UnknownVersion)?;
+            let owner = self.env().caller();
+            let assets: Vec<AccountId> = fees_e6.iter().map(|(asset, _)| *asset).collect();
+
+            let salt = self.deployed.to_le_bytes();
+            let lender = FlashLoanContractRef::new(fees_e6, allowed, free)
+                .endowment(0)
+                .code_hash(code_hash)
+                .salt_bytes(salt)
+                .instantiate()
+                .map_err(|_| LenderFactoryError::InstantiationFailed)?
+                .to_account_id();
+            self.deployed += 1;
+            OwnableRef::transfer_ownership(&lender, owner)?;
+
+            for asset in assets.iter() {
+                self.lenders_by_asset.insert(asset, &lender);
+            }
+            self.lenders_by_owner.insert(&owner, &lender);
+            self.lenders_by_version.insert(&version, &lender);
+            self.lenders.insert(
+                &lender,
+                &LenderInfo {
+                    owner,
+                    version,
+                    assets,
+                },
+            );
+
+            // FlashLoanContract events are in scope as well, so the contract has to be named
+            <EnvAccess<'_, DefaultEnvironment> as EmitEvent<LenderFactory>>::emit_event(
+                self.env(),
+                LenderDeployed {
+                    lender,
+                    owner,
+                    version,
+                },
+            );
+            Ok(lender)
+        }
+
+        #[ink(message)]
+        pub fn lender_info(&self, lender: AccountId) -> Option<LenderInfo> {
+            self.lenders.get(&lender)
+        }
+
+        /// returns up to `limit` deployed lenders with their info starting from index `offset`
+        #[ink(message)]
+        pub fn list_lenders(&self, offset: u32, limit: u32) -> Vec<(AccountId, LenderInfo)> {
+            self.lenders.page(offset, limit)
+        }
+
+        /// returns up to `limit` lenders deployed with `asset` starting from index `offset`
+        #[ink(message)]
+        pub fn lenders_by_asset(
+            &self,
+            asset: AccountId,
+            offset: u32,
+            limit: u32,
+        ) -> Vec<AccountId> {
+            self.lenders_by_asset.page(&asset, offset, limit)
+        }
+
+        /// returns up to `limit` lenders owned by `owner` on deployment starting from index `offset`
+        #[ink(message)]
+        pub fn lenders_by_owner(
+            &self,
+            owner: AccountId,
+            offset: u32,
+            limit: u32,
+        ) -> Vec<AccountId> {
+            self.lenders_by_owner.page(&owner, offset, limit)
+        }
+
+        /// returns up to `limit` lenders of `version` starting from index `offset`
+        #[ink(message)]
+        pub fn lenders_by_version(&self, version: u32, offset: u32, limit: u32) -> Vec<AccountId> {
+            self.lenders_by_version.page(&version, offset, limit)
+        }
+    }
+
+    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum LenderFactoryError {
+        Custom(String),
+        OwnableError(OwnableError),
+        /// returned if there is no code hash registered for the version
+        UnknownVersion,
+        InstantiationFailed,
+    }
+
+    impl From<OwnableError> for LenderFactoryError {
+        fn from(error: OwnableError) -> Self {
+            LenderFactoryError::OwnableError(error)
+        }
+    }
+}
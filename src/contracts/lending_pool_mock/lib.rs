@@ -0,0 +1,288 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+#![feature(min_specialization)]
+
+/// minimal collateralized lending pool used in liquidation and refinancing tests. There is no interest and prices are set by the owner.
+/// A position is healthy if sum of `collateral * price * collateral_coefficient` is not less than sum of `debt * price * borrow_coefficient`.
+#[openbrush::contract]
+pub mod lending_pool_mock {
+    use flash_loan::traits::lending_pool::*;
+    use ink_env::CallFlags;
+    use ink_lang::codegen::{EmitEvent, Env};
+    use ink_prelude::vec::Vec;
+    use ink_storage::traits::{PackedLayout, SpreadAllocate, SpreadLayout};
+    use openbrush::contracts::ownable::*;
+    use openbrush::contracts::traits::psp22::PSP22Ref;
+    use openbrush::modifiers;
+    use openbrush::storage::Mapping;
+    use openbrush::traits::Storage;
+
+    const E6: u128 = 1_000_000;
+
+    /// coefficients are multiplied by 10^6, like in `scripts/rules.json`
+    #[derive(
+        Debug,
+        Default,
+        Clone,
+        Copy,
+        PartialEq,
+        Eq,
+        scale::Encode,
+        scale::Decode,
+        SpreadLayout,
+        PackedLayout,
+    )]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+    )]
+    pub struct AssetConfig {
+        /// value of the smallest unit of the asset multiplied by 10^6
+        pub price_e6: u128,
+        pub collateral_coefficient_e6: u128,
+        pub borrow_coefficient_e6: u128,
+        /// bonus collateral taken by liquidators
+        pub penalty_e6: u128,
+    }
+
+    #[ink(storage)]
+    #[derive(Default, SpreadAllocate, Storage)]
+    pub struct LendingPoolMock {
+        #[storage_field]
+        ownable: ownable::Data,
+
+        assets: Vec<AccountId>,
+        configs: Mapping<AccountId, AssetConfig>,
+        /// (user, asset) -> amount
+        collaterals: Mapping<(AccountId, AccountId), Balance>,
+        debts: Mapping<(AccountId, AccountId), Balance>,
+    }
+
+    #[ink(event)]
+    pub struct Liquidation {
+        #[ink(topic)]
+        liquidator: AccountId,
+        #[ink(topic)]
+        user: AccountId,
+        debt_asset: AccountId,
+        collateral_asset: AccountId,
+        repaid: Balance,
+        seized: Balance,
+    }
+
+    impl Ownable for LendingPoolMock {}
+
+    impl LendingPool for LendingPoolMock {
+        #[ink(message)]
+        fn deposit(
+            &mut self,
+            asset: AccountId,
+            amount: Balance,
+            on_behalf_of: AccountId,
+        ) -> Result<(), LendingPoolError> {
+            self._config(&asset)?;
+            let caller = self.env().caller();
+            self._transfer_from(asset, caller, amount)?;
+            let collateral = self.collateral_of(on_behalf_of, asset);
+            self.collaterals
+                .insert(&(on_behalf_of, asset), &(collateral + amount));
+            Ok(())
+        }
+
+        #[ink(message)]
+        fn withdraw(
+            &mut self,
+            asset: AccountId,
+            amount: Balance,
+            to: AccountId,
+        ) -> Result<(), LendingPoolError> {
+            let caller = self.env().caller();
+            let collateral = self.collateral_of(caller, asset);
+            if collateral < amount {
+                return Err(LendingPoolError::InsufficientDeposit);
+            }
+            self.collaterals
+                .insert(&(caller, asset), &(collateral - amount));
+            if !self.is_healthy(caller) {
+                return Err(LendingPoolError::InsufficientCollateral);
+            }
+            self._transfer(asset, to, amount)
+        }
+
+        #[ink(message)]
+        fn borrow(&mut self, asset: AccountId, amount: Balance) -> Result<(), LendingPoolError> {
+            self._config(&asset)?;
+            let caller = self.env().caller();
+            let debt = self.debt_of(caller, asset);
+            self.debts.insert(&(caller, asset), &(debt + amount));
+            if !self.is_healthy(caller) {
+                return Err(LendingPoolError::InsufficientCollateral);
+            }
+            self._transfer(asset, caller, amount)
+        }
+
+        #[ink(message)]
+        fn repay(
+            &mut self,
+            asset: AccountId,
+            amount: Balance,
+            on_behalf_of: AccountId,
+        ) -> Result<Balance, LendingPoolError> {
+            let debt = self.debt_of(on_behalf_of, asset);
+            if debt == 0 {
+                return Err(LendingPoolError::NoDebt);
+            }
+            let repaid = amount.min(debt);
+            let caller = self.env().caller();
+            self._transfer_from(asset, caller, repaid)?;
+            self.debts.insert(&(on_behalf_of, asset), &(debt - repaid));
+            Ok(repaid)
+        }
+
+        #[ink(message)]
+        fn liquidate(
+            &mut self,
+            user: AccountId,
+            debt_asset: AccountId,
+            collateral_asset: AccountId,
+            amount: Balance,
+            min_collateral: Balance,
+        ) -> Result<Balance, LendingPoolError> {
+            if self.is_healthy(user) {
+                return Err(LendingPoolError::PositionHealthy);
+            }
+            let debt_config = self._config(&debt_asset)?;
+            let collateral_config = self._config(&collateral_asset)?;
+            let debt = self.debt_of(user, debt_asset);
+            if debt == 0 {
+                return Err(LendingPoolError::NoDebt);
+            }
+            let collateral = self.collateral_of(user, collateral_asset);
+
+            let repaid = amount.min(debt);
+            let seized = (repaid * debt_config.price_e6 / collateral_config.price_e6)
+                * (E6 + collateral_config.penalty_e6)
+                / E6;
+            let seized = seized.min(collateral);
+            if seized < min_collateral {
+                return Err(LendingPoolError::MinimumCollateralNotReached);
+            }
+
+            let liquidator = self.env().caller();
+            self._transfer_from(debt_asset, liquidator, repaid)?;
+            self.debts.insert(&(user, debt_asset), &(debt - repaid));
+            self.collaterals
+                .insert(&(user, collateral_asset), &(collateral - seized));
+            self._transfer(collateral_asset, liquidator, seized)?;
+
+            self.env().emit_event(Liquidation {
+                liquidator,
+                user,
+                debt_asset,
+                collateral_asset,
+                repaid,
+                seized,
+            });
+            Ok(seized)
+        }
+
+        #[ink(message)]
+        fn collateral_of(&self, user: AccountId, asset: AccountId) -> Balance {
+            self.collaterals.get(&(user, asset)).unwrap_or_default()
+        }
+
+        #[ink(message)]
+        fn debt_of(&self, user: AccountId, asset: AccountId) -> Balance {
+            self.debts.get(&(user, asset)).unwrap_or_default()
+        }
+
+        #[ink(message)]
+        fn is_healthy(&self, user: AccountId) -> bool {
+            let mut collateral_power: u128 = 0;
+            let mut debt_power: u128 = 0;
+            for asset in self.assets.iter() {
+                let config = self.configs.get(asset).unwrap_or_default();
+                collateral_power += self.collateral_of(user, *asset) * config.price_e6 / E6
+                    * config.collateral_coefficient_e6
+                    / E6;
+                debt_power += self.debt_of(user, *asset) * config.price_e6 / E6
+                    * config.borrow_coefficient_e6
+                    / E6;
+            }
+            collateral_power >= debt_power
+        }
+    }
+
+    impl LendingPoolMock {
+        #[ink(constructor)]
+        pub fn new() -> Self {
+            ink_lang::codegen::initialize_contract(|instance: &mut Self| {
+                let caller = instance.env().caller();
+                instance._init_with_owner(caller);
+            })
+        }
+
+        /// registers `asset` or updates its config
+        #[ink(message)]
+        #[modifiers(only_owner)]
+        pub fn set_asset(
+            &mut self,
+            asset: AccountId,
+            config: AssetConfig,
+        ) -> Result<(), OwnableError> {
+            if self.configs.get(&asset).is_none() {
+                self.assets.push(asset);
+            }
+            self.configs.insert(&asset, &config);
+            Ok(())
+        }
+
+        #[ink(message)]
+        #[modifiers(only_owner)]
+        pub fn set_price(&mut self, asset: AccountId, price_e6: u128) -> Result<(), OwnableError> {
+            if let Some(mut config) = self.configs.get(&asset) {
+                config.price_e6 = price_e6;
+                self.configs.insert(&asset, &config);
+            }
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn asset_config(&self, asset: AccountId) -> Option<AssetConfig> {
+            self.configs.get(&asset)
+        }
+
+        fn _config(&self, asset: &AccountId) -> Result<AssetConfig, LendingPoolError> {
+            self.configs
+                .get(asset)
+                .ok_or(LendingPoolError::UnknownAsset)
+        }
+
+        // tokens call before_received on the pool, so reentry has to be allowed
+        fn _transfer_from(
+            &self,
+            asset: AccountId,
+            from: AccountId,
+            amount: Balance,
+        ) -> Result<(), LendingPoolError> {
+            let this = self.env().account_id();
+            PSP22Ref::transfer_from_builder(&asset, from, this, amount, Vec::<u8>::new())
+                .call_flags(CallFlags::default().set_allow_reentry(true))
+                .fire()
+                .unwrap()?;
+            Ok(())
+        }
+
+        fn _transfer(
+            &self,
+            asset: AccountId,
+            to: AccountId,
+            amount: Balance,
+        ) -> Result<(), LendingPoolError> {
+            PSP22Ref::transfer_builder(&asset, to, amount, Vec::<u8>::new())
+                .call_flags(CallFlags::default().set_allow_reentry(true))
+                .fire()
+                .unwrap()?;
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,154 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+#![feature(min_specialization)]
+
+/// receiver that repays the debt of an unhealthy position in a lending pool with the borrowed asset, sells the seized collateral
+/// on an AMM pair and repays the loan from the proceeds.
+/// `data` is SCALE encoded `LiquidationParams`, it can be built with `encode_params` query.
+/// Only flash loans initiated by the owner are accepted. The profit stays in the contract and can be withdrawn by the owner.
+#[openbrush::contract]
+pub mod liquidation_receiver {
+    use flash_loan::impls::flash_loan_receiver::*;
+    use flash_loan::traits::amm_pair::{AmmPairError, AmmPairRef};
+    use flash_loan::traits::flash_loan::LoanId;
+    use flash_loan::traits::lending_pool::{LendingPoolError, LendingPoolRef};
+    use ink_env::CallFlags;
+    use ink_lang::codegen::{EmitEvent, Env};
+    use ink_prelude::{format, string::String, vec::Vec};
+    use ink_storage::traits::SpreadAllocate;
+    use openbrush::contracts::ownable::*;
+    use openbrush::contracts::traits::psp22::PSP22Ref;
+    use openbrush::traits::Storage;
+    use scale::{Decode, Encode};
+
+    #[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct LiquidationParams {
+        pub pool: AccountId,
+        /// owner of the unhealthy position
+        pub user: AccountId,
+        pub collateral_asset: AccountId,
+        /// pair of the collateral asset and the borrowed asset
+        pub pair: AccountId,
+        /// the operation fails if the profit after fee is less
+        pub min_profit: Balance,
+    }
+
+    #[ink(storage)]
+    #[derive(Default, SpreadAllocate, Storage)]
+    pub struct LiquidationReceiver {
+        #[storage_field]
+        ownable: ownable::Data,
+        #[storage_field]
+        receiver: flash_loan_receiver::Data,
+    }
+
+    #[ink(event)]
+    pub struct Liquidated {
+        #[ink(topic)]
+        user: AccountId,
+        debt_asset: AccountId,
+        repaid: Balance,
+        seized: Balance,
+        profit: Balance,
+    }
+
+    impl Ownable for LiquidationReceiver {}
+
+    impl FlashLoanReceiver for LiquidationReceiver {}
+
+    impl FlashLoanReceiverAdmin for LiquidationReceiver {}
+
+    impl FlashLoanReceiverInternal for LiquidationReceiver {
+        fn _is_authorized_initiator(&self, initiator: &AccountId) -> bool {
+            *initiator == self.owner()
+        }
+
+        fn _on_flash_loan(
+            &mut self,
+            _loan_id: LoanId,
+            assets: &Vec<AccountId>,
+            amounts: &Vec<Balance>,
+            fees: &Vec<Balance>,
+            data: &Vec<u8>,
+        ) -> Result<(), FlashLoanReceiverError> {
+            if assets.len() != 1 {
+                return Err(FlashLoanReceiverError::Custom(String::from(
+                    "Exactly one asset expected",
+                )));
+            }
+            let params = LiquidationParams::decode(&mut &data[..]).map_err(|_| {
+                FlashLoanReceiverError::Custom(String::from("Invalid liquidation params"))
+            })?;
+            let (asset, amount, fee) = (assets[0], amounts[0], fees[0]);
+            let this = self.env().account_id();
+            let balance_before = PSP22Ref::balance_of(&asset, this);
+            let debt_before = LendingPoolRef::debt_of(&params.pool, params.user, asset);
+
+            PSP22Ref::approve(&asset, params.pool, amount)?;
+            // the pool transfers the seized collateral to this contract, so reentry has to be allowed
+            let seized = LendingPoolRef::liquidate_builder(
+                &params.pool,
+                params.user,
+                asset,
+                params.collateral_asset,
+                amount,
+                0,
+            )
+            .call_flags(CallFlags::default().set_allow_reentry(true))
+            .fire()
+            .unwrap()
+            .map_err(|error: LendingPoolError| {
+                FlashLoanReceiverError::Custom(format!("Liquidation failed: {:?}", error))
+            })?;
+            let repaid = debt_before - LendingPoolRef::debt_of(&params.pool, params.user, asset);
+            // resets the allowance left if less than `amount` was repaid
+            PSP22Ref::approve(&asset, params.pool, 0)?;
+
+            PSP22Ref::approve(&params.collateral_asset, params.pair, seized)?;
+            AmmPairRef::swap_builder(&params.pair, params.collateral_asset, seized, 0, this)
+                .call_flags(CallFlags::default().set_allow_reentry(true))
+                .fire()
+                .unwrap()
+                .map_err(|error: AmmPairError| {
+                    FlashLoanReceiverError::Custom(format!("Swap failed: {:?}", error))
+                })?;
+
+            let balance_after = PSP22Ref::balance_of(&asset, this);
+            let profit = balance_after
+                .checked_sub(balance_before)
+                .and_then(|gain| gain.checked_sub(fee))
+                .unwrap_or_default();
+            if balance_after < balance_before + fee + params.min_profit {
+                return Err(FlashLoanReceiverError::Custom(format!(
+                    "Profit {} is less than {}",
+                    profit, params.min_profit
+                )));
+            }
+
+            self.env().emit_event(Liquidated {
+                user: params.user,
+                debt_asset: asset,
+                repaid,
+                seized,
+                profit,
+            });
+            Ok(())
+        }
+    }
+
+    impl LiquidationReceiver {
+        #[ink(constructor)]
+        pub fn new() -> Self {
+            ink_lang::codegen::initialize_contract(|instance: &mut Self| {
+                let caller = instance.env().caller();
+                instance._init_with_owner(caller);
+            })
+        }
+
+        /// returns `params` encoded as `data` for flash_loan
+        #[ink(message)]
+        pub fn encode_params(&self, params: LiquidationParams) -> Vec<u8> {
+            params.encode()
+        }
+    }
+}
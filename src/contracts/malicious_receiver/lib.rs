@@ -0,0 +1,195 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+#![feature(min_specialization)]
+
+/// receiver used to check that the lender invariants hold against attacks. The attack is selected with `set_attack`.
+/// Without an attack it mints the fees and approves `amounts + fees` like `flash_loan_receiver_mock`.
+#[openbrush::contract]
+pub mod malicious_receiver {
+    use flash_loan::raw_call::{EncodedArgs, RawOutput};
+    use flash_loan::traits::{
+        flash_loan::{FlashLoanError, FlashLoanRef, LoanId},
+        flash_loan_receiver::*,
+    };
+    use ink_env::call::{build_call, Call, ExecutionInput, Selector};
+    use ink_env::{CallFlags, DefaultEnvironment};
+    use ink_lang::codegen::Env;
+    use ink_prelude::{format, string::String, vec::Vec};
+    use ink_storage::traits::{PackedLayout, SpreadAllocate, SpreadLayout};
+    use openbrush::{
+        contracts::traits::psp22::{extensions::mintable::PSP22MintableRef, *},
+        storage::Mapping,
+        traits::{Flush, Storage},
+    };
+
+    #[derive(
+        Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode, SpreadLayout, PackedLayout,
+    )]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+    )]
+    pub enum Attack {
+        /// calls flash_loan of the lender again with the same arguments, the nested loan is repaid honestly
+        ReenterFlashLoan,
+        /// calls a message of the lender, `input` is SCALE encoded arguments
+        CallLender { selector: [u8; 4], input: Vec<u8> },
+        /// approves `amounts + fees` of the token instead of the lent asset
+        RepayWithToken(AccountId),
+        /// transfers `part_e6` / 10^6 of `amounts + fees` to the lender instead of approving
+        PartialTransfer { part_e6: u128 },
+        /// transfers lent amounts to the account and approves `amounts + fees` anyway
+        TransferElsewhere(AccountId),
+        /// writes to storage `iterations` times before repaying
+        BurnGas { iterations: u32 },
+        /// repays honestly assets before the index and fails on it
+        FailOnNthAsset(u32),
+    }
+
+    #[ink(storage)]
+    #[derive(Default, SpreadAllocate, Storage)]
+    pub struct MaliciousReceiver {
+        attack: Option<Attack>,
+        /// set while the nested flash loan of `ReenterFlashLoan` is executed
+        reentered: bool,
+        gas_burner: Mapping<u32, u32>,
+    }
+
+    impl FlashLoanReceiver for MaliciousReceiver {
+        #[ink(message)]
+        #[allow(unused_variables)]
+        fn execute_operation(
+            &mut self,
+            loan_id: LoanId,
+            initiator: AccountId,
+            assets: Vec<AccountId>,
+            amounts: Vec<u128>,
+            fees: Vec<u128>,
+            data: Vec<u8>,
+        ) -> Result<(), FlashLoanReceiverError> {
+            let lender = self.env().caller();
+            let this = self.env().account_id();
+            let attack = if self.reentered {
+                None
+            } else {
+                self.attack.clone()
+            };
+
+            match &attack {
+                Some(Attack::ReenterFlashLoan) => {
+                    self.reentered = true;
+                    self.flush();
+                    let result = FlashLoanRef::flash_loan_builder(
+                        &lender,
+                        this,
+                        assets.clone(),
+                        amounts.clone(),
+                        data,
+                    )
+                    .call_flags(CallFlags::default().set_allow_reentry(true))
+                    .fire()
+                    .unwrap();
+                    self.load();
+                    self.reentered = false;
+                    if let Err(error) = result {
+                        return Err(FlashLoanReceiverError::Custom(format!(
+                            "Reentry failed: {:?}",
+                            error
+                        )));
+                    }
+                }
+                Some(Attack::CallLender { selector, input }) => {
+                    let output = build_call::<DefaultEnvironment>()
+                        .call_type(Call::new().callee(lender))
+                        .exec_input(
+                            ExecutionInput::new(Selector::new(*selector))
+                                .push_arg(EncodedArgs(input.clone())),
+                        )
+                        .returns::<RawOutput>()
+                        .call_flags(CallFlags::default().set_allow_reentry(true))
+                        .fire()
+                        .map_err(|_| {
+                            FlashLoanReceiverError::Custom(String::from("Lender call failed"))
+                        })?;
+                    // Result::Err is encoded with 1 as the first byte
+                    if output.0.first() == Some(&1) {
+                        return Err(FlashLoanReceiverError::Custom(String::from(
+                            "Lender call returned Err",
+                        )));
+                    }
+                }
+                Some(Attack::BurnGas { iterations }) => {
+                    for i in 0..*iterations {
+                        self.gas_burner.insert(&i, &i);
+                    }
+                }
+                _ => {}
+            }
+
+            for i in 0..assets.len() {
+                if attack == Some(Attack::FailOnNthAsset(i as u32)) {
+                    return Err(FlashLoanReceiverError::Custom(format!(
+                        "Failed on asset {}",
+                        i
+                    )));
+                }
+                PSP22MintableRef::mint(&assets[i], this, fees[i])?;
+                let to_return = amounts[i] + fees[i];
+                match attack {
+                    Some(Attack::RepayWithToken(token)) => {
+                        PSP22MintableRef::mint(&token, this, to_return)?;
+                        PSP22Ref::approve(&token, lender, to_return)?;
+                    }
+                    Some(Attack::PartialTransfer { part_e6 }) => {
+                        PSP22Ref::transfer(
+                            &assets[i],
+                            lender,
+                            to_return * part_e6 / 1_000_000,
+                            Vec::<u8>::new(),
+                        )?;
+                    }
+                    Some(Attack::TransferElsewhere(to)) => {
+                        PSP22Ref::transfer(&assets[i], to, amounts[i], Vec::<u8>::new())?;
+                        PSP22Ref::approve(&assets[i], lender, to_return)?;
+                    }
+                    _ => {
+                        PSP22Ref::approve(&assets[i], lender, to_return)?;
+                    }
+                }
+            }
+            Ok(())
+        }
+    }
+
+    impl MaliciousReceiver {
+        #[ink(constructor)]
+        pub fn new() -> Self {
+            ink_lang::codegen::initialize_contract(|_instance: &mut Self| {})
+        }
+
+        /// None makes the receiver honest
+        #[ink(message)]
+        pub fn set_attack(&mut self, attack: Option<Attack>) {
+            self.attack = attack;
+        }
+
+        #[ink(message)]
+        pub fn attack(&self) -> Option<Attack> {
+            self.attack.clone()
+        }
+
+        /// takes a flash loan of `lender` with itself as the receiver, so it is the caller of nested loans too
+        #[ink(message)]
+        pub fn borrow(
+            &self,
+            lender: AccountId,
+            assets: Vec<AccountId>,
+            amounts: Vec<u128>,
+        ) -> Result<(), FlashLoanError> {
+            let this = self.env().account_id();
+            FlashLoanRef::flash_loan_builder(&lender, this, assets, amounts, Vec::new())
+                .call_flags(CallFlags::default().set_allow_reentry(true))
+                .fire()
+                .unwrap()
+        }
+    }
+}
@@ -0,0 +1,86 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+#![feature(min_specialization)]
+
+/// policy contract answering `authorize` as selected with `set_answer`, used to check how lenders handle policy contracts
+#[openbrush::contract]
+pub mod policy_mock {
+    use flash_loan::traits::policy_contract::*;
+    use ink_prelude::{string::String, vec::Vec};
+    use ink_storage::traits::{PackedLayout, SpreadAllocate, SpreadLayout};
+    use openbrush::storage::Mapping;
+    use openbrush::traits::Storage;
+
+    #[derive(
+        Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode, SpreadLayout, PackedLayout,
+    )]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+    )]
+    pub enum PolicyAnswer {
+        /// loans of callers that are not denied are allowed
+        Allow,
+        Deny,
+        /// returns `PolicyContractError::Custom("Failed")`
+        Fail,
+        Trap,
+        /// loops until it runs out of gas
+        BurnGas,
+    }
+
+    #[ink(storage)]
+    #[derive(Default, SpreadAllocate, Storage)]
+    pub struct PolicyMock {
+        /// None stands for `PolicyAnswer::Allow`
+        answer: Option<PolicyAnswer>,
+        denied: Mapping<AccountId, ()>,
+    }
+
+    impl FlashLoanPolicyContract for PolicyMock {
+        #[ink(message)]
+        fn authorize(
+            &self,
+            caller: AccountId,
+            _receiver: AccountId,
+            _assets: Vec<AccountId>,
+            _amounts: Vec<Balance>,
+            _data_hash: Hash,
+        ) -> Result<bool, PolicyContractError> {
+            match self.answer.unwrap_or(PolicyAnswer::Allow) {
+                PolicyAnswer::Allow => Ok(!self.denied.contains(&caller)),
+                PolicyAnswer::Deny => Ok(false),
+                PolicyAnswer::Fail => Err(PolicyContractError::Custom(String::from("Failed"))),
+                PolicyAnswer::Trap => panic!("trapped"),
+                PolicyAnswer::BurnGas => {
+                    let mut i: u64 = 0;
+                    loop {
+                        i = i.wrapping_add(
+                            ink_env::block_number::<ink_env::DefaultEnvironment>() as u64
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    impl PolicyMock {
+        #[ink(constructor)]
+        pub fn new() -> Self {
+            ink_lang::codegen::initialize_contract(|_instance: &mut Self| {})
+        }
+
+        #[ink(message)]
+        pub fn set_answer(&mut self, answer: PolicyAnswer) {
+            self.answer = Some(answer);
+        }
+
+        /// callers denied while the answer is `Allow`
+        #[ink(message)]
+        pub fn set_denied(&mut self, caller: AccountId, denied: bool) {
+            match denied {
+                true => self.denied.insert(&caller, &()),
+                false => self.denied.remove(&caller),
+            }
+        }
+    }
+}
@@ -0,0 +1,26 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+#![feature(min_specialization)]
+
+#[openbrush::contract]
+pub mod psp34_mintable {
+    use ink_storage::traits::SpreadAllocate;
+    use openbrush::{contracts::psp34::extensions::mintable::*, traits::Storage};
+
+    #[ink(storage)]
+    #[derive(Default, SpreadAllocate, Storage)]
+    pub struct PSP34MintableContract {
+        #[storage_field]
+        psp34: psp34::Data,
+    }
+
+    impl PSP34 for PSP34MintableContract {}
+
+    impl PSP34Mintable for PSP34MintableContract {}
+
+    impl PSP34MintableContract {
+        #[ink(constructor)]
+        pub fn new() -> Self {
+            ink_lang::codegen::initialize_contract(|_instance: &mut Self| {})
+        }
+    }
+}
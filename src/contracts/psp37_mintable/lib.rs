@@ -0,0 +1,27 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+#![feature(min_specialization)]
+
+/// PSP37 multi token (PSP35 in openbrush 2.2.0)
+#[openbrush::contract]
+pub mod psp37_mintable {
+    use ink_storage::traits::SpreadAllocate;
+    use openbrush::{contracts::psp35::extensions::mintable::*, traits::Storage};
+
+    #[ink(storage)]
+    #[derive(Default, SpreadAllocate, Storage)]
+    pub struct PSP37MintableContract {
+        #[storage_field]
+        psp35: psp35::Data,
+    }
+
+    impl PSP35 for PSP37MintableContract {}
+
+    impl PSP35Mintable for PSP37MintableContract {}
+
+    impl PSP37MintableContract {
+        #[ink(constructor)]
+        pub fn new() -> Self {
+            ink_lang::codegen::initialize_contract(|_instance: &mut Self| {})
+        }
+    }
+}
@@ -0,0 +1,293 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+#![feature(min_specialization)]
+
+/// receiver that refinances its own positions in lending pools: it migrates debt between pools or swaps the collateral of a position.
+/// `data` is SCALE encoded `RefinanceAction`, it can be built with `encode_action` query.
+/// Positions belong to the contract and are managed by the owner. Only flash loans initiated by the owner are accepted.
+#[openbrush::contract]
+pub mod refinance_receiver {
+    use flash_loan::impls::flash_loan_receiver::*;
+    use flash_loan::traits::amm_pair::{AmmPairError, AmmPairRef};
+    use flash_loan::traits::flash_loan::LoanId;
+    use flash_loan::traits::lending_pool::{LendingPoolError, LendingPoolRef};
+    use ink_env::CallFlags;
+    use ink_prelude::{format, string::String, vec::Vec};
+    use ink_storage::traits::SpreadAllocate;
+    use openbrush::contracts::ownable::*;
+    use openbrush::contracts::psp22::PSP22Error;
+    use openbrush::contracts::traits::psp22::PSP22Ref;
+    use openbrush::modifiers;
+    use openbrush::traits::Storage;
+    use scale::{Decode, Encode};
+
+    #[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum RefinanceAction {
+        MigrateDebt(MigrateDebtParams),
+        SwapCollateral(SwapCollateralParams),
+    }
+
+    /// repays the borrowed asset debt at `from_pool`, moves `collateral_amount` of `collateral_asset` to `to_pool`
+    /// and borrows there the repaid amount with the fee
+    #[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct MigrateDebtParams {
+        pub from_pool: AccountId,
+        pub to_pool: AccountId,
+        pub collateral_asset: AccountId,
+        pub collateral_amount: Balance,
+    }
+
+    /// deposits the borrowed asset at `pool`, withdraws `old_amount` of `old_collateral`
+    /// and sells it on `pair` for the borrowed asset
+    #[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct SwapCollateralParams {
+        pub pool: AccountId,
+        pub old_collateral: AccountId,
+        pub old_amount: Balance,
+        pub pair: AccountId,
+    }
+
+    #[ink(storage)]
+    #[derive(Default, SpreadAllocate, Storage)]
+    pub struct RefinanceReceiver {
+        #[storage_field]
+        ownable: ownable::Data,
+        #[storage_field]
+        receiver: flash_loan_receiver::Data,
+    }
+
+    impl Ownable for RefinanceReceiver {}
+
+    impl FlashLoanReceiver for RefinanceReceiver {}
+
+    impl FlashLoanReceiverAdmin for RefinanceReceiver {}
+
+    impl FlashLoanReceiverInternal for RefinanceReceiver {
+        fn _is_authorized_initiator(&self, initiator: &AccountId) -> bool {
+            *initiator == self.owner()
+        }
+
+        fn _on_flash_loan(
+            &mut self,
+            _loan_id: LoanId,
+            assets: &Vec<AccountId>,
+            amounts: &Vec<Balance>,
+            fees: &Vec<Balance>,
+            data: &Vec<u8>,
+        ) -> Result<(), FlashLoanReceiverError> {
+            if assets.len() != 1 {
+                return Err(FlashLoanReceiverError::Custom(String::from(
+                    "Exactly one asset expected",
+                )));
+            }
+            let action = RefinanceAction::decode(&mut &data[..]).map_err(|_| {
+                FlashLoanReceiverError::Custom(String::from("Invalid refinance action"))
+            })?;
+            let (asset, amount, fee) = (assets[0], amounts[0], fees[0]);
+            match action {
+                RefinanceAction::MigrateDebt(params) => {
+                    self._migrate_debt(asset, amount, fee, &params)
+                }
+                RefinanceAction::SwapCollateral(params) => {
+                    self._swap_collateral(asset, amount, fee, &params)
+                }
+            }
+        }
+    }
+
+    impl RefinanceReceiver {
+        #[ink(constructor)]
+        pub fn new() -> Self {
+            ink_lang::codegen::initialize_contract(|instance: &mut Self| {
+                let caller = instance.env().caller();
+                instance._init_with_owner(caller);
+            })
+        }
+
+        /// borrows `amount` of `asset` from `pool` against the collateral of the contract and transfers it to `to`
+        #[ink(message)]
+        #[modifiers(only_owner)]
+        pub fn borrow(
+            &mut self,
+            pool: AccountId,
+            asset: AccountId,
+            amount: Balance,
+            to: AccountId,
+        ) -> Result<(), RefinanceReceiverError> {
+            self._borrow(pool, asset, amount)?;
+            PSP22Ref::transfer(&asset, to, amount, Vec::<u8>::new())?;
+            Ok(())
+        }
+
+        /// withdraws `amount` of `asset` collateral of the contract from `pool` to `to`
+        #[ink(message)]
+        #[modifiers(only_owner)]
+        pub fn withdraw_collateral(
+            &mut self,
+            pool: AccountId,
+            asset: AccountId,
+            amount: Balance,
+            to: AccountId,
+        ) -> Result<(), RefinanceReceiverError> {
+            self._withdraw_collateral(pool, asset, amount, to)?;
+            Ok(())
+        }
+
+        /// returns `action` encoded as `data` for flash_loan
+        #[ink(message)]
+        pub fn encode_action(&self, action: RefinanceAction) -> Vec<u8> {
+            action.encode()
+        }
+
+        fn _migrate_debt(
+            &mut self,
+            asset: AccountId,
+            amount: Balance,
+            fee: Balance,
+            params: &MigrateDebtParams,
+        ) -> Result<(), FlashLoanReceiverError> {
+            let this = self.env().account_id();
+            let repaid = self
+                ._repay(params.from_pool, asset, amount)
+                .map_err(Self::_pool_error)?;
+            self._withdraw_collateral(
+                params.from_pool,
+                params.collateral_asset,
+                params.collateral_amount,
+                this,
+            )
+            .map_err(Self::_pool_error)?;
+            self._deposit(
+                params.to_pool,
+                params.collateral_asset,
+                params.collateral_amount,
+            )
+            .map_err(Self::_pool_error)?;
+            // `amount - repaid` was not used
+            self._borrow(params.to_pool, asset, repaid + fee)
+                .map_err(Self::_pool_error)
+        }
+
+        fn _swap_collateral(
+            &mut self,
+            asset: AccountId,
+            amount: Balance,
+            fee: Balance,
+            params: &SwapCollateralParams,
+        ) -> Result<(), FlashLoanReceiverError> {
+            let this = self.env().account_id();
+            let balance_before = PSP22Ref::balance_of(&asset, this);
+            self._deposit(params.pool, asset, amount)
+                .map_err(Self::_pool_error)?;
+            self._withdraw_collateral(params.pool, params.old_collateral, params.old_amount, this)
+                .map_err(Self::_pool_error)?;
+
+            PSP22Ref::approve(&params.old_collateral, params.pair, params.old_amount)?;
+            // the pair transfers the output to this contract, so reentry has to be allowed
+            AmmPairRef::swap_builder(
+                &params.pair,
+                params.old_collateral,
+                params.old_amount,
+                0,
+                this,
+            )
+            .call_flags(CallFlags::default().set_allow_reentry(true))
+            .fire()
+            .unwrap()
+            .map_err(|error: AmmPairError| {
+                FlashLoanReceiverError::Custom(format!("Swap failed: {:?}", error))
+            })?;
+
+            if PSP22Ref::balance_of(&asset, this) < balance_before + fee {
+                return Err(FlashLoanReceiverError::Custom(String::from(
+                    "Sold collateral does not cover the loan",
+                )));
+            }
+            Ok(())
+        }
+
+        fn _deposit(
+            &self,
+            pool: AccountId,
+            asset: AccountId,
+            amount: Balance,
+        ) -> Result<(), LendingPoolError> {
+            let this = self.env().account_id();
+            PSP22Ref::approve(&asset, pool, amount)?;
+            LendingPoolRef::deposit(&pool, asset, amount, this)
+        }
+
+        /// returns the repaid amount
+        fn _repay(
+            &self,
+            pool: AccountId,
+            asset: AccountId,
+            amount: Balance,
+        ) -> Result<Balance, LendingPoolError> {
+            let this = self.env().account_id();
+            PSP22Ref::approve(&asset, pool, amount)?;
+            let repaid = LendingPoolRef::repay(&pool, asset, amount, this)?;
+            // resets the allowance left if less than `amount` was repaid
+            PSP22Ref::approve(&asset, pool, 0)?;
+            Ok(repaid)
+        }
+
+        // the pool transfers assets to this contract, so reentry has to be allowed
+        fn _withdraw_collateral(
+            &self,
+            pool: AccountId,
+            asset: AccountId,
+            amount: Balance,
+            to: AccountId,
+        ) -> Result<(), LendingPoolError> {
+            LendingPoolRef::withdraw_builder(&pool, asset, amount, to)
+                .call_flags(CallFlags::default().set_allow_reentry(true))
+                .fire()
+                .unwrap()
+        }
+
+        fn _borrow(
+            &self,
+            pool: AccountId,
+            asset: AccountId,
+            amount: Balance,
+        ) -> Result<(), LendingPoolError> {
+            LendingPoolRef::borrow_builder(&pool, asset, amount)
+                .call_flags(CallFlags::default().set_allow_reentry(true))
+                .fire()
+                .unwrap()
+        }
+
+        fn _pool_error(error: LendingPoolError) -> FlashLoanReceiverError {
+            FlashLoanReceiverError::Custom(format!("Lending pool error: {:?}", error))
+        }
+    }
+
+    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum RefinanceReceiverError {
+        OwnableError(OwnableError),
+        PSP22Error(PSP22Error),
+        LendingPoolError(LendingPoolError),
+    }
+
+    impl From<OwnableError> for RefinanceReceiverError {
+        fn from(error: OwnableError) -> Self {
+            RefinanceReceiverError::OwnableError(error)
+        }
+    }
+
+    impl From<PSP22Error> for RefinanceReceiverError {
+        fn from(error: PSP22Error) -> Self {
+            RefinanceReceiverError::PSP22Error(error)
+        }
+    }
+
+    impl From<LendingPoolError> for RefinanceReceiverError {
+        fn from(error: LendingPoolError) -> Self {
+            RefinanceReceiverError::LendingPoolError(error)
+        }
+    }
+}
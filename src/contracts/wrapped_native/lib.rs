@@ -0,0 +1,201 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+#![feature(min_specialization)]
+
+#[openbrush::contract]
+pub mod wrapped_native {
+    use flash_loan::impls::flash_loan::*;
+    use flash_loan::traits::{asset_registry::FeeModel, flash_loan::*, wrapped_native::*};
+    use ink_lang::codegen::{EmitEvent, Env};
+    use ink_prelude::{string::*, vec, vec::Vec};
+    use ink_storage::traits::SpreadAllocate;
+    use openbrush::{
+        contracts::{ownable::*, psp22::extensions::metadata::*},
+        traits::{Flush, Storage},
+    };
+
+    #[ink(storage)]
+    #[derive(Default, SpreadAllocate, Storage)]
+    pub struct WrappedNativeContract {
+        #[storage_field]
+        psp22: psp22::Data,
+        #[storage_field]
+        metadata: metadata::Data,
+        #[storage_field]
+        ownable: ownable::Data,
+        #[storage_field]
+        flash_loan: flash_loan::impls::flash_loan::Data,
+
+        flash_fee_e6: u128,
+    }
+
+    #[ink(event)]
+    pub struct FlashLoanEvent {
+        #[ink(topic)]
+        loan_id: LoanId,
+        #[ink(topic)]
+        receiver: AccountId,
+        #[ink(topic)]
+        asset: AccountId,
+        amount: Balance,
+        fee: Balance,
+    }
+
+    impl PSP22 for WrappedNativeContract {}
+
+    impl PSP22Metadata for WrappedNativeContract {}
+
+    impl Ownable for WrappedNativeContract {}
+
+    impl WrappedNative for WrappedNativeContract {
+        #[ink(message, payable)]
+        fn deposit(&mut self) -> Result<(), PSP22Error> {
+            let value = self.env().transferred_value();
+            self._mint(self.env().caller(), value)
+        }
+
+        #[ink(message)]
+        fn withdraw(&mut self, amount: Balance) -> Result<(), PSP22Error> {
+            let caller = self.env().caller();
+            self._burn_from(caller, amount)?;
+            self.env()
+                .transfer(caller, amount)
+                .map_err(|_| PSP22Error::Custom(String::from("NativeTransferFailed")))
+        }
+    }
+
+    impl FlashLoanEventEmit for WrappedNativeContract {
+        fn _emit_flash_loan_event(
+            &mut self,
+            loan_id: LoanId,
+            receiver: AccountId,
+            asset: AccountId,
+            amount: Balance,
+            fee: Balance,
+        ) {
+            self.env().emit_event(FlashLoanEvent {
+                loan_id,
+                receiver,
+                asset,
+                amount,
+                fee,
+            });
+        }
+    }
+
+    /// flash-mint of the token itself, the fee goes to the owner
+    #[allow(unused_variables)]
+    impl FlashLoanInternal for WrappedNativeContract {
+        fn _before_flash_loan(
+            &mut self,
+            loan_id: LoanId,
+            receiver: &AccountId,
+            assets: &Vec<AccountId>,
+            amounts: &Vec<Balance>,
+            data: &mut Vec<u8>,
+        ) -> Result<(), FlashLoanError> {
+            if assets.iter().any(|asset| *asset != self.env().account_id()) {
+                return Err(FlashLoanError::Custom(String::from("NotWrappedNative")));
+            }
+            Ok(())
+        }
+
+        fn _quote_fees(
+            &self,
+            assets: &Vec<AccountId>,
+            amounts: &Vec<Balance>,
+        ) -> Result<Vec<Balance>, FlashLoanError> {
+            let mut fees: Vec<Balance> = vec![0; assets.len()];
+            for i in 0..assets.len() {
+                fees[i] = FeeModel::Proportional {
+                    fee_e6: self.flash_fee_e6,
+                }
+                .fee(amounts[i]);
+            }
+            Ok(fees)
+        }
+
+        fn _max_flash_loan(&self, asset: &AccountId) -> Balance {
+            if *asset != self.env().account_id() {
+                return 0;
+            }
+            Balance::MAX - self.total_supply()
+        }
+
+        /// the fee goes to the owner, the balance of the contract does not change
+        fn _solvency_balance(&self, asset: &AccountId) -> Result<Balance, FlashLoanError> {
+            Ok(self._balance_of(&self.owner()))
+        }
+
+        fn _send_flash_loan(
+            &mut self,
+            receiver: &AccountId,
+            assets: &Vec<AccountId>,
+            amounts: &Vec<Balance>,
+            fees: &Vec<Balance>,
+            data: &mut Vec<u8>,
+        ) -> Result<Vec<Balance>, FlashLoanError> {
+            for i in 0..assets.len() {
+                FlashLoanInternal::_before_send_asset(self, i, &assets[i], amounts[i], fees[i])?;
+                self._mint(*receiver, amounts[i])?;
+                FlashLoanInternal::_after_send_asset(self, i, &assets[i], amounts[i], fees[i])?;
+            }
+            // the state is loaded from storage after sending, and the receiver may call this contract (e.g. withdraw)
+            self.flush();
+            Ok(amounts.clone())
+        }
+
+        fn _get_back_flash_loan(
+            &mut self,
+            receiver: &AccountId,
+            assets: &Vec<AccountId>,
+            amounts: &Vec<Balance>,
+            fees: &Vec<Balance>,
+            data: &mut Vec<u8>,
+        ) -> Result<Vec<Balance>, FlashLoanError> {
+            self.load();
+            let this = self.env().account_id();
+            let fee_recipient = self.owner();
+            for i in 0..assets.len() {
+                FlashLoanInternal::_before_repay_asset(self, i, &assets[i], amounts[i], fees[i])?;
+                let to_repay = amounts[i]
+                    .checked_add(fees[i])
+                    .ok_or(FlashLoanError::Overflow)?;
+                let allowance = self.allowance(*receiver, this);
+                if allowance < to_repay {
+                    return Err(FlashLoanError::PSP22Error(
+                        PSP22Error::InsufficientAllowance,
+                    ));
+                }
+                self._approve_from_to(*receiver, this, allowance - to_repay)?;
+                self._burn_from(*receiver, amounts[i])?;
+                if fees[i] > 0 {
+                    self._transfer_from_to(*receiver, fee_recipient, fees[i], vec![])?;
+                }
+                FlashLoanInternal::_after_repay_asset(self, i, &assets[i], amounts[i], fees[i])?;
+            }
+            // the state is loaded from storage after pulling back
+            self.flush();
+            Ok(fees.clone())
+        }
+    }
+
+    impl FlashLoan for WrappedNativeContract {}
+
+    impl WrappedNativeContract {
+        #[ink(constructor)]
+        pub fn new(
+            name: Option<String>,
+            symbol: Option<String>,
+            decimal: u8,
+            flash_fee_e6: u128,
+        ) -> Self {
+            ink_lang::codegen::initialize_contract(|instance: &mut Self| {
+                instance._init_with_owner(instance.env().caller());
+                instance.metadata.name = name;
+                instance.metadata.symbol = symbol;
+                instance.metadata.decimals = decimal;
+                instance.flash_fee_e6 = flash_fee_e6;
+            })
+        }
+    }
+}
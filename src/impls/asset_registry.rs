@@ -0,0 +1,145 @@
+use crate::impls::loanable_asset::PSP22Asset;
+use crate::storage::EnumerableMap;
+use crate::traits::{flash_loan::FlashLoanError, loanable_asset::LoanableAsset};
+pub use crate::{impls::asset_registry, traits::asset_registry::*};
+use ink_prelude::vec::Vec;
+
+use openbrush::contracts::ownable::{self, only_owner};
+use openbrush::modifiers;
+use openbrush::traits::{AccountId, Balance, Storage};
+
+pub const STORAGE_KEY: u32 = openbrush::storage_unique_key!(Data);
+
+#[derive(Default, Debug)]
+#[openbrush::upgradeable_storage(STORAGE_KEY)]
+pub struct Data {
+    pub assets: EnumerableMap<AccountId, AssetInfo>,
+    pub _reserved: Option<()>,
+}
+
+pub trait AssetRegistryEvents {
+    /// !!! should be overriden in contract !!!
+    /// emits AssetListed event
+    fn _emit_asset_listed_event(&self, asset: AccountId, info: AssetInfo);
+
+    /// !!! should be overriden in contract !!!
+    /// emits AssetDelisted event
+    fn _emit_asset_delisted_event(&self, asset: AccountId);
+}
+
+impl<T> AssetRegistryEvents for T {
+    default fn _emit_asset_listed_event(&self, _asset: AccountId, _info: AssetInfo) {}
+
+    default fn _emit_asset_delisted_event(&self, _asset: AccountId) {}
+}
+
+pub trait AssetRegistryInternal {
+    /// returns info of `asset` if it is listed, enabled and `amount` does not exceed its cap
+    fn _supported_asset(
+        &self,
+        asset: &AccountId,
+        amount: Balance,
+    ) -> Result<AssetInfo, FlashLoanError>;
+
+    /// returns fee for lending `amount` of `asset` according to its fee model, fails like `_supported_asset`
+    fn _registry_fee(&self, asset: &AccountId, amount: Balance) -> Result<Balance, FlashLoanError>;
+
+    /// may be overriden in contract
+    /// returns amount of `asset` that can be lent. By default it is the PSP22 balance of the contract.
+    fn _asset_liquidity(&self, asset: &AccountId) -> Balance;
+
+    /// lists `asset` or updates its config, `listed_at` and `decimals` are kept on update
+    fn _list_asset(&mut self, asset: &AccountId, config: AssetConfig) -> AssetInfo;
+
+    fn _delist_asset(&mut self, asset: &AccountId) -> Result<(), AssetRegistryError>;
+}
+
+impl<T: Storage<Data>> AssetRegistryInternal for T {
+    default fn _supported_asset(
+        &self,
+        asset: &AccountId,
+        amount: Balance,
+    ) -> Result<AssetInfo, FlashLoanError> {
+        let info = match self.data().assets.get(asset) {
+            Some(info) if info.config.enabled => info,
+            _ => return Err(FlashLoanError::AssetNotSupported),
+        };
+        if amount > info.config.max_loan.unwrap_or(Balance::MAX) {
+            return Err(FlashLoanError::LoanCapExceeded);
+        }
+        Ok(info)
+    }
+
+    default fn _registry_fee(
+        &self,
+        asset: &AccountId,
+        amount: Balance,
+    ) -> Result<Balance, FlashLoanError> {
+        let info = AssetRegistryInternal::_supported_asset(self, asset, amount)?;
+        Ok(info.config.fee_model.fee(amount))
+    }
+
+    default fn _asset_liquidity(&self, asset: &AccountId) -> Balance {
+        PSP22Asset::new(*asset).balance_of(Self::env().account_id())
+    }
+
+    default fn _list_asset(&mut self, asset: &AccountId, config: AssetConfig) -> AssetInfo {
+        let info = match self.data().assets.get(asset) {
+            Some(info) => AssetInfo { config, ..info },
+            None => AssetInfo {
+                config,
+                decimals: PSP22Asset::new(*asset).metadata().decimals,
+                listed_at: Self::env().block_timestamp(),
+            },
+        };
+        self.data().assets.insert(asset, &info);
+        info
+    }
+
+    default fn _delist_asset(&mut self, asset: &AccountId) -> Result<(), AssetRegistryError> {
+        if !self.data().assets.remove(asset) {
+            return Err(AssetRegistryError::AssetNotListed);
+        }
+        Ok(())
+    }
+}
+
+impl<T: Storage<Data> + Storage<ownable::Data>> AssetRegistry for T {
+    #[modifiers(only_owner)]
+    default fn list_asset(
+        &mut self,
+        asset: AccountId,
+        config: AssetConfig,
+    ) -> Result<(), AssetRegistryError> {
+        let info = self._list_asset(&asset, config);
+        self._emit_asset_listed_event(asset, info);
+        Ok(())
+    }
+
+    #[modifiers(only_owner)]
+    default fn delist_asset(&mut self, asset: AccountId) -> Result<(), AssetRegistryError> {
+        self._delist_asset(&asset)?;
+        self._emit_asset_delisted_event(asset);
+        Ok(())
+    }
+
+    default fn asset_info(&self, asset: AccountId) -> Option<AssetInfo> {
+        self.data::<Data>().assets.get(&asset)
+    }
+
+    default fn supported_assets(
+        &self,
+        offset: u32,
+        limit: u32,
+    ) -> Vec<(AccountId, AssetInfo, Balance)> {
+        self.data::<Data>()
+            .assets
+            .page(offset, limit)
+            .into_iter()
+            .map(|(asset, info)| {
+                let liquidity = AssetRegistryInternal::_asset_liquidity(self, &asset);
+                (asset, info, liquidity)
+            })
+            .collect()
+    }
+}
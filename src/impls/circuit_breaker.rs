@@ -0,0 +1,164 @@
+use crate::impls::flash_loan::{self, when_not_in_flash_loan};
+use crate::impls::loanable_asset::PSP22Asset;
+use crate::traits::{flash_loan::FlashLoanError, loanable_asset::LoanableAsset};
+pub use crate::{impls::circuit_breaker, traits::circuit_breaker::*};
+
+use openbrush::contracts::ownable::{self, only_owner};
+use openbrush::modifiers;
+use openbrush::storage::Mapping;
+use openbrush::traits::{AccountId, Balance, Storage};
+
+pub const STORAGE_KEY: u32 = openbrush::storage_unique_key!(Data);
+
+#[derive(Default, Debug)]
+#[openbrush::upgradeable_storage(STORAGE_KEY)]
+pub struct Data {
+    pub thresholds: Mapping<AccountId, Balance>,
+    pub anomalies: Mapping<AccountId, Balance>,
+    pub paused: Mapping<AccountId, bool>,
+    /// balance of the asset recorded at the end of the last loan or check
+    pub last_balances: Mapping<AccountId, Balance>,
+    pub _reserved: Option<()>,
+}
+
+pub trait CircuitBreakerEvents {
+    /// !!! should be overriden in contract !!!
+    /// emits AssetPaused event
+    fn _emit_asset_paused_event(&self, asset: AccountId, anomalies: Balance);
+
+    /// !!! should be overriden in contract !!!
+    /// emits AssetUnpaused event
+    fn _emit_asset_unpaused_event(&self, asset: AccountId);
+}
+
+impl<T> CircuitBreakerEvents for T {
+    default fn _emit_asset_paused_event(&self, _asset: AccountId, _anomalies: Balance) {}
+
+    default fn _emit_asset_unpaused_event(&self, _asset: AccountId) {}
+}
+
+pub trait CircuitBreakerInternal {
+    /// may be overriden in contract
+    /// returns balance of `asset` watched by the breaker. By default it is the PSP22 balance of the contract.
+    fn _watched_balance(&self, asset: &AccountId) -> Balance;
+
+    fn _ensure_not_paused(&self, asset: &AccountId) -> Result<(), FlashLoanError>;
+
+    /// counts a drop of the watched balance since it was recorded as an anomaly and records the current balance.
+    /// Must not be called during a flash loan, lent amounts would be counted as a drop.
+    fn _check_balance(&mut self, asset: &AccountId);
+
+    /// records the current watched balance, should be called after the contract moves `asset` on purpose
+    fn _sync_balance(&mut self, asset: &AccountId);
+
+    /// adds `amount` to anomalies of `asset` and pauses it if they reach its threshold
+    fn _record_anomaly(&mut self, asset: &AccountId, amount: Balance);
+
+    fn _pause_asset(&mut self, asset: &AccountId);
+
+    fn _unpause_asset(&mut self, asset: &AccountId);
+}
+
+impl<T: Storage<Data>> CircuitBreakerInternal for T {
+    default fn _watched_balance(&self, asset: &AccountId) -> Balance {
+        PSP22Asset::new(*asset).balance_of(Self::env().account_id())
+    }
+
+    default fn _ensure_not_paused(&self, asset: &AccountId) -> Result<(), FlashLoanError> {
+        if self.data().paused.get(asset).unwrap_or_default() {
+            return Err(FlashLoanError::AssetPaused);
+        }
+        Ok(())
+    }
+
+    default fn _check_balance(&mut self, asset: &AccountId) {
+        let balance = CircuitBreakerInternal::_watched_balance(self, asset);
+        if let Some(last_balance) = self.data().last_balances.get(asset) {
+            if balance < last_balance {
+                CircuitBreakerInternal::_record_anomaly(self, asset, last_balance - balance);
+            }
+        }
+        self.data().last_balances.insert(asset, &balance);
+    }
+
+    default fn _sync_balance(&mut self, asset: &AccountId) {
+        let balance = CircuitBreakerInternal::_watched_balance(self, asset);
+        self.data().last_balances.insert(asset, &balance);
+    }
+
+    default fn _record_anomaly(&mut self, asset: &AccountId, amount: Balance) {
+        let anomalies = self
+            .data()
+            .anomalies
+            .get(asset)
+            .unwrap_or_default()
+            .saturating_add(amount);
+        self.data().anomalies.insert(asset, &anomalies);
+        match self.data().thresholds.get(asset) {
+            Some(threshold) if anomalies >= threshold => {
+                CircuitBreakerInternal::_pause_asset(self, asset)
+            }
+            _ => {}
+        }
+    }
+
+    default fn _pause_asset(&mut self, asset: &AccountId) {
+        self.data().paused.insert(asset, &true);
+        let anomalies = self.data().anomalies.get(asset).unwrap_or_default();
+        self._emit_asset_paused_event(*asset, anomalies);
+    }
+
+    default fn _unpause_asset(&mut self, asset: &AccountId) {
+        self.data().paused.remove(asset);
+        self.data().anomalies.remove(asset);
+        self._emit_asset_unpaused_event(*asset);
+    }
+}
+
+impl<T: Storage<Data> + Storage<ownable::Data> + Storage<flash_loan::Data>> CircuitBreaker for T {
+    #[modifiers(when_not_in_flash_loan)]
+    default fn check_asset(&mut self, asset: AccountId) -> Result<(), CircuitBreakerError> {
+        CircuitBreakerInternal::_check_balance(self, &asset);
+        Ok(())
+    }
+
+    #[modifiers(only_owner)]
+    default fn set_anomaly_threshold(
+        &mut self,
+        asset: AccountId,
+        threshold: Option<Balance>,
+    ) -> Result<(), CircuitBreakerError> {
+        match threshold {
+            Some(threshold) => self.data::<Data>().thresholds.insert(&asset, &threshold),
+            None => self.data::<Data>().thresholds.remove(&asset),
+        }
+        Ok(())
+    }
+
+    #[modifiers(only_owner)]
+    default fn pause_asset(&mut self, asset: AccountId) -> Result<(), CircuitBreakerError> {
+        CircuitBreakerInternal::_pause_asset(self, &asset);
+        Ok(())
+    }
+
+    #[modifiers(only_owner)]
+    default fn unpause_asset(&mut self, asset: AccountId) -> Result<(), CircuitBreakerError> {
+        CircuitBreakerInternal::_unpause_asset(self, &asset);
+        Ok(())
+    }
+
+    default fn anomaly_threshold(&self, asset: AccountId) -> Option<Balance> {
+        self.data::<Data>().thresholds.get(&asset)
+    }
+
+    default fn anomalies(&self, asset: AccountId) -> Balance {
+        self.data::<Data>()
+            .anomalies
+            .get(&asset)
+            .unwrap_or_default()
+    }
+
+    default fn is_asset_paused(&self, asset: AccountId) -> bool {
+        self.data::<Data>().paused.get(&asset).unwrap_or_default()
+    }
+}
@@ -0,0 +1,95 @@
+use crate::impls::flash_loan::{self, when_not_in_flash_loan};
+use crate::traits::asset_registry::FeeModel;
+pub use crate::{impls::fee_oracle, traits::fee_oracle::*};
+use ink_prelude::vec::Vec;
+
+use openbrush::contracts::ownable::{self, only_owner};
+use openbrush::modifiers;
+use openbrush::traits::{AccountId, Balance, Storage};
+
+pub const STORAGE_KEY: u32 = openbrush::storage_unique_key!(Data);
+
+#[derive(Default, Debug)]
+#[openbrush::upgradeable_storage(STORAGE_KEY)]
+pub struct Data {
+    pub config: Option<FeeOracleConfig>,
+    pub _reserved: Option<()>,
+}
+
+pub trait FeeOracleEvents {
+    /// !!! should be overriden in contract !!!
+    /// emits FeeOracleSet event
+    fn _emit_fee_oracle_set_event(&self, config: Option<FeeOracleConfig>);
+}
+
+impl<T> FeeOracleEvents for T {
+    default fn _emit_fee_oracle_set_event(&self, _config: Option<FeeOracleConfig>) {}
+}
+
+pub trait FeeOracleInternal {
+    /// returns fees of the loan decided by the fee oracle, e.g. in `_calculate_fees`. None if the oracle is not set.
+    /// Fees above the cap are lowered to it. If the oracle call fails or returns a wrong number of fees
+    /// the fallback fee model is used.
+    fn _oracle_fees(
+        &self,
+        receiver: &AccountId,
+        assets: &Vec<AccountId>,
+        amounts: &Vec<Balance>,
+    ) -> Option<Vec<Balance>>;
+}
+
+impl<T: Storage<Data>> FeeOracleInternal for T {
+    default fn _oracle_fees(
+        &self,
+        receiver: &AccountId,
+        assets: &Vec<AccountId>,
+        amounts: &Vec<Balance>,
+    ) -> Option<Vec<Balance>> {
+        let config = self.data().config?;
+        let result = FeeOracleRef::quote_fees_builder(
+            &config.oracle,
+            Self::env().caller(),
+            *receiver,
+            assets.clone(),
+            amounts.clone(),
+        )
+        .gas_limit(config.gas_limit)
+        .fire();
+        Some(match result {
+            Ok(Ok(fees)) if fees.len() == assets.len() => fees
+                .iter()
+                .zip(amounts.iter())
+                .map(|(fee, amount)| {
+                    (*fee).min(
+                        FeeModel::Proportional {
+                            fee_e6: config.max_fee_e6,
+                        }
+                        .fee(*amount),
+                    )
+                })
+                .collect(),
+            _ => amounts
+                .iter()
+                .map(|amount| config.fallback.fee(*amount))
+                .collect(),
+        })
+    }
+}
+
+impl<T: Storage<Data> + Storage<ownable::Data> + Storage<flash_loan::Data>> FeeOracleSettings
+    for T
+{
+    #[modifiers(only_owner, when_not_in_flash_loan)]
+    default fn set_fee_oracle(
+        &mut self,
+        config: Option<FeeOracleConfig>,
+    ) -> Result<(), FeeOracleSettingsError> {
+        self.data::<Data>().config = config;
+        self._emit_fee_oracle_set_event(config);
+        Ok(())
+    }
+
+    default fn fee_oracle(&self) -> Option<FeeOracleConfig> {
+        self.data::<Data>().config
+    }
+}
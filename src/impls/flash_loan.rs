@@ -1,23 +1,72 @@
 // TODO::think should we emit events on set_as_collateral
 
 #![allow(unused_variables)]
+use crate::impls::loanable_asset::{Asset, PSP22Asset};
 use crate::traits::{
-    flash_loan::FlashLoanError, flash_loan::*, flash_loan_receiver::FlashLoanReceiverRef,
+    flash_loan::FlashLoanError,
+    flash_loan::*,
+    flash_loan_policy::LoanRequest,
+    flash_loan_receiver::FlashLoanReceiverRef,
+    loanable_asset::{LoanableAsset, TokenQuirkPolicy},
 };
+use ink_env::hash::Blake2x256;
 use ink_env::CallFlags;
-use ink_prelude::{string::*, vec, vec::Vec};
+use ink_prelude::{vec, vec::Vec};
+use primitive_types::U256;
 
+use openbrush::modifier_definition;
+use openbrush::storage::Mapping;
 use openbrush::traits::DefaultEnv;
-use openbrush::{
-    contracts::traits::psp22::PSP22Ref,
-    traits::{AccountId, Balance},
-};
+use openbrush::traits::{AccountId, Balance, Flush, Hash, Storage};
+
+pub const STORAGE_KEY: u32 = openbrush::storage_unique_key!(Data);
+
+/// number of the latest loan receipts that are kept
+pub const MAX_LOAN_RECEIPTS: LoanId = 256;
+
+#[derive(Default, Debug)]
+#[openbrush::upgradeable_storage(STORAGE_KEY)]
+pub struct Data {
+    /// number of flash loans in progress, nested loans are counted
+    pub loans_in_progress: u32,
+    /// lent amounts that were not taken back yet
+    pub outstanding: Mapping<AccountId, Balance>,
+    /// id of the latest loan, 0 if there was none
+    pub last_loan_id: LoanId,
+    /// receipt of loan `id` is at key `id % MAX_LOAN_RECEIPTS`
+    pub receipts: Mapping<LoanId, LoanReceipt>,
+    pub _reserved: Option<()>,
+}
+
+/// rejects the call while a flash loan of the contract is in progress
+#[modifier_definition]
+pub fn when_not_in_flash_loan<T, F, R, E>(instance: &mut T, body: F) -> Result<R, E>
+where
+    T: Storage<Data>,
+    F: FnOnce(&mut T) -> Result<R, E>,
+    E: From<FlashLoanError>,
+{
+    if instance.data().loans_in_progress > 0 {
+        return Err(From::from(FlashLoanError::FlashLoanInProgress));
+    }
+    body(instance)
+}
+
+/// fails with `FlashLoanInProgress` if `lender` is in the middle of a flash loan.
+/// Contracts that read balances of the lender (e.g. as a price source) can call it first.
+pub fn ensure_not_in_flash_loan(lender: &AccountId) -> Result<(), FlashLoanError> {
+    if FlashLoanRef::is_in_flash_loan(lender) {
+        return Err(FlashLoanError::FlashLoanInProgress);
+    }
+    Ok(())
+}
 
 pub trait FlashLoanEventEmit {
     /// !!! should be overriden in contract !!!
     /// emits FlashLoanEvent
     fn _emit_flash_loan_event(
         &mut self,
+        loan_id: LoanId,
         receiver: AccountId,
         asset: AccountId,
         amount: Balance,
@@ -25,6 +74,7 @@ pub trait FlashLoanEventEmit {
     );
     fn _emit_flash_loan_events(
         &mut self,
+        loan_id: LoanId,
         receiver: &AccountId,
         assets: &Vec<AccountId>,
         amounts: &Vec<Balance>,
@@ -35,6 +85,7 @@ pub trait FlashLoanEventEmit {
 impl<T> FlashLoanEventEmit for T {
     default fn _emit_flash_loan_event(
         &mut self,
+        loan_id: LoanId,
         receiver: AccountId,
         asset: AccountId,
         amount: Balance,
@@ -43,22 +94,41 @@ impl<T> FlashLoanEventEmit for T {
     }
     default fn _emit_flash_loan_events(
         &mut self,
+        loan_id: LoanId,
         receiver: &AccountId,
         assets: &Vec<AccountId>,
         amounts: &Vec<Balance>,
         fees: &Vec<Balance>,
     ) -> Result<(), FlashLoanError> {
         for i in 0..assets.len() {
-            self._emit_flash_loan_event(*receiver, assets[i], amounts[i], fees[i]);
+            self._emit_flash_loan_event(loan_id, *receiver, assets[i], amounts[i], fees[i]);
         }
         Ok(())
     }
 }
 
 pub trait FlashLoanInternal {
+    /// may be overriden in contract
+    /// is called first for every loan, true ends the call with Ok before the loan is checked and nothing is lent.
+    /// Unlike an error it keeps what the hook stored, e.g. an asset paused by the circuit breaker. By default it is false.
+    fn _halt_loan(&mut self, assets: &Vec<AccountId>) -> bool;
+
+    /// may be overriden in contract, e.g. to run the pre-checks of the policy chain
+    /// is called for every loan that is not halted, an error rejects the loan
+    fn _pre_check_loan(&mut self, loan: &LoanRequest) -> Result<(), FlashLoanError>;
+
+    /// may be overriden in contract, e.g. to run the post-checks of the policy chain
+    /// is called last for every repaid loan, an error reverts the loan
+    fn _post_check_loan(
+        &mut self,
+        loan: &LoanRequest,
+        fees: &Vec<Balance>,
+    ) -> Result<(), FlashLoanError>;
+
     /// may be overriden in contract to perform any operation before sending flash laon
     fn _before_flash_loan(
         &mut self,
+        loan_id: LoanId,
         receiver: &AccountId,
         assets: &Vec<AccountId>,
         amounts: &Vec<Balance>,
@@ -68,6 +138,7 @@ pub trait FlashLoanInternal {
     /// may be overriden in contract to perform any operation after sending flash laon
     fn _after_flash_loan(
         &mut self,
+        loan_id: LoanId,
         receiver: &AccountId,
         assets: &Vec<AccountId>,
         amounts: &Vec<Balance>,
@@ -75,8 +146,9 @@ pub trait FlashLoanInternal {
         data: &Vec<u8>,
     ) -> Result<(), FlashLoanError>;
 
-    /// !!! should be overriden in contract !!!
+    /// may be overriden in contract
     ///  returns list of Balances (amounts) of fee that will be taken after fhlash loan.
+    /// By default fees are the ones returned by `_quote_fees`.
     fn _calculate_fees(
         &mut self,
         receiver: &AccountId,
@@ -85,18 +157,52 @@ pub trait FlashLoanInternal {
         data: &mut Vec<u8>,
     ) -> Result<Vec<Balance>, FlashLoanError>;
 
+    /// !!! should be overriden in contract !!!
+    ///  returns list of Balances (amounts) of fee for lending `amounts` of `assets` to any receiver.
+    fn _quote_fees(
+        &self,
+        assets: &Vec<AccountId>,
+        amounts: &Vec<Balance>,
+    ) -> Result<Vec<Balance>, FlashLoanError>;
+
+    /// may be overriden in contract
+    /// returns the maximum amount of `asset` that can be lent. By default it is the balance of the contract.
+    fn _max_flash_loan(&self, asset: &AccountId) -> Balance;
+
+    /// may be overriden in contract to lend assets other than PSP22
+    /// returns the asset that `asset` stands for. By default every asset is a PSP22 token.
+    /// Call it as `FlashLoanInternal::_loanable_asset(self, ..)` from `&mut self` methods,
+    /// `self._loanable_asset(..)` there does not reach the overriden version (min_specialization).
+    fn _loanable_asset(&self, asset: &AccountId) -> Result<Asset, FlashLoanError>;
+
+    /// may be overriden in contract
+    /// returns how PSP22 `asset` is treated if its balances do not change by the transferred amount. By default it is rejected.
+    fn _token_quirk_policy(&self, asset: &AccountId) -> TokenQuirkPolicy;
+
     /// may be overriden in contract
-    /// transfers assets to the receiver
+    /// returns balance of `asset` that must grow at least by the fee during a loan, otherwise the loan fails.
+    /// By default it is the balance of the contract.
+    fn _solvency_balance(&self, asset: &AccountId) -> Result<Balance, FlashLoanError>;
+
+    /// may be overriden in contract
+    /// transfers assets to the receiver and returns the amounts that arrived.
+    /// They may differ from `amounts` only for tokens with `TokenQuirkPolicy::Account`.
+    /// Assets may call back the contract, so the state is loaded from storage after it returns.
+    /// Overrides changing the state must flush it, the default implementation flushes around each transfer.
     fn _send_flash_loan(
         &mut self,
         receiver: &AccountId,
         assets: &Vec<AccountId>,
         amounts: &Vec<Balance>,
+        fees: &Vec<Balance>,
         data: &mut Vec<u8>,
-    ) -> Result<(), FlashLoanError>;
+    ) -> Result<Vec<Balance>, FlashLoanError>;
 
     /// may be overriden in contract
-    /// transfers assets back from the receiver to the contract
+    /// transfers `amounts` sent to the receiver and `fees` back to the contract and returns the fees that arrived.
+    /// They may differ from `fees` only for tokens with `TokenQuirkPolicy::Account`, whose fee is the balance change
+    /// of the contract minus the sent amount. Assets may call back the contract, so the state is loaded from storage
+    /// after it returns. Overrides changing the state must flush it, the default implementation flushes around each transfer.
     fn _get_back_flash_loan(
         &mut self,
         receiver: &AccountId,
@@ -104,11 +210,83 @@ pub trait FlashLoanInternal {
         amounts: &Vec<Balance>,
         fees: &Vec<Balance>,
         data: &mut Vec<u8>,
+    ) -> Result<Vec<Balance>, FlashLoanError>;
+
+    /// may be overriden in contract
+    /// calls `execute_operation` of the receiver with the lent `amounts` and the `fees` it owes.
+    /// The receiver may call the contract, so the state is in storage during the call and is loaded after it returns.
+    fn _execute_operation(
+        &mut self,
+        loan_id: LoanId,
+        receiver: &AccountId,
+        initiator: &AccountId,
+        assets: &Vec<AccountId>,
+        amounts: &Vec<Balance>,
+        fees: &Vec<Balance>,
+        data: &Vec<u8>,
+    ) -> Result<(), FlashLoanError>;
+
+    /// may be overriden in contract
+    /// is called by `_send_flash_loan` before `asset` at `index` of the loan is sent
+    fn _before_send_asset(
+        &mut self,
+        index: usize,
+        asset: &AccountId,
+        amount: Balance,
+        fee: Balance,
+    ) -> Result<(), FlashLoanError>;
+
+    /// may be overriden in contract
+    /// is called by `_send_flash_loan` after `asset` at `index` of the loan is sent
+    fn _after_send_asset(
+        &mut self,
+        index: usize,
+        asset: &AccountId,
+        amount: Balance,
+        fee: Balance,
+    ) -> Result<(), FlashLoanError>;
+
+    /// may be overriden in contract
+    /// is called by `_get_back_flash_loan` before `amount + fee` of `asset` at `index` of the loan is pulled back.
+    /// For tokens with `TokenQuirkPolicy::Account` `fee` also pays for what the token keeps.
+    fn _before_repay_asset(
+        &mut self,
+        index: usize,
+        asset: &AccountId,
+        amount: Balance,
+        fee: Balance,
+    ) -> Result<(), FlashLoanError>;
+
+    /// may be overriden in contract
+    /// is called by `_get_back_flash_loan` after `asset` at `index` of the loan is pulled back with the fee that arrived
+    fn _after_repay_asset(
+        &mut self,
+        index: usize,
+        asset: &AccountId,
+        amount: Balance,
+        fee: Balance,
     ) -> Result<(), FlashLoanError>;
 }
-impl<T: FlashLoanEventEmit> FlashLoanInternal for T {
+impl<T: FlashLoanEventEmit + Flush> FlashLoanInternal for T {
+    default fn _halt_loan(&mut self, assets: &Vec<AccountId>) -> bool {
+        false
+    }
+
+    default fn _pre_check_loan(&mut self, loan: &LoanRequest) -> Result<(), FlashLoanError> {
+        Ok(())
+    }
+
+    default fn _post_check_loan(
+        &mut self,
+        loan: &LoanRequest,
+        fees: &Vec<Balance>,
+    ) -> Result<(), FlashLoanError> {
+        Ok(())
+    }
+
     default fn _before_flash_loan(
         &mut self,
+        loan_id: LoanId,
         receiver: &AccountId,
         assets: &Vec<AccountId>,
         amounts: &Vec<Balance>,
@@ -119,6 +297,7 @@ impl<T: FlashLoanEventEmit> FlashLoanInternal for T {
 
     default fn _after_flash_loan(
         &mut self,
+        loan_id: LoanId,
         receiver: &AccountId,
         assets: &Vec<AccountId>,
         amounts: &Vec<Balance>,
@@ -133,25 +312,69 @@ impl<T: FlashLoanEventEmit> FlashLoanInternal for T {
         assets: &Vec<AccountId>,
         amounts: &Vec<Balance>,
         data: &mut Vec<u8>,
+    ) -> Result<Vec<Balance>, FlashLoanError> {
+        FlashLoanInternal::_quote_fees(self, assets, amounts)
+    }
+
+    default fn _quote_fees(
+        &self,
+        assets: &Vec<AccountId>,
+        amounts: &Vec<Balance>,
     ) -> Result<Vec<Balance>, FlashLoanError> {
         let fees: Vec<Balance> = vec![0; assets.len()];
         Ok(fees)
     }
 
+    default fn _max_flash_loan(&self, asset: &AccountId) -> Balance {
+        match self._loanable_asset(asset) {
+            Ok(asset) => asset.balance_of(Self::env().account_id()),
+            Err(_) => 0,
+        }
+    }
+
+    default fn _loanable_asset(&self, asset: &AccountId) -> Result<Asset, FlashLoanError> {
+        Ok(Asset::PSP22(PSP22Asset::with_quirk_policy(
+            *asset,
+            FlashLoanInternal::_token_quirk_policy(self, asset),
+        )))
+    }
+
+    default fn _token_quirk_policy(&self, asset: &AccountId) -> TokenQuirkPolicy {
+        TokenQuirkPolicy::Reject
+    }
+
+    default fn _solvency_balance(&self, asset: &AccountId) -> Result<Balance, FlashLoanError> {
+        Ok(FlashLoanInternal::_loanable_asset(self, asset)?.balance_of(Self::env().account_id()))
+    }
+
     default fn _send_flash_loan(
         &mut self,
         receiver: &AccountId,
         assets: &Vec<AccountId>,
         amounts: &Vec<Balance>,
+        fees: &Vec<Balance>,
         data: &mut Vec<u8>,
-    ) -> Result<(), FlashLoanError> {
+    ) -> Result<Vec<Balance>, FlashLoanError> {
+        let mut received: Vec<Balance> = amounts.clone();
         for i in 0..assets.len() {
-            PSP22Ref::transfer_builder(&assets[i], *receiver, amounts[i], Vec::<u8>::new())
-                .call_flags(CallFlags::default().set_allow_reentry(true))
-                .fire()
-                .unwrap()?;
+            FlashLoanInternal::_before_send_asset(self, i, &assets[i], amounts[i], fees[i])?;
+            let asset = FlashLoanInternal::_loanable_asset(self, &assets[i])?;
+            self.flush();
+            if is_accounted(&asset) {
+                let balance_before = asset.balance_of(*receiver);
+                asset.send(*receiver, amounts[i])?;
+                received[i] = asset
+                    .balance_of(*receiver)
+                    .checked_sub(balance_before)
+                    .unwrap_or_default();
+            } else {
+                asset.send(*receiver, amounts[i])?;
+            }
+            self.load();
+            FlashLoanInternal::_after_send_asset(self, i, &assets[i], amounts[i], fees[i])?;
         }
-        Ok(())
+        self.flush();
+        Ok(received)
     }
 
     default fn _get_back_flash_loan(
@@ -161,26 +384,100 @@ impl<T: FlashLoanEventEmit> FlashLoanInternal for T {
         amounts: &Vec<Balance>,
         fees: &Vec<Balance>,
         data: &mut Vec<u8>,
-    ) -> Result<(), FlashLoanError> {
+    ) -> Result<Vec<Balance>, FlashLoanError> {
+        let this = Self::env().account_id();
+        let mut earned: Vec<Balance> = fees.clone();
         for i in 0..assets.len() {
-            ink_env::debug_println!("[flash] before transfer_from");
-            PSP22Ref::transfer_from_builder(
-                &assets[i],
-                *receiver,
-                Self::env().account_id(),
-                amounts[i] + fees[i],
-                Vec::<u8>::new(),
-            )
-            .call_flags(ink_env::CallFlags::default().set_allow_reentry(true))
-            .fire()
-            .unwrap()?;
-            ink_env::debug_println!("[flash] after transfer_from");
+            FlashLoanInternal::_before_repay_asset(self, i, &assets[i], amounts[i], fees[i])?;
+            let asset = FlashLoanInternal::_loanable_asset(self, &assets[i])?;
+            let to_repay = amounts[i]
+                .checked_add(fees[i])
+                .ok_or(FlashLoanError::Overflow)?;
+            self.flush();
+            if is_accounted(&asset) {
+                let balance_before = asset.balance_of(this);
+                asset.pull_back(*receiver, to_repay)?;
+                earned[i] = asset
+                    .balance_of(this)
+                    .saturating_sub(balance_before)
+                    .saturating_sub(amounts[i]);
+            } else {
+                asset.pull_back(*receiver, to_repay)?;
+            }
+            self.load();
+            FlashLoanInternal::_after_repay_asset(self, i, &assets[i], amounts[i], earned[i])?;
         }
+        self.flush();
+        Ok(earned)
+    }
+
+    default fn _execute_operation(
+        &mut self,
+        loan_id: LoanId,
+        receiver: &AccountId,
+        initiator: &AccountId,
+        assets: &Vec<AccountId>,
+        amounts: &Vec<Balance>,
+        fees: &Vec<Balance>,
+        data: &Vec<u8>,
+    ) -> Result<(), FlashLoanError> {
+        FlashLoanReceiverRef::execute_operation_builder(
+            receiver,
+            loan_id,
+            *initiator,
+            assets.clone(),
+            amounts.clone(),
+            fees.clone(),
+            data.clone(),
+        )
+        .call_flags(CallFlags::default().set_allow_reentry(true))
+        .fire()
+        .unwrap()?;
+        Ok(())
+    }
+
+    default fn _before_send_asset(
+        &mut self,
+        index: usize,
+        asset: &AccountId,
+        amount: Balance,
+        fee: Balance,
+    ) -> Result<(), FlashLoanError> {
+        Ok(())
+    }
+
+    default fn _after_send_asset(
+        &mut self,
+        index: usize,
+        asset: &AccountId,
+        amount: Balance,
+        fee: Balance,
+    ) -> Result<(), FlashLoanError> {
+        Ok(())
+    }
+
+    default fn _before_repay_asset(
+        &mut self,
+        index: usize,
+        asset: &AccountId,
+        amount: Balance,
+        fee: Balance,
+    ) -> Result<(), FlashLoanError> {
+        Ok(())
+    }
+
+    default fn _after_repay_asset(
+        &mut self,
+        index: usize,
+        asset: &AccountId,
+        amount: Balance,
+        fee: Balance,
+    ) -> Result<(), FlashLoanError> {
         Ok(())
     }
 }
 
-impl<T: FlashLoanEventEmit + FlashLoanInternal> FlashLoan for T {
+impl<T: Storage<Data> + FlashLoanEventEmit + FlashLoanInternal> FlashLoan for T {
     default fn flash_loan(
         &mut self,
         receiver: AccountId,
@@ -191,29 +488,235 @@ impl<T: FlashLoanEventEmit + FlashLoanInternal> FlashLoan for T {
         if !(assets.len() == amounts.len()) {
             return Err(FlashLoanError::Parameters);
         }
+        if self._halt_loan(&assets) {
+            return Ok(());
+        }
+        // the id is flushed together with the loan mark before any asset is sent,
+        // so a loan started by a token or the receiver during this one gets the next id
+        let loan_id = self.data().last_loan_id + 1;
+        self.data().last_loan_id = loan_id;
+        let mut data_hash = <Blake2x256 as ink_env::hash::HashOutput>::Type::default();
+        ink_env::hash_bytes::<Blake2x256>(&data, &mut data_hash);
+
+        let loan = LoanRequest {
+            loan_id,
+            caller: Self::env().caller(),
+            receiver,
+            assets: assets.clone(),
+            amounts: amounts.clone(),
+            data_hash: Hash::from(data_hash),
+        };
+        self._pre_check_loan(&loan)?;
+
         ink_env::debug_println!("flash_loan | before_flash_loan");
-        self._before_flash_loan(&receiver, &assets, &amounts, &mut data)?;
+        self._before_flash_loan(loan_id, &receiver, &assets, &amounts, &mut data)?;
         let fees: Vec<Balance> = self._calculate_fees(&receiver, &assets, &amounts, &mut data)?;
 
+        // assets that can not be pulled and accounted tokens are repaid by the receiver on its own
+        let mut pushed_back: Vec<bool> = vec![];
+        let mut balances_before: Vec<Balance> = vec![];
+        for i in 0..assets.len() {
+            let asset = FlashLoanInternal::_loanable_asset(self, &assets[i])?;
+            pushed_back.push(!asset.can_pull_back() || is_accounted(&asset));
+            balances_before.push(FlashLoanInternal::_solvency_balance(self, &assets[i])?);
+        }
+
+        // the loan is marked before sending, so contracts called by tokens or the receiver see it.
+        // Tokens may call this contract during the transfer, so the mark must be in storage
+        self.data().loans_in_progress += 1;
+        for i in 0..assets.len() {
+            let outstanding = self.data().outstanding.get(&assets[i]).unwrap_or_default();
+            let outstanding = outstanding
+                .checked_add(amounts[i])
+                .ok_or(FlashLoanError::Overflow)?;
+            self.data().outstanding.insert(&assets[i], &outstanding);
+        }
+        self.flush();
+
         ink_env::debug_println!("flash_loan | _send_flash_loan");
-        self._send_flash_loan(&receiver, &assets, &amounts, &mut data)?;
+        let received = self._send_flash_loan(&receiver, &assets, &amounts, &fees, &mut data)?;
+        self.load();
+        // the receiver is told what arrived and pays on top of it what the contract loses on the way back
+        let mut lent = amounts.clone();
+        let mut owed_fees = fees.clone();
+        let mut repay_fees = fees.clone();
+        for i in 0..assets.len() {
+            if received[i] != amounts[i] {
+                let to_repay = grossed_up_repayment(amounts[i], fees[i], received[i])?;
+                lent[i] = received[i];
+                owed_fees[i] = to_repay.saturating_sub(received[i]);
+                repay_fees[i] = to_repay.saturating_sub(amounts[i]);
+            }
+        }
 
-        ink_env::debug_println!("flash_loan | execute_operation_builder");
-        FlashLoanReceiverRef::execute_operation_builder(
+        ink_env::debug_println!("flash_loan | _execute_operation");
+        // the receiver may call this contract, so the state must be in storage and loaded back after
+        self.flush();
+        self._execute_operation(
+            loan_id,
             &receiver,
-            assets.clone(),
-            amounts.clone(),
-            fees.clone(),
-            data.clone(),
-        )
-        .call_flags(CallFlags::default().set_allow_reentry(true))
-        .fire()
-        .unwrap()?;
+            &Self::env().caller(),
+            &assets,
+            &lent,
+            &owed_fees,
+            &data,
+        )?;
+        self.load();
         ink_env::debug_println!("flash_loan | _get_back_flash_loan");
-        self._get_back_flash_loan(&receiver, &assets, &amounts, &fees, &mut data)?;
+        // the fees that arrived are the ones recorded, the contract must still grow by the quoted ones
+        let earned =
+            self._get_back_flash_loan(&receiver, &assets, &amounts, &repay_fees, &mut data)?;
+        self.load();
+        // a shortfall of an asset that was pulled back means the contract lost funds on the way
+        let asset_fees = fees_by_asset(&assets, &fees);
+        for i in 0..assets.len() {
+            let balance_after = FlashLoanInternal::_solvency_balance(self, &assets[i])?;
+            if balance_after < balances_before[i].saturating_add(asset_fees[i]) {
+                return Err(if pushed_back[i] {
+                    FlashLoanError::NotRepaid
+                } else {
+                    FlashLoanError::InvariantViolated
+                });
+            }
+        }
+        for i in 0..assets.len() {
+            let outstanding = self.data().outstanding.get(&assets[i]).unwrap_or_default();
+            self.data()
+                .outstanding
+                .insert(&assets[i], &(outstanding - amounts[i]));
+        }
+        self.data().loans_in_progress -= 1;
+
+        self.data().receipts.insert(
+            &(loan_id % MAX_LOAN_RECEIPTS),
+            &LoanReceipt {
+                loan_id,
+                block: Self::env().block_number(),
+                initiator: Self::env().caller(),
+                receiver,
+                assets: assets.clone(),
+                amounts: amounts.clone(),
+                fees: earned.clone(),
+                data_hash: loan.data_hash,
+            },
+        );
+
         ink_env::debug_println!("flash_loan | _after_flash_loan");
-        self._after_flash_loan(&receiver, &assets, &amounts, &fees, &mut data)?;
+        self._after_flash_loan(loan_id, &receiver, &assets, &amounts, &earned, &mut data)?;
+        self._post_check_loan(&loan, &earned)?;
+        self._emit_flash_loan_events(loan_id, &receiver, &assets, &amounts, &earned)?;
 
         Ok(())
     }
+
+    default fn is_in_flash_loan(&self) -> bool {
+        self.data().loans_in_progress > 0
+    }
+
+    default fn outstanding(&self, asset: AccountId) -> Balance {
+        self.data().outstanding.get(&asset).unwrap_or_default()
+    }
+
+    default fn loan_receipt(&self, loan_id: LoanId) -> Option<LoanReceipt> {
+        self.data()
+            .receipts
+            .get(&(loan_id % MAX_LOAN_RECEIPTS))
+            .filter(|receipt| receipt.loan_id == loan_id)
+    }
+
+    default fn max_flash_loan(&self, asset: AccountId) -> Balance {
+        self._max_flash_loan(&asset)
+    }
+
+    default fn quote_fees(
+        &self,
+        assets: Vec<AccountId>,
+        amounts: Vec<Balance>,
+    ) -> Result<Vec<Balance>, FlashLoanError> {
+        if !(assets.len() == amounts.len()) {
+            return Err(FlashLoanError::Parameters);
+        }
+        self._quote_fees(&assets, &amounts)
+    }
+}
+
+/// true for PSP22 tokens lent with `TokenQuirkPolicy::Account`
+fn is_accounted(asset: &Asset) -> bool {
+    matches!(asset, Asset::PSP22(psp22) if psp22.quirk_policy == TokenQuirkPolicy::Account)
+}
+
+/// returns for each entry the sum of fees of all entries of its asset, an asset listed more than once
+/// must grow by all of them
+fn fees_by_asset(assets: &Vec<AccountId>, fees: &Vec<Balance>) -> Vec<Balance> {
+    assets
+        .iter()
+        .map(|asset| {
+            (0..assets.len())
+                .filter(|j| assets[*j] == *asset)
+                .fold(0, |sum: Balance, j| sum.saturating_add(fees[j]))
+        })
+        .collect()
+}
+
+/// returns `(amount + fee) * amount / received` rounded up, so the contract gets back `amount + fee`
+/// if the token keeps the same part of the repayment as it kept of the loan
+fn grossed_up_repayment(
+    amount: Balance,
+    fee: Balance,
+    received: Balance,
+) -> Result<Balance, FlashLoanError> {
+    if received == 0 {
+        return Err(FlashLoanError::TransferAmountMismatch);
+    }
+    let numerator = (U256::from(amount) + U256::from(fee)) * U256::from(amount);
+    let to_repay = (numerator + U256::from(received - 1)) / U256::from(received);
+    if to_repay > U256::from(Balance::MAX) {
+        return Err(FlashLoanError::TransferAmountMismatch);
+    }
+    Ok(to_repay.as_u128())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grossed_up_repayment_is_exact_without_transfer_loss() {
+        assert_eq!(grossed_up_repayment(1_000, 9, 1_000), Ok(1_009));
+    }
+
+    #[test]
+    fn grossed_up_repayment_rounds_up() {
+        // 1% kept by the token: 1_009 * 1_000 / 990 = 1_019.19...
+        assert_eq!(grossed_up_repayment(1_000, 9, 990), Ok(1_020));
+    }
+
+    #[test]
+    fn grossed_up_repayment_does_not_overflow_when_amount_and_fee_exceed_balance() {
+        assert_eq!(
+            grossed_up_repayment(Balance::MAX - 1, 2, Balance::MAX),
+            Ok(Balance::MAX)
+        );
+        assert_eq!(
+            grossed_up_repayment(Balance::MAX, 1, Balance::MAX),
+            Err(FlashLoanError::TransferAmountMismatch)
+        );
+    }
+
+    #[test]
+    fn fees_by_asset_adds_fees_of_repeated_assets() {
+        let (usdc, dai) = (AccountId::from([1; 32]), AccountId::from([2; 32]));
+        assert_eq!(
+            fees_by_asset(&vec![usdc, dai, usdc], &vec![10, 5, 7]),
+            vec![17, 5, 17]
+        );
+    }
+
+    #[test]
+    fn grossed_up_repayment_fails_when_nothing_was_received() {
+        assert_eq!(
+            grossed_up_repayment(1_000, 9, 0),
+            Err(FlashLoanError::TransferAmountMismatch)
+        );
+    }
 }
@@ -0,0 +1,440 @@
+use crate::impls::flash_loan::{self, when_not_in_flash_loan};
+use crate::storage::{EnumerableMap, EnumerableSet};
+use crate::traits::{flash_loan::FlashLoanError, policy_contract::FlashLoanPolicyRef};
+pub use crate::{impls::flash_loan_policy, traits::flash_loan_policy::*};
+use ink_prelude::vec::Vec;
+
+use openbrush::contracts::ownable::{self, only_owner};
+use openbrush::modifiers;
+use openbrush::storage::Mapping;
+use openbrush::traits::{AccountId, Balance, BlockNumber, Storage};
+
+pub const STORAGE_KEY: u32 = openbrush::storage_unique_key!(Data);
+
+#[derive(Default, Debug)]
+#[openbrush::upgradeable_storage(STORAGE_KEY)]
+pub struct Data {
+    /// module at each position of the chain
+    pub chain: Mapping<u32, PolicyModule>,
+    pub chain_length: u32,
+    pub allowlist: EnumerableSet<AccountId>,
+    pub caps: EnumerableMap<AccountId, Balance>,
+    pub loans_paused: bool,
+    pub rate_limits: Mapping<AccountId, RateLimit>,
+    /// start of the window and amount borrowed in it by (caller, asset)
+    pub rate_limit_usage: Mapping<(AccountId, AccountId), (BlockNumber, Balance)>,
+    /// last block of the credential of each account
+    pub credentials: EnumerableMap<AccountId, BlockNumber>,
+    pub policy_contract: Option<AccountId>,
+    /// 0 forwards all gas left to the policy contract
+    pub policy_contract_gas_limit: u64,
+    pub _reserved: Option<()>,
+}
+
+pub trait PolicyChainEvents {
+    /// !!! should be overriden in contract !!!
+    /// emits PolicyContractSet event
+    fn _emit_policy_contract_set_event(&self, policy_contract: Option<AccountId>, gas_limit: u64);
+}
+
+impl<T> PolicyChainEvents for T {
+    default fn _emit_policy_contract_set_event(
+        &self,
+        _policy_contract: Option<AccountId>,
+        _gas_limit: u64,
+    ) {
+    }
+}
+
+/// rejects callers that are not allowlisted
+pub struct AllowlistPolicy;
+
+/// rejects loans above the cap of an asset
+pub struct CapsPolicy;
+
+/// rejects all loans while loans are paused
+pub struct PausePolicy;
+
+/// rejects loans above the amount the caller has left in the current window of an asset,
+/// the borrowed amount is counted when the loan is let through, so loans nested in it count it too
+pub struct RateLimitPolicy;
+
+/// rejects callers without a credential or with an expired one
+pub struct CredentialPolicy;
+
+/// rejects loans not allowed by the policy contract, and all loans if it is not set
+pub struct ExternalPolicy;
+
+impl<T: FlashLoanPolicyInternal> FlashLoanPolicy<T> for AllowlistPolicy {
+    fn pre_check(&self, lender: &mut T, loan: &LoanRequest) -> Result<(), FlashLoanError> {
+        if !FlashLoanPolicyInternal::_is_allowlisted(lender, &loan.caller) {
+            return Err(FlashLoanError::PolicyRejected(PolicyModule::Allowlist));
+        }
+        Ok(())
+    }
+}
+
+impl<T: FlashLoanPolicyInternal> FlashLoanPolicy<T> for CapsPolicy {
+    fn pre_check(&self, lender: &mut T, loan: &LoanRequest) -> Result<(), FlashLoanError> {
+        for i in 0..loan.assets.len() {
+            match FlashLoanPolicyInternal::_cap(lender, &loan.assets[i]) {
+                Some(cap) if loan.amounts[i] > cap => {
+                    return Err(FlashLoanError::PolicyRejected(PolicyModule::Caps))
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<T: FlashLoanPolicyInternal> FlashLoanPolicy<T> for PausePolicy {
+    fn pre_check(&self, lender: &mut T, loan: &LoanRequest) -> Result<(), FlashLoanError> {
+        for asset in loan.assets.iter() {
+            if FlashLoanPolicyInternal::_is_paused(lender, asset) {
+                return Err(FlashLoanError::PolicyRejected(PolicyModule::Pause));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<T: Storage<Data>> FlashLoanPolicy<T> for RateLimitPolicy {
+    fn pre_check(&self, lender: &mut T, loan: &LoanRequest) -> Result<(), FlashLoanError> {
+        let block = T::env().block_number();
+        // amounts of an asset listed more than once in the loan are summed up
+        let mut requested: Vec<(AccountId, Balance)> = Vec::new();
+        for i in 0..loan.assets.len() {
+            match requested
+                .iter_mut()
+                .find(|(asset, _)| *asset == loan.assets[i])
+            {
+                Some((_, amount)) => *amount = amount.saturating_add(loan.amounts[i]),
+                None => requested.push((loan.assets[i], loan.amounts[i])),
+            }
+        }
+        let mut usage: Vec<(AccountId, BlockNumber, Balance)> = Vec::new();
+        for (asset, amount) in requested {
+            if let Some(rate_limit) = lender.data().rate_limits.get(&asset) {
+                let used = used_in_window(lender.data(), block, &loan.caller, &asset, &rate_limit)
+                    .saturating_add(amount);
+                if used > rate_limit.amount {
+                    return Err(FlashLoanError::PolicyRejected(PolicyModule::RateLimit));
+                }
+                usage.push((asset, window_start(block, &rate_limit), used));
+            }
+        }
+        // recorded before the loan is sent, a loan nested in this one sees it. A rejected or reverted loan reverts it
+        for (asset, start, used) in usage {
+            lender
+                .data()
+                .rate_limit_usage
+                .insert(&(loan.caller, asset), &(start, used));
+        }
+        Ok(())
+    }
+}
+
+impl<T: Storage<Data>> FlashLoanPolicy<T> for CredentialPolicy {
+    fn pre_check(&self, lender: &mut T, loan: &LoanRequest) -> Result<(), FlashLoanError> {
+        match lender.data().credentials.get(&loan.caller) {
+            Some(expiry) if expiry >= T::env().block_number() => Ok(()),
+            _ => Err(FlashLoanError::PolicyRejected(PolicyModule::Credential)),
+        }
+    }
+}
+
+impl<T: Storage<Data>> FlashLoanPolicy<T> for ExternalPolicy {
+    fn pre_check(&self, lender: &mut T, loan: &LoanRequest) -> Result<(), FlashLoanError> {
+        let policy_contract = lender
+            .data()
+            .policy_contract
+            .ok_or(FlashLoanError::PolicyRejected(PolicyModule::External))?;
+        let result = FlashLoanPolicyRef::authorize_builder(
+            &policy_contract,
+            loan.caller,
+            loan.receiver,
+            loan.assets.clone(),
+            loan.amounts.clone(),
+            loan.data_hash,
+        )
+        .gas_limit(lender.data().policy_contract_gas_limit)
+        .fire();
+        match result {
+            Ok(Ok(true)) => Ok(()),
+            Ok(Ok(false)) => Err(FlashLoanError::PolicyContractDenied),
+            Ok(Err(error)) => Err(FlashLoanError::PolicyContractError(error)),
+            Err(ink_env::Error::CalleeTrapped) => Err(FlashLoanError::PolicyContractTrapped),
+            Err(_) => Err(FlashLoanError::PolicyContractCallFailed),
+        }
+    }
+}
+
+impl<T: Storage<Data> + FlashLoanPolicyInternal> FlashLoanPolicy<T> for PolicyModule {
+    fn pre_check(&self, lender: &mut T, loan: &LoanRequest) -> Result<(), FlashLoanError> {
+        match self {
+            PolicyModule::Allowlist => AllowlistPolicy.pre_check(lender, loan),
+            PolicyModule::Caps => CapsPolicy.pre_check(lender, loan),
+            PolicyModule::Pause => PausePolicy.pre_check(lender, loan),
+            PolicyModule::RateLimit => RateLimitPolicy.pre_check(lender, loan),
+            PolicyModule::Credential => CredentialPolicy.pre_check(lender, loan),
+            PolicyModule::External => ExternalPolicy.pre_check(lender, loan),
+        }
+    }
+
+    fn post_check(
+        &self,
+        lender: &mut T,
+        loan: &LoanRequest,
+        fees: &Vec<Balance>,
+    ) -> Result<(), FlashLoanError> {
+        match self {
+            PolicyModule::Allowlist => AllowlistPolicy.post_check(lender, loan, fees),
+            PolicyModule::Caps => CapsPolicy.post_check(lender, loan, fees),
+            PolicyModule::Pause => PausePolicy.post_check(lender, loan, fees),
+            PolicyModule::RateLimit => RateLimitPolicy.post_check(lender, loan, fees),
+            PolicyModule::Credential => CredentialPolicy.post_check(lender, loan, fees),
+            PolicyModule::External => ExternalPolicy.post_check(lender, loan, fees),
+        }
+    }
+}
+
+pub trait FlashLoanPolicyInternal {
+    /// runs pre-checks of the chain in order and returns the first error, e.g. in `_pre_check_loan`
+    fn _run_pre_checks(&mut self, loan: &LoanRequest) -> Result<(), FlashLoanError>;
+
+    /// runs post-checks of the chain in order and returns the first error, e.g. in `_post_check_loan`
+    fn _run_post_checks(
+        &mut self,
+        loan: &LoanRequest,
+        fees: &Vec<Balance>,
+    ) -> Result<(), FlashLoanError>;
+
+    fn _policies(&self) -> Vec<PolicyModule>;
+
+    fn _set_policies(&mut self, policies: &Vec<PolicyModule>);
+
+    /// may be overriden in contract
+    /// returns true if `account` passes `PolicyModule::Allowlist`. By default it is in the allowlist.
+    fn _is_allowlisted(&self, account: &AccountId) -> bool;
+
+    /// may be overriden in contract
+    /// returns the cap of `asset` checked by `PolicyModule::Caps`. By default it is the one set with `set_cap`.
+    fn _cap(&self, asset: &AccountId) -> Option<Balance>;
+
+    /// may be overriden in contract
+    /// returns true if loans of `asset` are stopped by `PolicyModule::Pause`. By default all are while loans are paused.
+    fn _is_paused(&self, asset: &AccountId) -> bool;
+}
+
+impl<T: Storage<Data>> FlashLoanPolicyInternal for T {
+    default fn _run_pre_checks(&mut self, loan: &LoanRequest) -> Result<(), FlashLoanError> {
+        for module in FlashLoanPolicyInternal::_policies(self) {
+            module.pre_check(self, loan)?;
+        }
+        Ok(())
+    }
+
+    default fn _run_post_checks(
+        &mut self,
+        loan: &LoanRequest,
+        fees: &Vec<Balance>,
+    ) -> Result<(), FlashLoanError> {
+        for module in FlashLoanPolicyInternal::_policies(self) {
+            module.post_check(self, loan, fees)?;
+        }
+        Ok(())
+    }
+
+    default fn _policies(&self) -> Vec<PolicyModule> {
+        (0..self.data().chain_length)
+            .filter_map(|i| self.data().chain.get(&i))
+            .collect()
+    }
+
+    default fn _set_policies(&mut self, policies: &Vec<PolicyModule>) {
+        for i in policies.len() as u32..self.data().chain_length {
+            self.data().chain.remove(&i);
+        }
+        for (i, module) in policies.iter().enumerate() {
+            self.data().chain.insert(&(i as u32), module);
+        }
+        self.data().chain_length = policies.len() as u32;
+    }
+
+    default fn _is_allowlisted(&self, account: &AccountId) -> bool {
+        self.data().allowlist.contains(account)
+    }
+
+    default fn _cap(&self, asset: &AccountId) -> Option<Balance> {
+        self.data().caps.get(asset)
+    }
+
+    default fn _is_paused(&self, _asset: &AccountId) -> bool {
+        self.data().loans_paused
+    }
+}
+
+impl<T: Storage<Data> + Storage<ownable::Data> + Storage<flash_loan::Data>> PolicyChain for T {
+    default fn policies(&self) -> Vec<PolicyModule> {
+        FlashLoanPolicyInternal::_policies(self)
+    }
+
+    #[modifiers(only_owner, when_not_in_flash_loan)]
+    default fn set_policies(
+        &mut self,
+        policies: Vec<PolicyModule>,
+    ) -> Result<(), PolicyChainError> {
+        FlashLoanPolicyInternal::_set_policies(self, &policies);
+        Ok(())
+    }
+
+    #[modifiers(only_owner)]
+    default fn set_allowlisted(
+        &mut self,
+        account: AccountId,
+        allowlisted: bool,
+    ) -> Result<(), PolicyChainError> {
+        match allowlisted {
+            true => self.data::<Data>().allowlist.insert(&account),
+            false => self.data::<Data>().allowlist.remove(&account),
+        };
+        Ok(())
+    }
+
+    default fn is_allowlisted(&self, account: AccountId) -> bool {
+        self.data::<Data>().allowlist.contains(&account)
+    }
+
+    default fn list_allowlisted(&self, offset: u32, limit: u32) -> Vec<AccountId> {
+        self.data::<Data>().allowlist.page(offset, limit)
+    }
+
+    #[modifiers(only_owner)]
+    default fn set_cap(
+        &mut self,
+        asset: AccountId,
+        cap: Option<Balance>,
+    ) -> Result<(), PolicyChainError> {
+        match cap {
+            Some(cap) => self.data::<Data>().caps.insert(&asset, &cap),
+            None => {
+                self.data::<Data>().caps.remove(&asset);
+            }
+        }
+        Ok(())
+    }
+
+    default fn cap(&self, asset: AccountId) -> Option<Balance> {
+        self.data::<Data>().caps.get(&asset)
+    }
+
+    default fn list_caps(&self, offset: u32, limit: u32) -> Vec<(AccountId, Balance)> {
+        self.data::<Data>().caps.page(offset, limit)
+    }
+
+    #[modifiers(only_owner)]
+    default fn set_loans_paused(&mut self, paused: bool) -> Result<(), PolicyChainError> {
+        self.data::<Data>().loans_paused = paused;
+        Ok(())
+    }
+
+    default fn loans_paused(&self) -> bool {
+        self.data::<Data>().loans_paused
+    }
+
+    #[modifiers(only_owner)]
+    default fn set_rate_limit(
+        &mut self,
+        asset: AccountId,
+        rate_limit: Option<RateLimit>,
+    ) -> Result<(), PolicyChainError> {
+        match rate_limit {
+            Some(rate_limit) if rate_limit.window == 0 => {
+                return Err(PolicyChainError::InvalidRateLimit)
+            }
+            Some(rate_limit) => self.data::<Data>().rate_limits.insert(&asset, &rate_limit),
+            None => self.data::<Data>().rate_limits.remove(&asset),
+        }
+        Ok(())
+    }
+
+    default fn rate_limit(&self, asset: AccountId) -> Option<RateLimit> {
+        self.data::<Data>().rate_limits.get(&asset)
+    }
+
+    default fn rate_limit_used(&self, caller: AccountId, asset: AccountId) -> Balance {
+        match self.data::<Data>().rate_limits.get(&asset) {
+            Some(rate_limit) => used_in_window(
+                self.data::<Data>(),
+                Self::env().block_number(),
+                &caller,
+                &asset,
+                &rate_limit,
+            ),
+            None => 0,
+        }
+    }
+
+    #[modifiers(only_owner)]
+    default fn set_credential(
+        &mut self,
+        account: AccountId,
+        expiry: Option<BlockNumber>,
+    ) -> Result<(), PolicyChainError> {
+        match expiry {
+            Some(expiry) => self.data::<Data>().credentials.insert(&account, &expiry),
+            None => {
+                self.data::<Data>().credentials.remove(&account);
+            }
+        }
+        Ok(())
+    }
+
+    default fn credential(&self, account: AccountId) -> Option<BlockNumber> {
+        self.data::<Data>().credentials.get(&account)
+    }
+
+    default fn list_credentials(&self, offset: u32, limit: u32) -> Vec<(AccountId, BlockNumber)> {
+        self.data::<Data>().credentials.page(offset, limit)
+    }
+
+    #[modifiers(only_owner, when_not_in_flash_loan)]
+    default fn set_policy_contract(
+        &mut self,
+        policy_contract: Option<AccountId>,
+        gas_limit: u64,
+    ) -> Result<(), PolicyChainError> {
+        self.data::<Data>().policy_contract = policy_contract;
+        self.data::<Data>().policy_contract_gas_limit = gas_limit;
+        self._emit_policy_contract_set_event(policy_contract, gas_limit);
+        Ok(())
+    }
+
+    default fn policy_contract(&self) -> Option<(AccountId, u64)> {
+        self.data::<Data>().policy_contract.map(|policy_contract| {
+            (
+                policy_contract,
+                self.data::<Data>().policy_contract_gas_limit,
+            )
+        })
+    }
+}
+
+fn window_start(block: BlockNumber, rate_limit: &RateLimit) -> BlockNumber {
+    block - block % rate_limit.window
+}
+
+/// amount of `asset` borrowed by `caller` in the window of `rate_limit` containing `block`
+fn used_in_window(
+    data: &Data,
+    block: BlockNumber,
+    caller: &AccountId,
+    asset: &AccountId,
+    rate_limit: &RateLimit,
+) -> Balance {
+    match data.rate_limit_usage.get(&(*caller, *asset)) {
+        Some((start, used)) if start == window_start(block, rate_limit) => used,
+        _ => 0,
+    }
+}
@@ -0,0 +1,179 @@
+use crate::traits::flash_loan::LoanId;
+pub use crate::{impls::flash_loan_receiver, traits::flash_loan_receiver::*};
+use ink_prelude::vec::Vec;
+
+use openbrush::contracts::ownable::{self, only_owner};
+use openbrush::contracts::traits::psp22::PSP22Ref;
+use openbrush::modifiers;
+use openbrush::storage::Mapping;
+use openbrush::traits::{AccountId, Balance, Storage};
+
+pub const STORAGE_KEY: u32 = openbrush::storage_unique_key!(Data);
+
+#[derive(Default, Debug)]
+#[openbrush::upgradeable_storage(STORAGE_KEY)]
+pub struct Data {
+    /// lenders allowed to call execute_operation and the way they take the loan back
+    pub trusted_lenders: Mapping<AccountId, RepaymentMode>,
+    /// accounts allowed to initiate flash loans for the receiver
+    pub authorized_initiators: Mapping<AccountId, bool>,
+    pub _reserved: Option<()>,
+}
+
+pub trait FlashLoanReceiverInternal {
+    /// !!! should be overriden in contract !!!
+    /// performs the operation with lent assets. `amounts + fees` must be held by the receiver when it returns.
+    fn _on_flash_loan(
+        &mut self,
+        loan_id: LoanId,
+        assets: &Vec<AccountId>,
+        amounts: &Vec<Balance>,
+        fees: &Vec<Balance>,
+        data: &Vec<u8>,
+    ) -> Result<(), FlashLoanReceiverError>;
+
+    /// returns the repayment mode of `lender` or None if the lender is not trusted
+    fn _repayment_mode(&self, lender: &AccountId) -> Option<RepaymentMode>;
+
+    /// returns true if `initiator` may initiate flash loans for the receiver. The receiver itself is always authorized.
+    fn _is_authorized_initiator(&self, initiator: &AccountId) -> bool;
+
+    /// sets `lender` as trusted with `mode`, None removes it
+    fn _set_trusted_lender(&mut self, lender: &AccountId, mode: Option<RepaymentMode>);
+
+    fn _set_authorized_initiator(&mut self, initiator: &AccountId, authorized: bool);
+
+    /// may be overriden in contract
+    /// approves or transfers `amounts + fees` to the lender according to `mode`
+    fn _repay_flash_loan(
+        &mut self,
+        lender: &AccountId,
+        mode: RepaymentMode,
+        assets: &Vec<AccountId>,
+        amounts: &Vec<Balance>,
+        fees: &Vec<Balance>,
+    ) -> Result<(), FlashLoanReceiverError>;
+}
+
+impl<T: Storage<Data>> FlashLoanReceiverInternal for T {
+    default fn _on_flash_loan(
+        &mut self,
+        _loan_id: LoanId,
+        _assets: &Vec<AccountId>,
+        _amounts: &Vec<Balance>,
+        _fees: &Vec<Balance>,
+        _data: &Vec<u8>,
+    ) -> Result<(), FlashLoanReceiverError> {
+        Ok(())
+    }
+
+    default fn _repayment_mode(&self, lender: &AccountId) -> Option<RepaymentMode> {
+        self.data().trusted_lenders.get(lender)
+    }
+
+    default fn _is_authorized_initiator(&self, initiator: &AccountId) -> bool {
+        *initiator == Self::env().account_id()
+            || self
+                .data()
+                .authorized_initiators
+                .get(initiator)
+                .unwrap_or_default()
+    }
+
+    default fn _set_trusted_lender(&mut self, lender: &AccountId, mode: Option<RepaymentMode>) {
+        match mode {
+            Some(mode) => self.data().trusted_lenders.insert(lender, &mode),
+            None => self.data().trusted_lenders.remove(lender),
+        }
+    }
+
+    default fn _set_authorized_initiator(&mut self, initiator: &AccountId, authorized: bool) {
+        if authorized {
+            self.data()
+                .authorized_initiators
+                .insert(initiator, &authorized);
+        } else {
+            self.data().authorized_initiators.remove(initiator);
+        }
+    }
+
+    default fn _repay_flash_loan(
+        &mut self,
+        lender: &AccountId,
+        mode: RepaymentMode,
+        assets: &Vec<AccountId>,
+        amounts: &Vec<Balance>,
+        fees: &Vec<Balance>,
+    ) -> Result<(), FlashLoanReceiverError> {
+        // approve replaces the allowance, so amounts and fees of an asset listed more than once are summed up
+        let mut owed: Vec<(AccountId, Balance)> = Vec::new();
+        for i in 0..assets.len() {
+            let amount = amounts[i].saturating_add(fees[i]);
+            match mode {
+                RepaymentMode::Approve => {
+                    match owed.iter_mut().find(|(asset, _)| *asset == assets[i]) {
+                        Some((_, total)) => *total = total.saturating_add(amount),
+                        None => owed.push((assets[i], amount)),
+                    }
+                }
+                RepaymentMode::Transfer => {
+                    PSP22Ref::transfer(&assets[i], *lender, amount, Vec::<u8>::new())?
+                }
+            }
+        }
+        for (asset, total) in owed {
+            PSP22Ref::approve(&asset, *lender, total)?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: Storage<Data>> FlashLoanReceiver for T {
+    default fn execute_operation(
+        &mut self,
+        loan_id: LoanId,
+        initiator: AccountId,
+        assets: Vec<AccountId>,
+        amounts: Vec<Balance>,
+        fees: Vec<Balance>,
+        data: Vec<u8>,
+    ) -> Result<(), FlashLoanReceiverError> {
+        let lender = Self::env().caller();
+        let mode = match FlashLoanReceiverInternal::_repayment_mode(self, &lender) {
+            Some(mode) => mode,
+            None => return Err(FlashLoanReceiverError::UntrustedLender),
+        };
+        if !FlashLoanReceiverInternal::_is_authorized_initiator(self, &initiator) {
+            return Err(FlashLoanReceiverError::UnauthorizedInitiator);
+        }
+        if !(assets.len() == amounts.len() && assets.len() == fees.len()) {
+            return Err(FlashLoanReceiverError::ExecuteOperationFailed);
+        }
+
+        self._on_flash_loan(loan_id, &assets, &amounts, &fees, &data)?;
+        self._repay_flash_loan(&lender, mode, &assets, &amounts, &fees)
+    }
+}
+
+impl<T: Storage<Data> + Storage<ownable::Data>> FlashLoanReceiverAdmin for T {
+    #[modifiers(only_owner)]
+    default fn set_trusted_lender(
+        &mut self,
+        lender: AccountId,
+        mode: Option<RepaymentMode>,
+    ) -> Result<(), FlashLoanReceiverAdminError> {
+        FlashLoanReceiverInternal::_set_trusted_lender(self, &lender, mode);
+        Ok(())
+    }
+
+    #[modifiers(only_owner)]
+    default fn withdraw(
+        &mut self,
+        asset: AccountId,
+        to: AccountId,
+        amount: Balance,
+    ) -> Result<(), FlashLoanReceiverAdminError> {
+        PSP22Ref::transfer(&asset, to, amount, Vec::<u8>::new())?;
+        Ok(())
+    }
+}
@@ -0,0 +1,160 @@
+pub use crate::{impls::loan_stats, traits::loan_stats::*};
+use ink_prelude::vec::Vec;
+
+use openbrush::contracts::ownable::{self, only_owner};
+use openbrush::modifiers;
+use openbrush::storage::Mapping;
+use openbrush::traits::{AccountId, Balance, BlockNumber, Storage};
+
+pub const STORAGE_KEY: u32 = openbrush::storage_unique_key!(Data);
+
+/// number of epoch snapshots kept per asset
+pub const MAX_EPOCH_SNAPSHOTS: u32 = 32;
+
+/// epoch length used until it is set, about a day with 6 second blocks
+pub const DEFAULT_EPOCH_LENGTH: BlockNumber = 14_400;
+
+#[derive(Default, Debug)]
+#[openbrush::upgradeable_storage(STORAGE_KEY)]
+pub struct Data {
+    pub assets: Mapping<AccountId, AssetStats>,
+    pub callers: Mapping<AccountId, u64>,
+    pub receivers: Mapping<AccountId, u64>,
+    /// ring buffer of each asset, the snapshot number `n` is at index `n % MAX_EPOCH_SNAPSHOTS`
+    pub snapshots: Mapping<(AccountId, u32), EpochSnapshot>,
+    /// number of snapshots ever taken of each asset
+    pub snapshot_counts: Mapping<AccountId, u32>,
+    /// 0 stands for `DEFAULT_EPOCH_LENGTH`
+    pub epoch_length: BlockNumber,
+    pub _reserved: Option<()>,
+}
+
+pub trait LoanStatsInternal {
+    /// should be called after every flash loan, e.g. in `_after_flash_loan`
+    fn _record_flash_loan(
+        &mut self,
+        caller: &AccountId,
+        receiver: &AccountId,
+        assets: &Vec<AccountId>,
+        amounts: &Vec<Balance>,
+        fees: &Vec<Balance>,
+    );
+
+    /// adds the loan to the snapshot of the current epoch of `asset`, a new snapshot is started in a new epoch
+    fn _record_epoch_loan(&mut self, asset: &AccountId, amount: Balance, fee: Balance);
+
+    fn _epoch_length(&self) -> BlockNumber;
+}
+
+impl<T: Storage<Data>> LoanStatsInternal for T {
+    default fn _record_flash_loan(
+        &mut self,
+        caller: &AccountId,
+        receiver: &AccountId,
+        assets: &Vec<AccountId>,
+        amounts: &Vec<Balance>,
+        fees: &Vec<Balance>,
+    ) {
+        for i in 0..assets.len() {
+            let mut stats = self.data().assets.get(&assets[i]).unwrap_or_default();
+            add_loan(&mut stats, amounts[i], fees[i]);
+            self.data().assets.insert(&assets[i], &stats);
+            LoanStatsInternal::_record_epoch_loan(self, &assets[i], amounts[i], fees[i]);
+        }
+        let caller_loans = self.data().callers.get(caller).unwrap_or_default();
+        self.data().callers.insert(caller, &(caller_loans + 1));
+        let receiver_loans = self.data().receivers.get(receiver).unwrap_or_default();
+        self.data()
+            .receivers
+            .insert(receiver, &(receiver_loans + 1));
+    }
+
+    default fn _record_epoch_loan(&mut self, asset: &AccountId, amount: Balance, fee: Balance) {
+        let block = Self::env().block_number();
+        let start_block = block - block % LoanStatsInternal::_epoch_length(self);
+        let count = self.data().snapshot_counts.get(asset).unwrap_or_default();
+        let latest = match count {
+            0 => None,
+            _ => self
+                .data()
+                .snapshots
+                .get(&(*asset, (count - 1) % MAX_EPOCH_SNAPSHOTS)),
+        };
+        let (index, mut snapshot) = match latest {
+            Some(snapshot) if snapshot.start_block == start_block => {
+                ((count - 1) % MAX_EPOCH_SNAPSHOTS, snapshot)
+            }
+            _ => {
+                self.data().snapshot_counts.insert(asset, &(count + 1));
+                (
+                    count % MAX_EPOCH_SNAPSHOTS,
+                    EpochSnapshot {
+                        start_block,
+                        stats: AssetStats::default(),
+                    },
+                )
+            }
+        };
+        add_loan(&mut snapshot.stats, amount, fee);
+        self.data().snapshots.insert(&(*asset, index), &snapshot);
+    }
+
+    default fn _epoch_length(&self) -> BlockNumber {
+        match self.data().epoch_length {
+            0 => DEFAULT_EPOCH_LENGTH,
+            length => length,
+        }
+    }
+}
+
+impl<T: Storage<Data> + Storage<ownable::Data>> LoanStats for T {
+    default fn asset_stats(&self, asset: AccountId) -> AssetStats {
+        self.data::<Data>().assets.get(&asset).unwrap_or_default()
+    }
+
+    default fn caller_loans(&self, caller: AccountId) -> u64 {
+        self.data::<Data>().callers.get(&caller).unwrap_or_default()
+    }
+
+    default fn receiver_loans(&self, receiver: AccountId) -> u64 {
+        self.data::<Data>()
+            .receivers
+            .get(&receiver)
+            .unwrap_or_default()
+    }
+
+    default fn epoch_snapshots(&self, asset: AccountId) -> Vec<EpochSnapshot> {
+        let count = self
+            .data::<Data>()
+            .snapshot_counts
+            .get(&asset)
+            .unwrap_or_default();
+        (count.saturating_sub(MAX_EPOCH_SNAPSHOTS)..count)
+            .rev()
+            .filter_map(|n| {
+                self.data::<Data>()
+                    .snapshots
+                    .get(&(asset, n % MAX_EPOCH_SNAPSHOTS))
+            })
+            .collect()
+    }
+
+    default fn epoch_length(&self) -> BlockNumber {
+        LoanStatsInternal::_epoch_length(self)
+    }
+
+    #[modifiers(only_owner)]
+    default fn set_epoch_length(&mut self, blocks: BlockNumber) -> Result<(), LoanStatsError> {
+        if blocks == 0 {
+            return Err(LoanStatsError::InvalidEpochLength);
+        }
+        self.data::<Data>().epoch_length = blocks;
+        Ok(())
+    }
+}
+
+fn add_loan(stats: &mut AssetStats, amount: Balance, fee: Balance) {
+    stats.volume = stats.volume.saturating_add(amount);
+    stats.fees = stats.fees.saturating_add(fee);
+    stats.loans += 1;
+}
@@ -0,0 +1,307 @@
+use crate::impls::pallet_assets::PalletAssetsAsset;
+use crate::traits::{flash_loan::FlashLoanError, loanable_asset::*};
+use ink_env::CallFlags;
+use ink_prelude::vec::Vec;
+
+use openbrush::contracts::traits::{
+    psp22::{extensions::metadata::PSP22MetadataRef, PSP22Ref},
+    psp34::{Id, PSP34Ref},
+    psp35::PSP35Ref,
+};
+use openbrush::traits::{AccountId, Balance, DefaultEnv};
+
+/// PSP22 token
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PSP22Asset {
+    pub token: AccountId,
+    pub quirk_policy: TokenQuirkPolicy,
+}
+
+impl PSP22Asset {
+    /// transfers that do not move exactly the requested amount are rejected
+    pub fn new(token: AccountId) -> Self {
+        Self::with_quirk_policy(token, TokenQuirkPolicy::Reject)
+    }
+
+    pub fn with_quirk_policy(token: AccountId, quirk_policy: TokenQuirkPolicy) -> Self {
+        Self {
+            token,
+            quirk_policy,
+        }
+    }
+}
+
+impl LoanableAsset for PSP22Asset {
+    /// with `TokenQuirkPolicy::Reject` fails with `TransferAmountMismatch` if the balances of the contract and `to`
+    /// did not change by `amount`
+    fn send(&self, to: AccountId, amount: Balance) -> Result<(), FlashLoanError> {
+        if self.quirk_policy == TokenQuirkPolicy::Account {
+            PSP22Ref::transfer_builder(&self.token, to, amount, Vec::<u8>::new())
+                .call_flags(CallFlags::default().set_allow_reentry(true))
+                .fire()
+                .unwrap()?;
+            return Ok(());
+        }
+        let this = Self::env().account_id();
+        let this_before = self.balance_of(this);
+        let to_before = self.balance_of(to);
+        PSP22Ref::transfer_builder(&self.token, to, amount, Vec::<u8>::new())
+            .call_flags(CallFlags::default().set_allow_reentry(true))
+            .fire()
+            .unwrap()?;
+        if this_before.checked_sub(self.balance_of(this)) != Some(amount)
+            || self.balance_of(to).checked_sub(to_before) != Some(amount)
+        {
+            return Err(FlashLoanError::TransferAmountMismatch);
+        }
+        Ok(())
+    }
+
+    /// with `TokenQuirkPolicy::Reject` fails with `TransferAmountMismatch` if the balance of the contract
+    /// did not grow by `amount`
+    fn pull_back(&self, from: AccountId, amount: Balance) -> Result<(), FlashLoanError> {
+        let this = Self::env().account_id();
+        if self.quirk_policy == TokenQuirkPolicy::Account {
+            PSP22Ref::transfer_from_builder(&self.token, from, this, amount, Vec::<u8>::new())
+                .call_flags(CallFlags::default().set_allow_reentry(true))
+                .fire()
+                .unwrap()?;
+            return Ok(());
+        }
+        let this_before = self.balance_of(this);
+        PSP22Ref::transfer_from_builder(&self.token, from, this, amount, Vec::<u8>::new())
+            .call_flags(CallFlags::default().set_allow_reentry(true))
+            .fire()
+            .unwrap()?;
+        if self.balance_of(this).checked_sub(this_before) != Some(amount) {
+            return Err(FlashLoanError::TransferAmountMismatch);
+        }
+        Ok(())
+    }
+
+    fn balance_of(&self, owner: AccountId) -> Balance {
+        PSP22Ref::balance_of(&self.token, owner)
+    }
+
+    fn metadata(&self) -> AssetMetadata {
+        // metadata extension is optional so failed calls are not an error
+        AssetMetadata {
+            kind: AssetKind::PSP22,
+            name: PSP22MetadataRef::token_name_builder(&self.token)
+                .fire()
+                .unwrap_or_default(),
+            symbol: PSP22MetadataRef::token_symbol_builder(&self.token)
+                .fire()
+                .unwrap_or_default(),
+            decimals: PSP22MetadataRef::token_decimals_builder(&self.token)
+                .fire()
+                .ok(),
+        }
+    }
+}
+
+/// native currency of the chain.
+/// It can not be pulled, the receiver has to transfer `amount + fee` back during `execute_operation`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NativeAsset {}
+
+impl LoanableAsset for NativeAsset {
+    fn send(&self, to: AccountId, amount: Balance) -> Result<(), FlashLoanError> {
+        Self::env()
+            .transfer(to, amount)
+            .map_err(|_| FlashLoanError::NativeTransferFailed)
+    }
+
+    fn pull_back(&self, _from: AccountId, _amount: Balance) -> Result<(), FlashLoanError> {
+        Ok(())
+    }
+
+    fn can_pull_back(&self) -> bool {
+        false
+    }
+
+    /// the environment exposes only the balance of the calling contract, 0 is returned for other accounts
+    fn balance_of(&self, owner: AccountId) -> Balance {
+        if owner == Self::env().account_id() {
+            Self::env().balance()
+        } else {
+            0
+        }
+    }
+
+    fn metadata(&self) -> AssetMetadata {
+        AssetMetadata {
+            kind: AssetKind::Native,
+            name: None,
+            symbol: None,
+            decimals: None,
+        }
+    }
+}
+
+/// single token `id` of PSP34 `collection`. Only `amount` equal to 1 can be lent, fee must be 0.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PSP34Asset {
+    pub collection: AccountId,
+    pub id: Id,
+}
+
+impl PSP34Asset {
+    pub fn new(collection: AccountId, id: Id) -> Self {
+        Self { collection, id }
+    }
+}
+
+impl LoanableAsset for PSP34Asset {
+    fn send(&self, to: AccountId, amount: Balance) -> Result<(), FlashLoanError> {
+        if amount != 1 {
+            return Err(FlashLoanError::Parameters);
+        }
+        PSP34Ref::transfer_builder(&self.collection, to, self.id.clone(), Vec::<u8>::new())
+            .call_flags(CallFlags::default().set_allow_reentry(true))
+            .fire()
+            .unwrap()?;
+        Ok(())
+    }
+
+    /// `from` must approve the calling contract as operator of the token
+    fn pull_back(&self, _from: AccountId, amount: Balance) -> Result<(), FlashLoanError> {
+        if amount != 1 {
+            return Err(FlashLoanError::Parameters);
+        }
+        PSP34Ref::transfer_builder(
+            &self.collection,
+            Self::env().account_id(),
+            self.id.clone(),
+            Vec::<u8>::new(),
+        )
+        .call_flags(CallFlags::default().set_allow_reentry(true))
+        .fire()
+        .unwrap()?;
+        Ok(())
+    }
+
+    fn balance_of(&self, owner: AccountId) -> Balance {
+        match PSP34Ref::owner_of(&self.collection, self.id.clone()) {
+            Some(token_owner) if token_owner == owner => 1,
+            _ => 0,
+        }
+    }
+
+    fn metadata(&self) -> AssetMetadata {
+        AssetMetadata {
+            kind: AssetKind::PSP34,
+            name: None,
+            symbol: None,
+            decimals: Some(0),
+        }
+    }
+}
+
+/// token `id` of PSP37 (PSP35 in openbrush 2.2.0) multi token contract `token`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PSP37Asset {
+    pub token: AccountId,
+    pub id: Id,
+}
+
+impl PSP37Asset {
+    pub fn new(token: AccountId, id: Id) -> Self {
+        Self { token, id }
+    }
+}
+
+impl LoanableAsset for PSP37Asset {
+    fn send(&self, to: AccountId, amount: Balance) -> Result<(), FlashLoanError> {
+        PSP35Ref::transfer_builder(&self.token, to, self.id.clone(), amount, Vec::<u8>::new())
+            .call_flags(CallFlags::default().set_allow_reentry(true))
+            .fire()
+            .unwrap()?;
+        Ok(())
+    }
+
+    fn pull_back(&self, from: AccountId, amount: Balance) -> Result<(), FlashLoanError> {
+        PSP35Ref::transfer_from_builder(
+            &self.token,
+            from,
+            Self::env().account_id(),
+            self.id.clone(),
+            amount,
+            Vec::<u8>::new(),
+        )
+        .call_flags(CallFlags::default().set_allow_reentry(true))
+        .fire()
+        .unwrap()?;
+        Ok(())
+    }
+
+    fn balance_of(&self, owner: AccountId) -> Balance {
+        PSP35Ref::balance_of(&self.token, owner, Some(self.id.clone()))
+    }
+
+    fn metadata(&self) -> AssetMetadata {
+        AssetMetadata {
+            kind: AssetKind::PSP37,
+            name: None,
+            symbol: None,
+            decimals: None,
+        }
+    }
+}
+
+/// any asset supported by the library. Returned by `FlashLoanInternal::_loanable_asset`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Asset {
+    PSP22(PSP22Asset),
+    Native(NativeAsset),
+    PSP34(PSP34Asset),
+    PSP37(PSP37Asset),
+    PalletAssets(PalletAssetsAsset),
+}
+
+impl Asset {
+    /// returns the asset that `account` stands for according to `spec`. PSP22 tokens reject quirks.
+    pub fn from_spec(account: AccountId, spec: AssetSpec) -> Self {
+        match spec {
+            AssetSpec::PSP22 => Asset::PSP22(PSP22Asset::new(account)),
+            AssetSpec::Native => Asset::Native(NativeAsset {}),
+            AssetSpec::PSP34(id) => Asset::PSP34(PSP34Asset::new(account, id)),
+            AssetSpec::PSP37(id) => Asset::PSP37(PSP37Asset::new(account, id)),
+            AssetSpec::PalletAssets(asset_id) => {
+                Asset::PalletAssets(PalletAssetsAsset::new(asset_id))
+            }
+        }
+    }
+
+    fn inner(&self) -> &dyn LoanableAsset {
+        match self {
+            Asset::PSP22(asset) => asset,
+            Asset::Native(asset) => asset,
+            Asset::PSP34(asset) => asset,
+            Asset::PSP37(asset) => asset,
+            Asset::PalletAssets(asset) => asset,
+        }
+    }
+}
+
+impl LoanableAsset for Asset {
+    fn send(&self, to: AccountId, amount: Balance) -> Result<(), FlashLoanError> {
+        self.inner().send(to, amount)
+    }
+
+    fn pull_back(&self, from: AccountId, amount: Balance) -> Result<(), FlashLoanError> {
+        self.inner().pull_back(from, amount)
+    }
+
+    fn can_pull_back(&self) -> bool {
+        self.inner().can_pull_back()
+    }
+
+    fn balance_of(&self, owner: AccountId) -> Balance {
+        self.inner().balance_of(owner)
+    }
+
+    fn metadata(&self) -> AssetMetadata {
+        self.inner().metadata()
+    }
+}
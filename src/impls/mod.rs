@@ -1 +1,11 @@
+pub mod asset_registry;
+pub mod circuit_breaker;
+pub mod fee_oracle;
 pub mod flash_loan;
+pub mod flash_loan_policy;
+pub mod flash_loan_receiver;
+pub mod loan_stats;
+pub mod loanable_asset;
+pub mod pallet_assets;
+pub mod reconciliation;
+pub mod voucher;
@@ -0,0 +1,546 @@
+use crate::traits::{
+    flash_loan::FlashLoanError,
+    loanable_asset::*,
+    pallet_assets::{func_id, AssetId, PalletAssetsError},
+};
+use ink_env::chain_extension::ChainExtensionMethod;
+use ink_prelude::{string::String, vec::Vec};
+
+use openbrush::traits::{AccountId, Balance};
+
+/// calls to the pallet-assets chain extension. Transfers and approvals are done on behalf of the calling contract.
+pub struct PalletAssets;
+
+impl PalletAssets {
+    pub fn balance(asset_id: AssetId, owner: AccountId) -> Result<Balance, PalletAssetsError> {
+        ChainExtensionMethod::build(func_id::BALANCE)
+            .input::<(AssetId, AccountId)>()
+            .output::<Balance>()
+            .handle_error_code::<PalletAssetsError>()
+            .call(&(asset_id, owner))
+    }
+
+    pub fn transfer(
+        asset_id: AssetId,
+        target: AccountId,
+        amount: Balance,
+    ) -> Result<(), PalletAssetsError> {
+        ChainExtensionMethod::build(func_id::TRANSFER)
+            .input::<(AssetId, AccountId, Balance)>()
+            .output::<()>()
+            .handle_error_code::<PalletAssetsError>()
+            .call(&(asset_id, target, amount))
+    }
+
+    pub fn transfer_approved(
+        asset_id: AssetId,
+        owner: AccountId,
+        destination: AccountId,
+        amount: Balance,
+    ) -> Result<(), PalletAssetsError> {
+        ChainExtensionMethod::build(func_id::TRANSFER_APPROVED)
+            .input::<(AssetId, AccountId, AccountId, Balance)>()
+            .output::<()>()
+            .handle_error_code::<PalletAssetsError>()
+            .call(&(asset_id, owner, destination, amount))
+    }
+
+    pub fn approve_transfer(
+        asset_id: AssetId,
+        delegate: AccountId,
+        amount: Balance,
+    ) -> Result<(), PalletAssetsError> {
+        ChainExtensionMethod::build(func_id::APPROVE_TRANSFER)
+            .input::<(AssetId, AccountId, Balance)>()
+            .output::<()>()
+            .handle_error_code::<PalletAssetsError>()
+            .call(&(asset_id, delegate, amount))
+    }
+
+    /// returns (name, symbol, decimals)
+    pub fn metadata(asset_id: AssetId) -> Result<(Vec<u8>, Vec<u8>, u8), PalletAssetsError> {
+        ChainExtensionMethod::build(func_id::METADATA)
+            .input::<AssetId>()
+            .output::<(Vec<u8>, Vec<u8>, u8)>()
+            .handle_error_code::<PalletAssetsError>()
+            .call(&asset_id)
+    }
+}
+
+/// asset `asset_id` of pallet-assets accessed through the chain extension.
+/// The receiver repays by calling `approve_transfer` for the flash loan contract.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PalletAssetsAsset {
+    pub asset_id: AssetId,
+}
+
+impl PalletAssetsAsset {
+    pub fn new(asset_id: AssetId) -> Self {
+        Self { asset_id }
+    }
+}
+
+impl LoanableAsset for PalletAssetsAsset {
+    fn send(&self, to: AccountId, amount: Balance) -> Result<(), FlashLoanError> {
+        PalletAssets::transfer(self.asset_id, to, amount)?;
+        Ok(())
+    }
+
+    fn pull_back(&self, from: AccountId, amount: Balance) -> Result<(), FlashLoanError> {
+        PalletAssets::transfer_approved(
+            self.asset_id,
+            from,
+            ink_env::account_id::<ink_env::DefaultEnvironment>(),
+            amount,
+        )?;
+        Ok(())
+    }
+
+    fn balance_of(&self, owner: AccountId) -> Balance {
+        PalletAssets::balance(self.asset_id, owner).unwrap_or_default()
+    }
+
+    fn metadata(&self) -> AssetMetadata {
+        match PalletAssets::metadata(self.asset_id) {
+            Ok((name, symbol, decimals)) => AssetMetadata {
+                kind: AssetKind::PalletAssets,
+                name: String::from_utf8(name).ok(),
+                symbol: String::from_utf8(symbol).ok(),
+                decimals: Some(decimals),
+            },
+            Err(_) => AssetMetadata {
+                kind: AssetKind::PalletAssets,
+                name: None,
+                symbol: None,
+                decimals: None,
+            },
+        }
+    }
+}
+
+/// mock of the pallet-assets chain extension for ink off-chain test environment.
+/// Off-chain environment does not support cross contract calls, so a lender under test overrides
+/// `FlashLoanInternal::_execute_operation` in place of the receiver (see tests below).
+#[cfg(feature = "std")]
+pub mod mock {
+    use super::*;
+    use std::{cell::RefCell, collections::BTreeMap, rc::Rc};
+
+    #[derive(Default)]
+    pub struct PalletAssetsMockState {
+        /// account on behalf of which transfers and approvals are done (the calling contract)
+        pub origin: AccountId,
+        pub balances: BTreeMap<(AssetId, AccountId), Balance>,
+        pub approvals: BTreeMap<(AssetId, AccountId, AccountId), Balance>,
+        pub metadata: BTreeMap<AssetId, (Vec<u8>, Vec<u8>, u8)>,
+    }
+
+    impl PalletAssetsMockState {
+        fn balance(
+            &self,
+            asset_id: AssetId,
+            owner: AccountId,
+        ) -> Result<Balance, PalletAssetsError> {
+            if !self.metadata.contains_key(&asset_id) {
+                return Err(PalletAssetsError::Unknown);
+            }
+            Ok(*self.balances.get(&(asset_id, owner)).unwrap_or(&0))
+        }
+
+        fn transfer(
+            &mut self,
+            asset_id: AssetId,
+            from: AccountId,
+            to: AccountId,
+            amount: Balance,
+        ) -> Result<(), PalletAssetsError> {
+            let from_balance = self.balance(asset_id, from)?;
+            if from_balance < amount {
+                return Err(PalletAssetsError::BalanceLow);
+            }
+            self.balances
+                .insert((asset_id, from), from_balance - amount);
+            let to_balance = self.balance(asset_id, to)?;
+            self.balances.insert((asset_id, to), to_balance + amount);
+            Ok(())
+        }
+
+        fn call(&mut self, func_id: u32, mut input: &[u8]) -> Result<Vec<u8>, PalletAssetsError> {
+            use scale::{Decode, Encode};
+            let input = &mut input;
+            let decode_error = |_| PalletAssetsError::UnknownStatusCode(u32::MAX);
+            match func_id {
+                func_id::BALANCE => {
+                    let (asset_id, owner) =
+                        <(AssetId, AccountId)>::decode(input).map_err(decode_error)?;
+                    Ok(self.balance(asset_id, owner)?.encode())
+                }
+                func_id::TRANSFER => {
+                    let (asset_id, target, amount) =
+                        <(AssetId, AccountId, Balance)>::decode(input).map_err(decode_error)?;
+                    self.transfer(asset_id, self.origin, target, amount)?;
+                    Ok(Vec::new())
+                }
+                func_id::TRANSFER_APPROVED => {
+                    let (asset_id, owner, destination, amount) =
+                        <(AssetId, AccountId, AccountId, Balance)>::decode(input)
+                            .map_err(decode_error)?;
+                    let key = (asset_id, owner, self.origin);
+                    let approved = *self.approvals.get(&key).unwrap_or(&0);
+                    if approved < amount {
+                        return Err(PalletAssetsError::Unapproved);
+                    }
+                    self.transfer(asset_id, owner, destination, amount)?;
+                    self.approvals.insert(key, approved - amount);
+                    Ok(Vec::new())
+                }
+                func_id::APPROVE_TRANSFER => {
+                    let (asset_id, delegate, amount) =
+                        <(AssetId, AccountId, Balance)>::decode(input).map_err(decode_error)?;
+                    self.balance(asset_id, self.origin)?;
+                    self.approvals
+                        .insert((asset_id, self.origin, delegate), amount);
+                    Ok(Vec::new())
+                }
+                func_id::METADATA => {
+                    let asset_id = AssetId::decode(input).map_err(decode_error)?;
+                    self.metadata
+                        .get(&asset_id)
+                        .map(|metadata| metadata.encode())
+                        .ok_or(PalletAssetsError::Unknown)
+                }
+                _ => Err(PalletAssetsError::UnknownStatusCode(func_id)),
+            }
+        }
+    }
+
+    /// handle to the state shared by all registered functions of the mock
+    #[derive(Clone, Default)]
+    pub struct PalletAssetsMock {
+        state: Rc<RefCell<PalletAssetsMockState>>,
+    }
+
+    struct PalletAssetsMockMethod {
+        func_id: u32,
+        state: Rc<RefCell<PalletAssetsMockState>>,
+    }
+
+    impl ink_env::test::ChainExtension for PalletAssetsMockMethod {
+        fn func_id(&self) -> u32 {
+            self.func_id
+        }
+
+        fn call(&mut self, input: &[u8], output: &mut Vec<u8>) -> u32 {
+            // off-chain engine passes the input encoded once more as Vec<u8>
+            let input = match <Vec<u8> as scale::Decode>::decode(&mut &input[..]) {
+                Ok(input) => input,
+                Err(_) => return u32::MAX,
+            };
+            match self.state.borrow_mut().call(self.func_id, &input) {
+                Ok(encoded) => {
+                    output.extend(encoded);
+                    0
+                }
+                Err(error) => error.status_code(),
+            }
+        }
+    }
+
+    impl PalletAssetsMock {
+        /// registers all functions of the chain extension in the off-chain environment
+        pub fn register() -> Self {
+            let mock = Self::default();
+            for func_id in [
+                func_id::BALANCE,
+                func_id::TRANSFER,
+                func_id::TRANSFER_APPROVED,
+                func_id::APPROVE_TRANSFER,
+                func_id::METADATA,
+            ] {
+                ink_env::test::register_chain_extension(PalletAssetsMockMethod {
+                    func_id,
+                    state: mock.state.clone(),
+                });
+            }
+            mock
+        }
+
+        /// sets account on behalf of which the next calls are done. Should be the contract under test.
+        pub fn set_origin(&self, origin: AccountId) {
+            self.state.borrow_mut().origin = origin;
+        }
+
+        pub fn create(&self, asset_id: AssetId, name: &str, symbol: &str, decimals: u8) {
+            self.state.borrow_mut().metadata.insert(
+                asset_id,
+                (
+                    name.as_bytes().to_vec(),
+                    symbol.as_bytes().to_vec(),
+                    decimals,
+                ),
+            );
+        }
+
+        pub fn mint(&self, asset_id: AssetId, to: AccountId, amount: Balance) {
+            let mut state = self.state.borrow_mut();
+            let balance = state.balances.entry((asset_id, to)).or_insert(0);
+            *balance += amount;
+        }
+
+        pub fn balance(&self, asset_id: AssetId, owner: AccountId) -> Balance {
+            *self
+                .state
+                .borrow()
+                .balances
+                .get(&(asset_id, owner))
+                .unwrap_or(&0)
+        }
+
+        pub fn approval(
+            &self,
+            asset_id: AssetId,
+            owner: AccountId,
+            delegate: AccountId,
+        ) -> Balance {
+            *self
+                .state
+                .borrow()
+                .approvals
+                .get(&(asset_id, owner, delegate))
+                .unwrap_or(&0)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{mock::PalletAssetsMock, *};
+    use crate::impls::{flash_loan::*, loanable_asset::Asset};
+    use crate::traits::{
+        flash_loan::{FlashLoan, LoanId},
+        flash_loan_receiver::FlashLoanReceiverError,
+    };
+    use ink_env::{test, DefaultEnvironment};
+    use ink_primitives::Key;
+    use ink_storage::traits::{KeyPtr, SpreadAllocate, SpreadLayout};
+    use openbrush::traits::Storage;
+
+    const USDT: AssetId = 1;
+    /// account standing for USDT in the loans of the lender
+    const USDT_ACCOUNT: [u8; 32] = [1; 32];
+
+    /// lends USDT of pallet-assets for a 1% fee. The receiver is not a contract, it approves the repayment before the loan.
+    #[derive(SpreadLayout, SpreadAllocate, Storage)]
+    struct PalletAssetsLender {
+        #[storage_field]
+        flash_loan: crate::impls::flash_loan::Data,
+    }
+
+    impl PalletAssetsLender {
+        /// allocates the storage like a contract, `Default` would put every mapping at the same key
+        fn new() -> Self {
+            Self::allocate_spread(&mut KeyPtr::from(Key::from([0; 32])))
+        }
+    }
+
+    impl FlashLoanInternal for PalletAssetsLender {
+        fn _quote_fees(
+            &self,
+            _assets: &Vec<AccountId>,
+            amounts: &Vec<Balance>,
+        ) -> Result<Vec<Balance>, FlashLoanError> {
+            Ok(amounts.iter().map(|amount| amount / 100).collect())
+        }
+
+        fn _loanable_asset(&self, _asset: &AccountId) -> Result<Asset, FlashLoanError> {
+            Ok(Asset::PalletAssets(PalletAssetsAsset::new(USDT)))
+        }
+
+        /// checks that the receiver holds the loan while the operation runs
+        fn _execute_operation(
+            &mut self,
+            _loan_id: LoanId,
+            receiver: &AccountId,
+            _initiator: &AccountId,
+            _assets: &Vec<AccountId>,
+            amounts: &Vec<Balance>,
+            _fees: &Vec<Balance>,
+            _data: &Vec<u8>,
+        ) -> Result<(), FlashLoanError> {
+            if PalletAssetsAsset::new(USDT).balance_of(*receiver) < amounts[0] {
+                return Err(FlashLoanError::FlashLoanReceiverError(
+                    FlashLoanReceiverError::ExecuteOperationFailed,
+                ));
+            }
+            Ok(())
+        }
+    }
+
+    /// registers the mock with USDT, of which the contract (django) holds 1_000
+    fn setup() -> (PalletAssetsMock, test::DefaultAccounts<DefaultEnvironment>) {
+        let accounts = test::default_accounts::<DefaultEnvironment>();
+        test::set_callee::<DefaultEnvironment>(accounts.django);
+        let assets = PalletAssetsMock::register();
+        assets.set_origin(accounts.django);
+        assets.create(USDT, "Tether", "USDT", 6);
+        assets.mint(USDT, accounts.django, 1_000);
+        (assets, accounts)
+    }
+
+    #[ink_lang::test]
+    fn send_transfers_from_the_contract() {
+        let (assets, accounts) = setup();
+        let asset = PalletAssetsAsset::new(USDT);
+        assert_eq!(asset.send(accounts.bob, 400), Ok(()));
+        assert_eq!(assets.balance(USDT, accounts.django), 600);
+        assert_eq!(assets.balance(USDT, accounts.bob), 400);
+        assert_eq!(
+            asset.send(accounts.bob, 601),
+            Err(FlashLoanError::PalletAssetsError(
+                PalletAssetsError::BalanceLow
+            ))
+        );
+        assert_eq!(
+            PalletAssetsAsset::new(2).send(accounts.bob, 1),
+            Err(FlashLoanError::PalletAssetsError(
+                PalletAssetsError::Unknown
+            ))
+        );
+    }
+
+    #[ink_lang::test]
+    fn pull_back_needs_approval_of_the_receiver() {
+        let (assets, accounts) = setup();
+        let asset = PalletAssetsAsset::new(USDT);
+        asset.send(accounts.bob, 400).unwrap();
+        assert_eq!(
+            asset.pull_back(accounts.bob, 400),
+            Err(FlashLoanError::PalletAssetsError(
+                PalletAssetsError::Unapproved
+            ))
+        );
+
+        assets.set_origin(accounts.bob);
+        PalletAssets::approve_transfer(USDT, accounts.django, 400).unwrap();
+        assets.set_origin(accounts.django);
+        assert_eq!(asset.pull_back(accounts.bob, 400), Ok(()));
+        assert_eq!(assets.balance(USDT, accounts.django), 1_000);
+        assert_eq!(assets.balance(USDT, accounts.bob), 0);
+        assert_eq!(assets.approval(USDT, accounts.bob, accounts.django), 0);
+    }
+
+    #[ink_lang::test]
+    fn balance_of_is_zero_for_unknown_assets() {
+        let (_assets, accounts) = setup();
+        assert_eq!(
+            PalletAssetsAsset::new(USDT).balance_of(accounts.django),
+            1_000
+        );
+        assert_eq!(PalletAssetsAsset::new(USDT).balance_of(accounts.bob), 0);
+        assert_eq!(PalletAssetsAsset::new(2).balance_of(accounts.django), 0);
+    }
+
+    #[ink_lang::test]
+    fn metadata_is_read_from_the_pallet() {
+        let (_assets, _accounts) = setup();
+        assert_eq!(
+            PalletAssetsAsset::new(USDT).metadata(),
+            AssetMetadata {
+                kind: AssetKind::PalletAssets,
+                name: Some(String::from("Tether")),
+                symbol: Some(String::from("USDT")),
+                decimals: Some(6),
+            }
+        );
+        assert_eq!(
+            PalletAssetsAsset::new(2).metadata(),
+            AssetMetadata {
+                kind: AssetKind::PalletAssets,
+                name: None,
+                symbol: None,
+                decimals: None,
+            }
+        );
+    }
+
+    /// gives bob the 1% fee of a loan of `amount` and approves the lender (django) to take back `approved`
+    fn receiver_approves(
+        assets: &PalletAssetsMock,
+        accounts: &test::DefaultAccounts<DefaultEnvironment>,
+        amount: Balance,
+        approved: Balance,
+    ) {
+        assets.mint(USDT, accounts.bob, amount / 100);
+        assets.set_origin(accounts.bob);
+        PalletAssets::approve_transfer(USDT, accounts.django, approved).unwrap();
+        assets.set_origin(accounts.django);
+    }
+
+    #[ink_lang::test]
+    fn flash_loan_lends_and_takes_back_a_pallet_asset_with_the_fee() {
+        let (assets, accounts) = setup();
+        let mut lender = PalletAssetsLender::new();
+        receiver_approves(&assets, &accounts, 500, 505);
+
+        assert_eq!(
+            lender.flash_loan(
+                accounts.bob,
+                vec![AccountId::from(USDT_ACCOUNT)],
+                vec![500],
+                vec![]
+            ),
+            Ok(())
+        );
+        assert_eq!(assets.balance(USDT, accounts.django), 1_005);
+        assert_eq!(assets.balance(USDT, accounts.bob), 0);
+        assert_eq!(assets.approval(USDT, accounts.bob, accounts.django), 0);
+        assert!(!lender.is_in_flash_loan());
+        assert_eq!(lender.outstanding(AccountId::from(USDT_ACCOUNT)), 0);
+        let receipt = lender.loan_receipt(1).unwrap();
+        assert_eq!(receipt.receiver, accounts.bob);
+        assert_eq!(receipt.amounts, vec![500]);
+        assert_eq!(receipt.fees, vec![5]);
+    }
+
+    #[ink_lang::test]
+    fn flash_loan_fails_if_the_receiver_does_not_approve_the_fee() {
+        let (assets, accounts) = setup();
+        let mut lender = PalletAssetsLender::new();
+        receiver_approves(&assets, &accounts, 500, 500);
+
+        // on chain the error reverts the loan, off-chain the state is left as it was when the loan failed
+        assert_eq!(
+            lender.flash_loan(
+                accounts.bob,
+                vec![AccountId::from(USDT_ACCOUNT)],
+                vec![500],
+                vec![]
+            ),
+            Err(FlashLoanError::PalletAssetsError(
+                PalletAssetsError::Unapproved
+            ))
+        );
+        assert_eq!(assets.balance(USDT, accounts.bob), 505);
+        assert_eq!(lender.loan_receipt(1), None);
+    }
+
+    #[ink_lang::test]
+    fn flash_loan_fails_if_the_lender_does_not_hold_the_amount() {
+        let (assets, accounts) = setup();
+        let mut lender = PalletAssetsLender::new();
+        receiver_approves(&assets, &accounts, 1_001, 1_011);
+
+        assert_eq!(
+            lender.flash_loan(
+                accounts.bob,
+                vec![AccountId::from(USDT_ACCOUNT)],
+                vec![1_001],
+                vec![]
+            ),
+            Err(FlashLoanError::PalletAssetsError(
+                PalletAssetsError::BalanceLow
+            ))
+        );
+        assert_eq!(assets.balance(USDT, accounts.django), 1_000);
+    }
+}
@@ -0,0 +1,189 @@
+use crate::impls::flash_loan::{self, when_not_in_flash_loan};
+use crate::impls::loanable_asset::PSP22Asset;
+use crate::traits::loanable_asset::LoanableAsset;
+pub use crate::{impls::reconciliation, traits::reconciliation::*};
+use ink_prelude::vec::Vec;
+
+use openbrush::contracts::ownable::{self, only_owner};
+use openbrush::contracts::traits::psp22::PSP22Ref;
+use openbrush::modifiers;
+use openbrush::storage::Mapping;
+use openbrush::traits::{AccountId, Balance, Storage};
+
+pub const STORAGE_KEY: u32 = openbrush::storage_unique_key!(Data);
+
+#[derive(Default, Debug)]
+#[openbrush::upgradeable_storage(STORAGE_KEY)]
+pub struct Data {
+    pub principal: Mapping<AccountId, Balance>,
+    /// earned fees that were not withdrawn
+    pub fees: Mapping<AccountId, Balance>,
+    pub treasury: Option<AccountId>,
+    pub _reserved: Option<()>,
+}
+
+pub trait ReconciliationEvents {
+    /// !!! should be overriden in contract !!!
+    /// emits Deposited event
+    fn _emit_deposited_event(&self, asset: AccountId, from: AccountId, amount: Balance);
+
+    /// !!! should be overriden in contract !!!
+    /// emits Skimmed event
+    fn _emit_skimmed_event(&self, asset: AccountId, treasury: AccountId, amount: Balance);
+
+    /// !!! should be overriden in contract !!!
+    /// emits Rescued event
+    fn _emit_rescued_event(&self, token: AccountId, to: AccountId, amount: Balance);
+}
+
+impl<T> ReconciliationEvents for T {
+    default fn _emit_deposited_event(&self, _asset: AccountId, _from: AccountId, _amount: Balance) {
+    }
+
+    default fn _emit_skimmed_event(
+        &self,
+        _asset: AccountId,
+        _treasury: AccountId,
+        _amount: Balance,
+    ) {
+    }
+
+    default fn _emit_rescued_event(&self, _token: AccountId, _to: AccountId, _amount: Balance) {}
+}
+
+pub trait ReconciliationInternal {
+    /// should be called after a loan for every lent asset
+    fn _track_fee(&mut self, asset: &AccountId, fee: Balance);
+
+    /// should be called when the contract withdraws `amount` of `asset`, it is taken from fees first
+    fn _track_withdrawal(&mut self, asset: &AccountId, amount: Balance);
+
+    fn _reconcile(&self, asset: &AccountId) -> AssetReconciliation;
+
+    /// may be overriden in contract
+    /// returns false for tokens that can not be rescued. By default tokens with tracked principal or fees.
+    fn _can_rescue(&self, token: &AccountId) -> bool;
+
+    /// may be overriden in contract
+    /// is called after `deposit`, `skim` and `rescue` move tokens, e.g. to record the new balance
+    fn _on_balance_changed(&mut self, asset: &AccountId);
+}
+
+impl<T: Storage<Data>> ReconciliationInternal for T {
+    default fn _track_fee(&mut self, asset: &AccountId, fee: Balance) {
+        let fees = self.data().fees.get(asset).unwrap_or_default();
+        self.data().fees.insert(asset, &(fees + fee));
+    }
+
+    default fn _track_withdrawal(&mut self, asset: &AccountId, amount: Balance) {
+        let fees = self.data().fees.get(asset).unwrap_or_default();
+        let from_fees = amount.min(fees);
+        self.data().fees.insert(asset, &(fees - from_fees));
+        // principal stays unrecorded for assets that were never deposited
+        if let Some(principal) = self.data().principal.get(asset) {
+            self.data()
+                .principal
+                .insert(asset, &principal.saturating_sub(amount - from_fees));
+        }
+    }
+
+    default fn _reconcile(&self, asset: &AccountId) -> AssetReconciliation {
+        let balance = PSP22Asset::new(*asset).balance_of(Self::env().account_id());
+        let principal = self.data().principal.get(asset).unwrap_or_default();
+        let fees = self.data().fees.get(asset).unwrap_or_default();
+        let tracked = principal.saturating_add(fees);
+        AssetReconciliation {
+            asset: *asset,
+            balance,
+            principal,
+            fees,
+            excess: balance.saturating_sub(tracked),
+            shortfall: tracked.saturating_sub(balance),
+        }
+    }
+
+    default fn _can_rescue(&self, token: &AccountId) -> bool {
+        !self.data().principal.contains(token) && !self.data().fees.contains(token)
+    }
+
+    default fn _on_balance_changed(&mut self, _asset: &AccountId) {}
+}
+
+impl<T: Storage<Data> + Storage<ownable::Data> + Storage<flash_loan::Data>> Reconciliation for T {
+    #[modifiers(only_owner, when_not_in_flash_loan)]
+    default fn deposit(
+        &mut self,
+        asset: AccountId,
+        amount: Balance,
+    ) -> Result<(), ReconciliationError> {
+        let caller = Self::env().caller();
+        let this = Self::env().account_id();
+        let balance_before = PSP22Ref::balance_of(&asset, this);
+        PSP22Ref::transfer_from(&asset, caller, this, amount, Vec::<u8>::new())?;
+        // fee-on-transfer tokens deliver less than `amount`
+        let received = PSP22Ref::balance_of(&asset, this).saturating_sub(balance_before);
+        let principal = self
+            .data::<Data>()
+            .principal
+            .get(&asset)
+            .unwrap_or_default();
+        self.data::<Data>()
+            .principal
+            .insert(&asset, &(principal + received));
+        ReconciliationInternal::_on_balance_changed(self, &asset);
+        self._emit_deposited_event(asset, caller, received);
+        Ok(())
+    }
+
+    default fn reconcile(&self, assets: Vec<AccountId>) -> Vec<AssetReconciliation> {
+        assets
+            .iter()
+            .map(|asset| ReconciliationInternal::_reconcile(self, asset))
+            .collect()
+    }
+
+    #[modifiers(only_owner, when_not_in_flash_loan)]
+    default fn skim(&mut self, asset: AccountId) -> Result<(), ReconciliationError> {
+        let treasury = self
+            .data::<Data>()
+            .treasury
+            .ok_or(ReconciliationError::TreasuryNotSet)?;
+        if !self.data::<Data>().principal.contains(&asset) {
+            return Err(ReconciliationError::PrincipalNotRecorded);
+        }
+        let excess = ReconciliationInternal::_reconcile(self, &asset).excess;
+        if excess == 0 {
+            return Ok(());
+        }
+        PSP22Ref::transfer(&asset, treasury, excess, Vec::<u8>::new())?;
+        ReconciliationInternal::_on_balance_changed(self, &asset);
+        self._emit_skimmed_event(asset, treasury, excess);
+        Ok(())
+    }
+
+    #[modifiers(only_owner, when_not_in_flash_loan)]
+    default fn rescue(
+        &mut self,
+        token: AccountId,
+        to: AccountId,
+        amount: Balance,
+    ) -> Result<(), ReconciliationError> {
+        if !ReconciliationInternal::_can_rescue(self, &token) {
+            return Err(ReconciliationError::NotRescuable);
+        }
+        PSP22Ref::transfer(&token, to, amount, Vec::<u8>::new())?;
+        ReconciliationInternal::_on_balance_changed(self, &token);
+        self._emit_rescued_event(token, to, amount);
+        Ok(())
+    }
+
+    #[modifiers(only_owner)]
+    default fn set_treasury(&mut self, treasury: AccountId) -> Result<(), ReconciliationError> {
+        self.data::<Data>().treasury = Some(treasury);
+        Ok(())
+    }
+
+    default fn treasury(&self) -> Option<AccountId> {
+        self.data::<Data>().treasury
+    }
+}
@@ -0,0 +1,163 @@
+use crate::impls::flash_loan::{self, when_not_in_flash_loan};
+use crate::traits::asset_registry::FeeModel;
+use crate::traits::flash_loan::{FlashLoan, FlashLoanError};
+pub use crate::{impls::voucher, traits::voucher::*};
+use ink_env::hash::{Blake2x256, HashOutput};
+use ink_prelude::vec::Vec;
+
+use openbrush::contracts::ownable::{self, only_owner};
+use openbrush::modifiers;
+use openbrush::storage::Mapping;
+use openbrush::traits::{AccountId, Balance, Storage};
+
+pub const STORAGE_KEY: u32 = openbrush::storage_unique_key!(Data);
+
+#[derive(Default, Debug)]
+#[openbrush::upgradeable_storage(STORAGE_KEY)]
+pub struct Data {
+    pub signer: Option<AccountId>,
+    pub used_nonces: Mapping<(AccountId, u64), ()>,
+    /// voucher of the loan started by `flash_loan_with_voucher`
+    pub active: Option<Voucher>,
+    pub _reserved: Option<()>,
+}
+
+pub trait VoucherInternal {
+    /// checks `voucher` for a loan of `amounts` of `assets` by the caller, marks its nonce as used and makes it active
+    fn _use_voucher(
+        &mut self,
+        voucher: &SignedVoucher,
+        assets: &Vec<AccountId>,
+        amounts: &Vec<Balance>,
+    ) -> Result<(), FlashLoanError>;
+
+    /// returns true if the active voucher allows the loan, e.g. in `_is_allowlisted`.
+    /// Loans nested in the loan of the voucher are not allowed by it.
+    fn _voucher_allows(&self) -> bool;
+
+    /// returns fees of the loan set by the active voucher, e.g. in `_calculate_fees`. None if it does not set them.
+    fn _voucher_fees(&self, amounts: &Vec<Balance>) -> Option<Vec<Balance>>;
+
+    /// returns the account of the ECDSA key that signed `voucher`
+    fn _voucher_signer(&self, voucher: &SignedVoucher) -> Result<AccountId, FlashLoanError>;
+}
+
+impl<T: Storage<Data> + Storage<flash_loan::Data>> VoucherInternal for T {
+    default fn _use_voucher(
+        &mut self,
+        voucher: &SignedVoucher,
+        assets: &Vec<AccountId>,
+        amounts: &Vec<Balance>,
+    ) -> Result<(), FlashLoanError> {
+        let signer = VoucherInternal::_voucher_signer(self, voucher)?;
+        let terms = &voucher.voucher;
+        if Some(signer) != self.data::<Data>().signer
+            || terms.lender != Self::env().account_id()
+            || terms.caller != Self::env().caller()
+        {
+            return Err(FlashLoanError::InvalidVoucher);
+        }
+        if Self::env().block_number() > terms.expiry {
+            return Err(FlashLoanError::VoucherExpired);
+        }
+        if self
+            .data::<Data>()
+            .used_nonces
+            .contains(&(terms.caller, terms.nonce))
+        {
+            return Err(FlashLoanError::VoucherReplayed);
+        }
+        for (asset, limit) in terms.limits.iter() {
+            let borrowed = (0..assets.len())
+                .filter(|i| assets[*i] == *asset)
+                .fold(0, |sum: Balance, i| sum.saturating_add(amounts[i]));
+            if borrowed > *limit {
+                return Err(FlashLoanError::VoucherLimitExceeded);
+            }
+        }
+        if assets
+            .iter()
+            .any(|asset| !terms.limits.iter().any(|(limited, _)| limited == asset))
+        {
+            return Err(FlashLoanError::VoucherLimitExceeded);
+        }
+        self.data::<Data>()
+            .used_nonces
+            .insert(&(terms.caller, terms.nonce), &());
+        self.data::<Data>().active = Some(terms.clone());
+        Ok(())
+    }
+
+    default fn _voucher_allows(&self) -> bool {
+        match &self.data::<Data>().active {
+            Some(voucher) => {
+                voucher.caller == Self::env().caller()
+                    && self.data::<flash_loan::Data>().loans_in_progress == 0
+            }
+            None => false,
+        }
+    }
+
+    default fn _voucher_fees(&self, amounts: &Vec<Balance>) -> Option<Vec<Balance>> {
+        if !VoucherInternal::_voucher_allows(self) {
+            return None;
+        }
+        let fee_e6 = self.data::<Data>().active.as_ref()?.fee_e6?;
+        Some(
+            amounts
+                .iter()
+                .map(|amount| FeeModel::Proportional { fee_e6 }.fee(*amount))
+                .collect(),
+        )
+    }
+
+    default fn _voucher_signer(
+        &self,
+        voucher: &SignedVoucher,
+    ) -> Result<AccountId, FlashLoanError> {
+        let mut message_hash = <Blake2x256 as HashOutput>::Type::default();
+        ink_env::hash_encoded::<Blake2x256, _>(&voucher.voucher, &mut message_hash);
+        let mut public_key = [0u8; 33];
+        ink_env::ecdsa_recover(&voucher.signature, &message_hash, &mut public_key)
+            .map_err(|_| FlashLoanError::InvalidVoucher)?;
+        let mut account = <Blake2x256 as HashOutput>::Type::default();
+        ink_env::hash_bytes::<Blake2x256>(&public_key, &mut account);
+        Ok(AccountId::from(account))
+    }
+}
+
+impl<T: Storage<Data> + Storage<ownable::Data> + Storage<flash_loan::Data> + FlashLoan> Vouchers
+    for T
+{
+    #[modifiers(when_not_in_flash_loan)]
+    default fn flash_loan_with_voucher(
+        &mut self,
+        receiver: AccountId,
+        assets: Vec<AccountId>,
+        amounts: Vec<Balance>,
+        data: Vec<u8>,
+        voucher: SignedVoucher,
+    ) -> Result<(), FlashLoanError> {
+        VoucherInternal::_use_voucher(self, &voucher, &assets, &amounts)?;
+        FlashLoan::flash_loan(self, receiver, assets, amounts, data)?;
+        self.data::<Data>().active = None;
+        Ok(())
+    }
+
+    #[modifiers(only_owner)]
+    default fn set_voucher_signer(
+        &mut self,
+        signer: Option<AccountId>,
+    ) -> Result<(), VoucherError> {
+        self.data::<Data>().signer = signer;
+        Ok(())
+    }
+
+    default fn voucher_signer(&self) -> Option<AccountId> {
+        self.data::<Data>().signer
+    }
+
+    default fn is_nonce_used(&self, caller: AccountId, nonce: u64) -> bool {
+        self.data::<Data>().used_nonces.contains(&(caller, nonce))
+    }
+}
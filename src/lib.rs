@@ -3,4 +3,6 @@
 #![feature(try_blocks)]
 
 pub mod impls;
+pub mod raw_call;
+pub mod storage;
 pub mod traits;
@@ -0,0 +1,28 @@
+//! input and output of cross contract calls built from a selector and already encoded arguments,
+//! like calls passed to the flash loan executor or made by test contracts.
+
+use ink_prelude::{vec, vec::Vec};
+
+/// already encoded arguments, pushed to the call input as they are
+pub struct EncodedArgs(pub Vec<u8>);
+
+impl scale::Encode for EncodedArgs {
+    fn size_hint(&self) -> usize {
+        self.0.len()
+    }
+
+    fn encode_to<O: scale::Output + ?Sized>(&self, dest: &mut O) {
+        dest.write(&self.0)
+    }
+}
+
+/// whole output of a call
+pub struct RawOutput(pub Vec<u8>);
+
+impl scale::Decode for RawOutput {
+    fn decode<I: scale::Input>(input: &mut I) -> Result<Self, scale::Error> {
+        let mut output = vec![0u8; input.remaining_len()?.unwrap_or_default()];
+        input.read(&mut output)?;
+        Ok(RawOutput(output))
+    }
+}
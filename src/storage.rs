@@ -0,0 +1,484 @@
+//! storage primitives that, unlike `openbrush::storage::Mapping`, can be enumerated.
+//! Items are kept at indexes `0..len`, removing an item moves the last one into its place, so the order is not stable.
+
+use ink_prelude::vec::Vec;
+use ink_storage::traits::{KeyPtr, PackedLayout, SpreadAllocate, SpreadLayout};
+use openbrush::storage::Mapping;
+
+/// set of keys that can be listed page by page
+#[derive(Debug)]
+pub struct EnumerableSet<K> {
+    items: Mapping<u32, K>,
+    /// index of the key in `items` + 1
+    positions: Mapping<K, u32>,
+    len: u32,
+}
+
+impl<K: PackedLayout + Clone> EnumerableSet<K> {
+    /// returns false if `key` was already in the set
+    pub fn insert(&mut self, key: &K) -> bool {
+        if self.contains(key) {
+            return false;
+        }
+        self.items.insert(&self.len, key);
+        self.len += 1;
+        self.positions.insert(key, &self.len);
+        true
+    }
+
+    /// returns false if `key` was not in the set
+    pub fn remove(&mut self, key: &K) -> bool {
+        let position = match self.positions.get(key) {
+            Some(position) => position,
+            None => return false,
+        };
+        // positions are kept in `1..=len`, anything else is not a key of the set
+        let last = match self.len.checked_sub(1) {
+            Some(last) if position > 0 && position - 1 <= last => last,
+            _ => return false,
+        };
+        if position - 1 != last {
+            let moved = self.items.get(&last).unwrap();
+            self.items.insert(&(position - 1), &moved);
+            self.positions.insert(&moved, &position);
+        }
+        self.items.remove(&last);
+        self.positions.remove(key);
+        self.len = last;
+        true
+    }
+
+    pub fn contains(&self, key: &K) -> bool {
+        self.positions.contains(key)
+    }
+
+    pub fn len(&self) -> u32 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// returns the key at `index`, None if `index >= len`
+    pub fn at(&self, index: u32) -> Option<K> {
+        self.items.get(&index)
+    }
+
+    /// returns up to `limit` keys starting from index `offset`
+    pub fn page(&self, offset: u32, limit: u32) -> Vec<K> {
+        let end = offset.saturating_add(limit).min(self.len);
+        (offset..end).filter_map(|index| self.at(index)).collect()
+    }
+}
+
+/// map whose entries can be listed page by page
+#[derive(Debug)]
+pub struct EnumerableMap<K, V> {
+    keys: EnumerableSet<K>,
+    values: Mapping<K, V>,
+}
+
+impl<K: PackedLayout + Clone, V: PackedLayout> EnumerableMap<K, V> {
+    /// inserts or updates the value of `key`
+    pub fn insert(&mut self, key: &K, value: &V) {
+        self.keys.insert(key);
+        self.values.insert(key, value);
+    }
+
+    /// returns false if `key` was not in the map
+    pub fn remove(&mut self, key: &K) -> bool {
+        self.values.remove(key);
+        self.keys.remove(key)
+    }
+
+    pub fn get(&self, key: &K) -> Option<V> {
+        self.values.get(key)
+    }
+
+    pub fn contains(&self, key: &K) -> bool {
+        self.keys.contains(key)
+    }
+
+    pub fn len(&self) -> u32 {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// returns up to `limit` keys starting from index `offset`
+    pub fn keys(&self, offset: u32, limit: u32) -> Vec<K> {
+        self.keys.page(offset, limit)
+    }
+
+    /// returns up to `limit` entries starting from index `offset`
+    pub fn page(&self, offset: u32, limit: u32) -> Vec<(K, V)> {
+        self.keys
+            .page(offset, limit)
+            .into_iter()
+            .filter_map(|key| {
+                let value = self.values.get(&key)?;
+                Some((key, value))
+            })
+            .collect()
+    }
+}
+
+/// sets of values grouped by key, the values of each key can be listed page by page like `EnumerableSet`
+#[derive(Debug)]
+pub struct EnumerableIndex<K, V> {
+    items: Mapping<(K, u32), V>,
+    /// index of the value in `items` of the key + 1
+    positions: Mapping<(K, V), u32>,
+    lens: Mapping<K, u32>,
+}
+
+impl<K: PackedLayout + Clone, V: PackedLayout + Clone> EnumerableIndex<K, V> {
+    /// returns false if `value` was already in the set of `key`
+    pub fn insert(&mut self, key: &K, value: &V) -> bool {
+        if self.contains(key, value) {
+            return false;
+        }
+        let len = self.len(key);
+        self.items.insert(&(key.clone(), len), value);
+        self.positions
+            .insert(&(key.clone(), value.clone()), &(len + 1));
+        self.lens.insert(key, &(len + 1));
+        true
+    }
+
+    /// returns false if `value` was not in the set of `key`
+    pub fn remove(&mut self, key: &K, value: &V) -> bool {
+        let position = match self.positions.get(&(key.clone(), value.clone())) {
+            Some(position) => position,
+            None => return false,
+        };
+        // positions are kept in `1..=len` of the key, anything else is not a value of the key
+        let last = match self.len(key).checked_sub(1) {
+            Some(last) if position > 0 && position - 1 <= last => last,
+            _ => return false,
+        };
+        if position - 1 != last {
+            let moved = self.items.get(&(key.clone(), last)).unwrap();
+            self.items.insert(&(key.clone(), position - 1), &moved);
+            self.positions.insert(&(key.clone(), moved), &position);
+        }
+        self.items.remove(&(key.clone(), last));
+        self.positions.remove(&(key.clone(), value.clone()));
+        if last == 0 {
+            self.lens.remove(key);
+        } else {
+            self.lens.insert(key, &last);
+        }
+        true
+    }
+
+    pub fn contains(&self, key: &K, value: &V) -> bool {
+        self.positions.contains(&(key.clone(), value.clone()))
+    }
+
+    /// returns the number of values of `key`
+    pub fn len(&self, key: &K) -> u32 {
+        self.lens.get(key).unwrap_or_default()
+    }
+
+    /// returns up to `limit` values of `key` starting from index `offset`
+    pub fn page(&self, key: &K, offset: u32, limit: u32) -> Vec<V> {
+        let end = offset.saturating_add(limit).min(self.len(key));
+        (offset..end)
+            .filter_map(|index| self.items.get(&(key.clone(), index)))
+            .collect()
+    }
+}
+
+// `Default`, `SpreadLayout` and `SpreadAllocate` are implemented manually because the derived implementations
+// add trait bounds to keys and values
+
+impl<K> Default for EnumerableSet<K> {
+    fn default() -> Self {
+        Self {
+            items: Default::default(),
+            positions: Default::default(),
+            len: 0,
+        }
+    }
+}
+
+impl<K> SpreadLayout for EnumerableSet<K> {
+    const FOOTPRINT: u64 = 3;
+    const REQUIRES_DEEP_CLEAN_UP: bool = false;
+
+    fn pull_spread(ptr: &mut KeyPtr) -> Self {
+        Self {
+            items: SpreadLayout::pull_spread(ptr),
+            positions: SpreadLayout::pull_spread(ptr),
+            len: SpreadLayout::pull_spread(ptr),
+        }
+    }
+
+    fn push_spread(&self, ptr: &mut KeyPtr) {
+        SpreadLayout::push_spread(&self.items, ptr);
+        SpreadLayout::push_spread(&self.positions, ptr);
+        SpreadLayout::push_spread(&self.len, ptr);
+    }
+
+    fn clear_spread(&self, ptr: &mut KeyPtr) {
+        SpreadLayout::clear_spread(&self.items, ptr);
+        SpreadLayout::clear_spread(&self.positions, ptr);
+        SpreadLayout::clear_spread(&self.len, ptr);
+    }
+}
+
+impl<K> SpreadAllocate for EnumerableSet<K> {
+    fn allocate_spread(ptr: &mut KeyPtr) -> Self {
+        Self {
+            items: SpreadAllocate::allocate_spread(ptr),
+            positions: SpreadAllocate::allocate_spread(ptr),
+            len: SpreadAllocate::allocate_spread(ptr),
+        }
+    }
+}
+
+impl<K, V> Default for EnumerableMap<K, V> {
+    fn default() -> Self {
+        Self {
+            keys: Default::default(),
+            values: Default::default(),
+        }
+    }
+}
+
+impl<K, V> SpreadLayout for EnumerableMap<K, V> {
+    const FOOTPRINT: u64 = 4;
+    const REQUIRES_DEEP_CLEAN_UP: bool = false;
+
+    fn pull_spread(ptr: &mut KeyPtr) -> Self {
+        Self {
+            keys: SpreadLayout::pull_spread(ptr),
+            values: SpreadLayout::pull_spread(ptr),
+        }
+    }
+
+    fn push_spread(&self, ptr: &mut KeyPtr) {
+        SpreadLayout::push_spread(&self.keys, ptr);
+        SpreadLayout::push_spread(&self.values, ptr);
+    }
+
+    fn clear_spread(&self, ptr: &mut KeyPtr) {
+        SpreadLayout::clear_spread(&self.keys, ptr);
+        SpreadLayout::clear_spread(&self.values, ptr);
+    }
+}
+
+impl<K, V> SpreadAllocate for EnumerableMap<K, V> {
+    fn allocate_spread(ptr: &mut KeyPtr) -> Self {
+        Self {
+            keys: SpreadAllocate::allocate_spread(ptr),
+            values: SpreadAllocate::allocate_spread(ptr),
+        }
+    }
+}
+
+impl<K, V> Default for EnumerableIndex<K, V> {
+    fn default() -> Self {
+        Self {
+            items: Default::default(),
+            positions: Default::default(),
+            lens: Default::default(),
+        }
+    }
+}
+
+impl<K, V> SpreadLayout for EnumerableIndex<K, V> {
+    const FOOTPRINT: u64 = 3;
+    const REQUIRES_DEEP_CLEAN_UP: bool = false;
+
+    fn pull_spread(ptr: &mut KeyPtr) -> Self {
+        Self {
+            items: SpreadLayout::pull_spread(ptr),
+            positions: SpreadLayout::pull_spread(ptr),
+            lens: SpreadLayout::pull_spread(ptr),
+        }
+    }
+
+    fn push_spread(&self, ptr: &mut KeyPtr) {
+        SpreadLayout::push_spread(&self.items, ptr);
+        SpreadLayout::push_spread(&self.positions, ptr);
+        SpreadLayout::push_spread(&self.lens, ptr);
+    }
+
+    fn clear_spread(&self, ptr: &mut KeyPtr) {
+        SpreadLayout::clear_spread(&self.items, ptr);
+        SpreadLayout::clear_spread(&self.positions, ptr);
+        SpreadLayout::clear_spread(&self.lens, ptr);
+    }
+}
+
+impl<K, V> SpreadAllocate for EnumerableIndex<K, V> {
+    fn allocate_spread(ptr: &mut KeyPtr) -> Self {
+        Self {
+            items: SpreadAllocate::allocate_spread(ptr),
+            positions: SpreadAllocate::allocate_spread(ptr),
+            lens: SpreadAllocate::allocate_spread(ptr),
+        }
+    }
+}
+
+/// implemented manually because the derived implementation does not bound the keys and values with `TypeInfo`
+#[cfg(feature = "std")]
+const _: () = {
+    use ink_metadata::layout::{FieldLayout, Layout, StructLayout};
+    use ink_storage::traits::StorageLayout;
+    use scale_info::TypeInfo;
+
+    impl<K: TypeInfo + 'static> StorageLayout for EnumerableSet<K> {
+        fn layout(key_ptr: &mut KeyPtr) -> Layout {
+            Layout::Struct(StructLayout::new([
+                FieldLayout::new("items", <Mapping<u32, K> as StorageLayout>::layout(key_ptr)),
+                FieldLayout::new(
+                    "positions",
+                    <Mapping<K, u32> as StorageLayout>::layout(key_ptr),
+                ),
+                FieldLayout::new("len", <u32 as StorageLayout>::layout(key_ptr)),
+            ]))
+        }
+    }
+
+    impl<K: TypeInfo + 'static, V: TypeInfo + 'static> StorageLayout for EnumerableMap<K, V> {
+        fn layout(key_ptr: &mut KeyPtr) -> Layout {
+            Layout::Struct(StructLayout::new([
+                FieldLayout::new("keys", <EnumerableSet<K> as StorageLayout>::layout(key_ptr)),
+                FieldLayout::new("values", <Mapping<K, V> as StorageLayout>::layout(key_ptr)),
+            ]))
+        }
+    }
+
+    impl<K: TypeInfo + 'static, V: TypeInfo + 'static> StorageLayout for EnumerableIndex<K, V> {
+        fn layout(key_ptr: &mut KeyPtr) -> Layout {
+            Layout::Struct(StructLayout::new([
+                FieldLayout::new(
+                    "items",
+                    <Mapping<(K, u32), V> as StorageLayout>::layout(key_ptr),
+                ),
+                FieldLayout::new(
+                    "positions",
+                    <Mapping<(K, V), u32> as StorageLayout>::layout(key_ptr),
+                ),
+                FieldLayout::new("lens", <Mapping<K, u32> as StorageLayout>::layout(key_ptr)),
+            ]))
+        }
+    }
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ink_primitives::Key;
+
+    /// allocates the mappings at distinct storage keys, like the fields of a contract
+    fn allocate<T: SpreadAllocate>() -> T {
+        T::allocate_spread(&mut KeyPtr::from(Key::from([0; 32])))
+    }
+
+    fn set_of(keys: &[u32]) -> EnumerableSet<u32> {
+        let mut set = allocate::<EnumerableSet<u32>>();
+        for key in keys {
+            assert!(set.insert(key));
+        }
+        set
+    }
+
+    #[ink_lang::test]
+    fn set_insert_keeps_keys_in_order() {
+        let mut set = set_of(&[10, 20, 30]);
+        assert!(!set.insert(&20));
+        assert_eq!(set.len(), 3);
+        assert_eq!(set.page(0, 10), vec![10, 20, 30]);
+    }
+
+    #[ink_lang::test]
+    fn set_remove_first_moves_the_last_key_into_its_place() {
+        let mut set = set_of(&[10, 20, 30]);
+        assert!(set.remove(&10));
+        assert_eq!(set.page(0, 10), vec![30, 20]);
+        assert!(!set.contains(&10));
+        assert!(!set.remove(&10));
+    }
+
+    #[ink_lang::test]
+    fn set_remove_middle_and_last() {
+        let mut set = set_of(&[10, 20, 30]);
+        assert!(set.remove(&20));
+        assert_eq!(set.page(0, 10), vec![10, 30]);
+        assert!(set.remove(&30));
+        assert_eq!(set.page(0, 10), vec![10]);
+        assert_eq!(set.at(1), None);
+        assert!(set.remove(&10));
+        assert!(set.is_empty());
+        assert_eq!(set.page(0, 10), Vec::<u32>::new());
+    }
+
+    #[ink_lang::test]
+    fn set_reinsert_after_remove_appends_the_key() {
+        let mut set = set_of(&[10, 20, 30]);
+        assert!(set.remove(&10));
+        assert!(set.insert(&10));
+        assert_eq!(set.page(0, 10), vec![30, 20, 10]);
+        // the moved key can still be removed
+        assert!(set.remove(&30));
+        assert_eq!(set.page(0, 10), vec![10, 20]);
+    }
+
+    #[ink_lang::test]
+    fn set_page_beyond_len_is_empty_or_truncated() {
+        let set = set_of(&[10, 20, 30]);
+        assert_eq!(set.page(2, 10), vec![30]);
+        assert_eq!(set.page(3, 10), Vec::<u32>::new());
+        assert_eq!(set.page(u32::MAX, u32::MAX), Vec::<u32>::new());
+    }
+
+    #[ink_lang::test]
+    fn set_remove_does_not_underflow_on_a_stale_position() {
+        let mut set = set_of(&[10]);
+        set.positions.insert(&20, &2);
+        assert!(!set.remove(&20));
+        set.len = 0;
+        assert!(!set.remove(&10));
+    }
+
+    #[ink_lang::test]
+    fn index_keeps_the_values_of_each_key_apart() {
+        let mut index = allocate::<EnumerableIndex<u8, u32>>();
+        for value in [10, 20, 30] {
+            assert!(index.insert(&1, &value));
+        }
+        assert!(index.insert(&2, &10));
+        assert!(!index.insert(&1, &10));
+        assert_eq!(index.len(&1), 3);
+        assert_eq!(index.len(&2), 1);
+        assert_eq!(index.len(&3), 0);
+
+        assert!(index.remove(&1, &10));
+        assert_eq!(index.page(&1, 0, 10), vec![30, 20]);
+        assert_eq!(index.page(&2, 0, 10), vec![10]);
+        assert!(index.contains(&2, &10));
+        assert!(!index.remove(&3, &10));
+
+        assert!(index.remove(&1, &20));
+        assert!(index.remove(&1, &30));
+        assert_eq!(index.len(&1), 0);
+        assert_eq!(index.page(&1, 0, 10), Vec::<u32>::new());
+        assert!(index.insert(&1, &20));
+        assert_eq!(index.page(&1, 0, 10), vec![20]);
+        assert_eq!(index.page(&2, 1, 10), Vec::<u32>::new());
+    }
+
+    #[ink_lang::test]
+    fn index_remove_does_not_underflow_on_a_stale_position() {
+        let mut index = allocate::<EnumerableIndex<u8, u32>>();
+        index.positions.insert(&(1, 10), &1);
+        assert!(!index.remove(&1, &10));
+    }
+}
@@ -0,0 +1,56 @@
+use openbrush::contracts::psp22::PSP22Error;
+use openbrush::traits::{AccountId, Balance};
+
+#[openbrush::wrapper]
+pub type AmmPairRef = dyn AmmPair;
+
+#[openbrush::trait_definition]
+pub trait AmmPair {
+    #[ink(message)]
+    /// returns tokens of the pair
+    fn tokens(&self) -> (AccountId, AccountId);
+
+    #[ink(message)]
+    /// returns reserves of the pair in order of `tokens`
+    fn get_reserves(&self) -> (Balance, Balance);
+
+    #[ink(message)]
+    /// returns the amount of the other token that would be received for `amount_in` of `token_in`
+    fn get_amount_out(
+        &self,
+        token_in: AccountId,
+        amount_in: Balance,
+    ) -> Result<Balance, AmmPairError>;
+
+    #[ink(message)]
+    /// transfers `amount_0` and `amount_1` of tokens from the caller to the pair
+    fn add_liquidity(&mut self, amount_0: Balance, amount_1: Balance) -> Result<(), AmmPairError>;
+
+    #[ink(message)]
+    /// transfers `amount_in` of `token_in` from the caller and the other token to `to`. Returns amount of the other token.
+    ///
+    ///  * `min_amount_out` - the swap fails if less would be received
+    fn swap(
+        &mut self,
+        token_in: AccountId,
+        amount_in: Balance,
+        min_amount_out: Balance,
+        to: AccountId,
+    ) -> Result<Balance, AmmPairError>;
+}
+
+#[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub enum AmmPairError {
+    PSP22Error(PSP22Error),
+    /// returned if the token is not in the pair
+    UnknownToken,
+    InsufficientLiquidity,
+    InsufficientOutputAmount,
+}
+
+impl From<PSP22Error> for AmmPairError {
+    fn from(error: PSP22Error) -> Self {
+        AmmPairError::PSP22Error(error)
+    }
+}
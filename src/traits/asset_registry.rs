@@ -0,0 +1,128 @@
+use ink_prelude::vec::Vec;
+use ink_storage::traits::{PackedLayout, SpreadLayout};
+use openbrush::contracts::ownable::OwnableError;
+use openbrush::traits::{AccountId, Balance, Timestamp};
+use primitive_types::U256;
+
+use super::loanable_asset::TokenQuirkPolicy;
+
+#[openbrush::wrapper]
+pub type AssetRegistryRef = dyn AssetRegistry;
+
+#[openbrush::trait_definition]
+pub trait AssetRegistry {
+    /// lists `asset` with `config` or updates the config of an already listed asset. Only the owner can call it.
+    /// Decimals are read from PSP22Metadata when the asset is listed for the first time.
+    #[ink(message)]
+    fn list_asset(
+        &mut self,
+        asset: AccountId,
+        config: AssetConfig,
+    ) -> Result<(), AssetRegistryError>;
+
+    /// removes `asset` from the registry, loans of it fail with `AssetNotSupported`. Only the owner can call it.
+    #[ink(message)]
+    fn delist_asset(&mut self, asset: AccountId) -> Result<(), AssetRegistryError>;
+
+    /// returns None if `asset` is not listed
+    #[ink(message)]
+    fn asset_info(&self, asset: AccountId) -> Option<AssetInfo>;
+
+    /// returns up to `limit` listed assets starting from `offset` with their info and liquidity (amount that can be lent)
+    #[ink(message)]
+    fn supported_assets(&self, offset: u32, limit: u32) -> Vec<(AccountId, AssetInfo, Balance)>;
+}
+
+/// fee taken for lending `amount` of an asset
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode, SpreadLayout, PackedLayout,
+)]
+#[cfg_attr(
+    feature = "std",
+    derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+)]
+pub enum FeeModel {
+    /// `amount * fee_e6 / 10^6`
+    Proportional { fee_e6: u128 },
+    /// `fee` regardless of the amount
+    Flat { fee: Balance },
+}
+
+impl FeeModel {
+    /// returns fee for lending `amount`. The proportional fee is computed in 256 bits and saturates at `Balance::MAX`
+    pub fn fee(&self, amount: Balance) -> Balance {
+        match self {
+            FeeModel::Proportional { fee_e6 } => {
+                let fee = U256::from(amount) * U256::from(*fee_e6) / U256::from(1_000_000_u128);
+                if fee > U256::from(Balance::MAX) {
+                    Balance::MAX
+                } else {
+                    fee.as_u128()
+                }
+            }
+            FeeModel::Flat { fee } => *fee,
+        }
+    }
+}
+
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode, SpreadLayout, PackedLayout,
+)]
+#[cfg_attr(
+    feature = "std",
+    derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+)]
+pub struct AssetConfig {
+    /// loans of a disabled asset fail with `AssetNotSupported`, but it stays listed
+    pub enabled: bool,
+    pub fee_model: FeeModel,
+    /// maximum amount lent in one loan, None if there is no cap
+    pub max_loan: Option<Balance>,
+    pub quirk_policy: TokenQuirkPolicy,
+}
+
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode, SpreadLayout, PackedLayout,
+)]
+#[cfg_attr(
+    feature = "std",
+    derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+)]
+pub struct AssetInfo {
+    pub config: AssetConfig,
+    /// None if the token does not implement PSP22Metadata
+    pub decimals: Option<u8>,
+    /// block timestamp of the first listing
+    pub listed_at: Timestamp,
+}
+
+#[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub enum AssetRegistryError {
+    OwnableError(OwnableError),
+    /// returned if the asset is not listed
+    AssetNotListed,
+}
+
+impl From<OwnableError> for AssetRegistryError {
+    fn from(error: OwnableError) -> Self {
+        AssetRegistryError::OwnableError(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn proportional_fee_does_not_overflow() {
+        assert_eq!(
+            FeeModel::Proportional { fee_e6: 1_000 }.fee(Balance::MAX),
+            Balance::MAX / 1_000
+        );
+        assert_eq!(
+            FeeModel::Proportional { fee_e6: 2_000_000 }.fee(Balance::MAX),
+            Balance::MAX
+        );
+    }
+}
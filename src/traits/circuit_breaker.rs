@@ -0,0 +1,62 @@
+use openbrush::contracts::ownable::OwnableError;
+use openbrush::traits::{AccountId, Balance};
+
+use super::flash_loan::FlashLoanError;
+
+#[openbrush::wrapper]
+pub type CircuitBreakerRef = dyn CircuitBreaker;
+
+/// keeps the balance of each asset from the end of the last loan and counts unexplained drops of it as anomalies.
+/// An asset whose cumulative anomalies reach its threshold is paused and loans of it fail with `AssetPaused`.
+#[openbrush::trait_definition]
+pub trait CircuitBreaker {
+    /// compares the balance of `asset` with the recorded one, a drop is counted as an anomaly.
+    /// Anyone can call it, but not during a flash loan.
+    #[ink(message)]
+    fn check_asset(&mut self, asset: AccountId) -> Result<(), CircuitBreakerError>;
+
+    /// sets cumulative anomalies of `asset` that pause it, None disables the breaker for the asset. Only the owner can call it.
+    #[ink(message)]
+    fn set_anomaly_threshold(
+        &mut self,
+        asset: AccountId,
+        threshold: Option<Balance>,
+    ) -> Result<(), CircuitBreakerError>;
+
+    /// Only the owner can call it.
+    #[ink(message)]
+    fn pause_asset(&mut self, asset: AccountId) -> Result<(), CircuitBreakerError>;
+
+    /// unpauses `asset` and resets its anomalies. Only the owner can call it.
+    #[ink(message)]
+    fn unpause_asset(&mut self, asset: AccountId) -> Result<(), CircuitBreakerError>;
+
+    #[ink(message)]
+    fn anomaly_threshold(&self, asset: AccountId) -> Option<Balance>;
+
+    /// returns sum of anomalies of `asset` since it was unpaused
+    #[ink(message)]
+    fn anomalies(&self, asset: AccountId) -> Balance;
+
+    #[ink(message)]
+    fn is_asset_paused(&self, asset: AccountId) -> bool;
+}
+
+#[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub enum CircuitBreakerError {
+    OwnableError(OwnableError),
+    FlashLoanError(FlashLoanError),
+}
+
+impl From<OwnableError> for CircuitBreakerError {
+    fn from(error: OwnableError) -> Self {
+        CircuitBreakerError::OwnableError(error)
+    }
+}
+
+impl From<FlashLoanError> for CircuitBreakerError {
+    fn from(error: FlashLoanError) -> Self {
+        CircuitBreakerError::FlashLoanError(error)
+    }
+}
@@ -0,0 +1,83 @@
+use ink_prelude::{string::String, vec::Vec};
+use ink_storage::traits::{PackedLayout, SpreadLayout};
+use openbrush::contracts::ownable::OwnableError;
+use openbrush::traits::{AccountId, Balance};
+
+use super::{asset_registry::FeeModel, flash_loan::FlashLoanError};
+
+#[openbrush::wrapper]
+pub type FeeOracleRef = dyn FeeOracle;
+
+/// contract deciding fees of flash loans, e.g. a fee schedule shared by several lenders
+#[openbrush::trait_definition]
+pub trait FeeOracle {
+    #[ink(message)]
+    /// returns list of Balances (amounts) of fee for lending `amounts` of `assets` to `receiver` in a loan called by `caller`.
+    /// Order in list coresponds to order of list of the assets.
+    fn quote_fees(
+        &self,
+        caller: AccountId,
+        receiver: AccountId,
+        assets: Vec<AccountId>,
+        amounts: Vec<Balance>,
+    ) -> Result<Vec<Balance>, FeeOracleError>;
+}
+
+#[openbrush::wrapper]
+pub type FeeOracleSettingsRef = dyn FeeOracleSettings;
+
+/// lender option taking fees from a fee oracle
+#[openbrush::trait_definition]
+pub trait FeeOracleSettings {
+    /// sets the fee oracle used for fees of loans, None stops using it. Only the owner can call it.
+    #[ink(message)]
+    fn set_fee_oracle(
+        &mut self,
+        config: Option<FeeOracleConfig>,
+    ) -> Result<(), FeeOracleSettingsError>;
+
+    #[ink(message)]
+    fn fee_oracle(&self) -> Option<FeeOracleConfig>;
+}
+
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode, SpreadLayout, PackedLayout,
+)]
+#[cfg_attr(
+    feature = "std",
+    derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+)]
+pub struct FeeOracleConfig {
+    pub oracle: AccountId,
+    /// fee of an asset is at most `amount * max_fee_e6 / 10^6`, higher fees returned by the oracle are lowered to it
+    pub max_fee_e6: u128,
+    /// fee model of all assets of the loan if the oracle call fails
+    pub fallback: FeeModel,
+    /// gas limit of the oracle call, 0 forwards all gas left
+    pub gas_limit: u64,
+}
+
+#[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub enum FeeOracleError {
+    Custom(String),
+}
+
+#[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub enum FeeOracleSettingsError {
+    OwnableError(OwnableError),
+    FlashLoanError(FlashLoanError),
+}
+
+impl From<OwnableError> for FeeOracleSettingsError {
+    fn from(error: OwnableError) -> Self {
+        FeeOracleSettingsError::OwnableError(error)
+    }
+}
+
+impl From<FlashLoanError> for FeeOracleSettingsError {
+    fn from(error: FlashLoanError) -> Self {
+        FeeOracleSettingsError::FlashLoanError(error)
+    }
+}
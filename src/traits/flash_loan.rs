@@ -1,20 +1,27 @@
 use ink_prelude::{string::String, vec::Vec};
-use openbrush::traits::{AccountId, Balance};
+use ink_storage::traits::{PackedLayout, SpreadLayout};
+use openbrush::traits::{AccountId, Balance, BlockNumber, Hash};
 
-use openbrush::contracts::psp22::PSP22Error;
+use openbrush::contracts::{psp22::PSP22Error, psp34::PSP34Error, psp35::PSP35Error};
 
-use super::flash_loan_receiver::FlashLoanReceiverError;
+use super::{
+    flash_loan_policy::PolicyModule, flash_loan_receiver::FlashLoanReceiverError,
+    pallet_assets::PalletAssetsError, policy_contract::PolicyContractError,
+};
 
 #[openbrush::wrapper]
 pub type FlashLoanRef = dyn FlashLoan;
 
+/// identifier of a flash loan, assigned in increasing order starting from 1
+pub type LoanId = u64;
+
 #[openbrush::trait_definition]
 pub trait FlashLoan {
     #[ink(message)]
     /// is used to perform a flash loan
     ///
     ///  * `receiver` - AccountId (aka address) of a contract that will receive the flash loan.
-    ///  * `assets` - list of AccountIds (aka adrress) of PSP22 Tokens that should be lent.
+    ///  * `assets` - list of AccountIds (aka adrress) of assets that should be lent. PSP22 Tokens by default, see `FlashLoanInternal::_loanable_asset`.
     ///  * `amount` - list of Balances (amounts) to be lent. Order in list coresponds to order of list of the assets.
     ///  * `data` - list of bytes that can be used for any purpose
     fn flash_loan(
@@ -24,6 +31,49 @@ pub trait FlashLoan {
         amounts: Vec<Balance>,
         data: Vec<u8>,
     ) -> Result<(), FlashLoanError>;
+
+    #[ink(message)]
+    /// returns true while a flash loan of the contract is in progress (between sending the assets and taking them back)
+    fn is_in_flash_loan(&self) -> bool;
+
+    #[ink(message)]
+    /// returns amount of `asset` that was lent and not taken back yet, it is 0 outside of a flash loan
+    fn outstanding(&self, asset: AccountId) -> Balance;
+
+    #[ink(message)]
+    /// returns the receipt of the loan with `loan_id`, None if the loan did not happen or its receipt was already overwritten
+    fn loan_receipt(&self, loan_id: LoanId) -> Option<LoanReceipt>;
+
+    #[ink(message)]
+    /// returns the maximum amount of `asset` that can be lent
+    fn max_flash_loan(&self, asset: AccountId) -> Balance;
+
+    #[ink(message)]
+    /// returns fees that would be taken for lending `amounts` of `assets`. Order in list coresponds to order of list of the assets.
+    /// Fees actually taken during flash_loan may be lower for some receivers or decided by a fee oracle.
+    fn quote_fees(
+        &self,
+        assets: Vec<AccountId>,
+        amounts: Vec<Balance>,
+    ) -> Result<Vec<Balance>, FlashLoanError>;
+}
+
+/// record of a repaid flash loan
+#[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode, SpreadLayout, PackedLayout)]
+#[cfg_attr(
+    feature = "std",
+    derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+)]
+pub struct LoanReceipt {
+    pub loan_id: LoanId,
+    pub block: BlockNumber,
+    pub initiator: AccountId,
+    pub receiver: AccountId,
+    pub assets: Vec<AccountId>,
+    pub amounts: Vec<Balance>,
+    pub fees: Vec<Balance>,
+    /// blake2x256 hash of `data` passed to flash_loan
+    pub data_hash: Hash,
 }
 
 #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
@@ -31,9 +81,48 @@ pub trait FlashLoan {
 pub enum FlashLoanError {
     Custom(String),
     PSP22Error(PSP22Error),
+    PSP34Error(PSP34Error),
+    PSP35Error(PSP35Error),
+    PalletAssetsError(PalletAssetsError),
     FlashLoanReceiverError(FlashLoanReceiverError),
     /// returned if assets.len() != amounts.len()
     Parameters,
+    /// returned if transfer of native currency failed
+    NativeTransferFailed,
+    /// returned if an asset that can not be pulled was not transferred back with the fee
+    NotRepaid,
+    /// returned if balances did not change by the transferred amount (fee-on-transfer, rebasing or lying tokens)
+    TransferAmountMismatch,
+    /// returned if the asset is not listed in the asset registry or is disabled
+    AssetNotSupported,
+    /// returned if the amount exceeds the cap of the asset
+    LoanCapExceeded,
+    /// returned if the call is not allowed while a flash loan is in progress
+    FlashLoanInProgress,
+    /// returned if the contract ends a loan with less than its balance before the loan plus the fee
+    InvariantViolated,
+    /// returned if the asset was paused by the circuit breaker
+    AssetPaused,
+    /// returned if a module of the policy chain rejected the loan
+    PolicyRejected(PolicyModule),
+    /// returned if the policy contract did not allow the loan
+    PolicyContractDenied,
+    /// returned if the policy contract returned an error
+    PolicyContractError(PolicyContractError),
+    /// returned if the policy contract trapped or ran out of its gas limit
+    PolicyContractTrapped,
+    /// returned if the policy contract could not be called, e.g. there is no contract under its address
+    PolicyContractCallFailed,
+    /// returned if the voucher is not signed by the voucher signer or is not meant for the lender and the caller
+    InvalidVoucher,
+    /// returned if the voucher is used after its expiry block
+    VoucherExpired,
+    /// returned if the nonce of the voucher was already used
+    VoucherReplayed,
+    /// returned if the loan exceeds the limits of the voucher
+    VoucherLimitExceeded,
+    /// returned if amounts or fees add up to more than `Balance::MAX`
+    Overflow,
 }
 
 impl From<PSP22Error> for FlashLoanError {
@@ -42,6 +131,24 @@ impl From<PSP22Error> for FlashLoanError {
     }
 }
 
+impl From<PSP34Error> for FlashLoanError {
+    fn from(error: PSP34Error) -> Self {
+        FlashLoanError::PSP34Error(error)
+    }
+}
+
+impl From<PSP35Error> for FlashLoanError {
+    fn from(error: PSP35Error) -> Self {
+        FlashLoanError::PSP35Error(error)
+    }
+}
+
+impl From<PalletAssetsError> for FlashLoanError {
+    fn from(error: PalletAssetsError) -> Self {
+        FlashLoanError::PalletAssetsError(error)
+    }
+}
+
 impl From<FlashLoanReceiverError> for FlashLoanError {
     fn from(error: FlashLoanReceiverError) -> Self {
         FlashLoanError::FlashLoanReceiverError(error)
@@ -0,0 +1,187 @@
+use ink_prelude::vec::Vec;
+use ink_storage::traits::{PackedLayout, SpreadLayout};
+use openbrush::contracts::ownable::OwnableError;
+use openbrush::traits::{AccountId, Balance, BlockNumber, Hash};
+
+use super::flash_loan::{FlashLoanError, LoanId};
+
+#[openbrush::wrapper]
+pub type PolicyChainRef = dyn PolicyChain;
+
+/// ordered list of policy modules checking every flash loan of the lender
+#[openbrush::trait_definition]
+pub trait PolicyChain {
+    /// returns policy modules in the order they are run
+    #[ink(message)]
+    fn policies(&self) -> Vec<PolicyModule>;
+
+    /// replaces the chain, modules are run in the order of `policies`. Only the owner can call it.
+    #[ink(message)]
+    fn set_policies(&mut self, policies: Vec<PolicyModule>) -> Result<(), PolicyChainError>;
+
+    /// adds `account` to or removes it from the list checked by `PolicyModule::Allowlist`. Only the owner can call it.
+    #[ink(message)]
+    fn set_allowlisted(
+        &mut self,
+        account: AccountId,
+        allowlisted: bool,
+    ) -> Result<(), PolicyChainError>;
+
+    #[ink(message)]
+    fn is_allowlisted(&self, account: AccountId) -> bool;
+
+    /// returns up to `limit` allowlisted accounts starting from index `offset`
+    #[ink(message)]
+    fn list_allowlisted(&self, offset: u32, limit: u32) -> Vec<AccountId>;
+
+    /// sets the largest amount of `asset` that can be lent in one loan, None removes the cap.
+    /// Only the owner can call it.
+    #[ink(message)]
+    fn set_cap(&mut self, asset: AccountId, cap: Option<Balance>) -> Result<(), PolicyChainError>;
+
+    #[ink(message)]
+    fn cap(&self, asset: AccountId) -> Option<Balance>;
+
+    /// returns up to `limit` assets with their caps starting from index `offset`
+    #[ink(message)]
+    fn list_caps(&self, offset: u32, limit: u32) -> Vec<(AccountId, Balance)>;
+
+    /// stops or resumes all loans checked by `PolicyModule::Pause`. Only the owner can call it.
+    #[ink(message)]
+    fn set_loans_paused(&mut self, paused: bool) -> Result<(), PolicyChainError>;
+
+    #[ink(message)]
+    fn loans_paused(&self) -> bool;
+
+    /// sets how much of `asset` one caller can borrow per window, None removes the limit.
+    /// Only the owner can call it.
+    #[ink(message)]
+    fn set_rate_limit(
+        &mut self,
+        asset: AccountId,
+        rate_limit: Option<RateLimit>,
+    ) -> Result<(), PolicyChainError>;
+
+    #[ink(message)]
+    fn rate_limit(&self, asset: AccountId) -> Option<RateLimit>;
+
+    /// returns amount of `asset` borrowed by `caller` in the current window
+    #[ink(message)]
+    fn rate_limit_used(&self, caller: AccountId, asset: AccountId) -> Balance;
+
+    /// gives `account` a credential valid until block `expiry` (inclusive), None revokes it.
+    /// Only the owner can call it.
+    #[ink(message)]
+    fn set_credential(
+        &mut self,
+        account: AccountId,
+        expiry: Option<BlockNumber>,
+    ) -> Result<(), PolicyChainError>;
+
+    /// returns the last block of the credential of `account`
+    #[ink(message)]
+    fn credential(&self, account: AccountId) -> Option<BlockNumber>;
+
+    /// returns up to `limit` accounts with the last blocks of their credentials starting from index `offset`
+    #[ink(message)]
+    fn list_credentials(&self, offset: u32, limit: u32) -> Vec<(AccountId, BlockNumber)>;
+
+    /// sets the contract called by `PolicyModule::External` with at most `gas_limit` gas, 0 forwards all gas left.
+    /// None removes it, then `PolicyModule::External` rejects all loans. Only the owner can call it.
+    #[ink(message)]
+    fn set_policy_contract(
+        &mut self,
+        policy_contract: Option<AccountId>,
+        gas_limit: u64,
+    ) -> Result<(), PolicyChainError>;
+
+    /// returns the policy contract and its gas limit
+    #[ink(message)]
+    fn policy_contract(&self) -> Option<(AccountId, u64)>;
+}
+
+/// policy modules that can be put in the chain, each is implemented by a `FlashLoanPolicy`
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode, SpreadLayout, PackedLayout,
+)]
+#[cfg_attr(
+    feature = "std",
+    derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+)]
+pub enum PolicyModule {
+    /// only allowlisted callers can take loans
+    Allowlist,
+    /// loans above the cap of an asset are rejected
+    Caps,
+    /// no loans can be taken while loans are paused, the lender may also pause single assets
+    Pause,
+    /// a caller can borrow at most the rate limit of an asset per window
+    RateLimit,
+    /// only callers with a credential that has not expired can take loans
+    Credential,
+    /// the policy contract decides, see `FlashLoanPolicyContract`
+    External,
+}
+
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode, SpreadLayout, PackedLayout,
+)]
+#[cfg_attr(
+    feature = "std",
+    derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+)]
+pub struct RateLimit {
+    /// amount that can be borrowed per window
+    pub amount: Balance,
+    /// length of the window in blocks, windows start at multiples of it
+    pub window: BlockNumber,
+}
+
+/// flash loan as seen by policies
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoanRequest {
+    pub loan_id: LoanId,
+    pub caller: AccountId,
+    pub receiver: AccountId,
+    pub assets: Vec<AccountId>,
+    pub amounts: Vec<Balance>,
+    /// blake2x256 hash of the data passed to flash_loan
+    pub data_hash: Hash,
+}
+
+/// check of a flash loan run by the policy chain of lender `T`
+pub trait FlashLoanPolicy<T> {
+    /// is run before the loan is sent, an error rejects the loan
+    fn pre_check(&self, lender: &mut T, loan: &LoanRequest) -> Result<(), FlashLoanError>;
+
+    /// is run after the loan is repaid, an error reverts the loan
+    fn post_check(
+        &self,
+        _lender: &mut T,
+        _loan: &LoanRequest,
+        _fees: &Vec<Balance>,
+    ) -> Result<(), FlashLoanError> {
+        Ok(())
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub enum PolicyChainError {
+    OwnableError(OwnableError),
+    FlashLoanError(FlashLoanError),
+    /// returned if the window of a rate limit is 0
+    InvalidRateLimit,
+}
+
+impl From<OwnableError> for PolicyChainError {
+    fn from(error: OwnableError) -> Self {
+        PolicyChainError::OwnableError(error)
+    }
+}
+
+impl From<FlashLoanError> for PolicyChainError {
+    fn from(error: FlashLoanError) -> Self {
+        PolicyChainError::FlashLoanError(error)
+    }
+}
@@ -1,5 +1,11 @@
 use ink_prelude::{string::String, vec::Vec};
-use openbrush::traits::AccountId;
+use ink_storage::traits::{PackedLayout, SpreadLayout};
+use openbrush::traits::{AccountId, Balance};
+
+use super::flash_loan::LoanId;
+
+use openbrush::contracts::ownable::OwnableError;
+use openbrush::contracts::psp22::PSP22Error;
 
 #[openbrush::wrapper]
 pub type FlashLoanReceiverRef = dyn FlashLoanReceiver;
@@ -9,12 +15,16 @@ pub trait FlashLoanReceiver {
     #[ink(message)]
     /// is called during flash_loan message to perform operation during flash_loan
     ///
+    ///  * `loan_id` - id assigned to the loan by the lender, it is included in the lender events and receipt.
+    ///  * `initiator` - AccountId (aka adrress) of the account that called flash_loan.
     ///  * `assets` - list of AccountIds (aka adrress) of PSP22 Tokens that were lend.
     ///  * `amounts` - list of Balances (amounts) that were lend and should be available to be transfer back to flash_loan contract. Order in list coresponds to order of list of the assets.
     ///  * `fees` - list of Balances (amounts) that must be additionaly paid back to flash loan contract. Order in list coresponds to order of list of the assets.
     ///  * `data` - list of bytes that can be used for any purpose
     fn execute_operation(
         &mut self,
+        loan_id: LoanId,
+        initiator: AccountId,
         assets: Vec<AccountId>,
         amounts: Vec<u128>,
         fees: Vec<u128>,
@@ -22,9 +32,78 @@ pub trait FlashLoanReceiver {
     ) -> Result<(), FlashLoanReceiverError>;
 }
 
+#[openbrush::wrapper]
+pub type FlashLoanReceiverAdminRef = dyn FlashLoanReceiverAdmin;
+
+/// management of receivers owned by an account
+#[openbrush::trait_definition]
+pub trait FlashLoanReceiverAdmin {
+    /// sets `lender` as trusted with repayment `mode`, None removes it
+    #[ink(message)]
+    fn set_trusted_lender(
+        &mut self,
+        lender: AccountId,
+        mode: Option<RepaymentMode>,
+    ) -> Result<(), FlashLoanReceiverAdminError>;
+
+    /// transfers `amount` of `asset` held by the contract to `to`
+    #[ink(message)]
+    fn withdraw(
+        &mut self,
+        asset: AccountId,
+        to: AccountId,
+        amount: Balance,
+    ) -> Result<(), FlashLoanReceiverAdminError>;
+}
+
+/// how a trusted lender takes the loan back
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode, SpreadLayout, PackedLayout,
+)]
+#[cfg_attr(
+    feature = "std",
+    derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+)]
+pub enum RepaymentMode {
+    /// the receiver approves the lender to transfer `amount + fee` (lenders using the default implementation)
+    Approve,
+    /// the receiver transfers `amount + fee` to the lender
+    Transfer,
+}
+
 #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
 #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
 pub enum FlashLoanReceiverError {
     Custom(String),
     ExecuteOperationFailed,
+    PSP22Error(PSP22Error),
+    /// returned if execute_operation was called by a lender that is not trusted
+    UntrustedLender,
+    /// returned if the flash loan was initiated by an account that is not authorized
+    UnauthorizedInitiator,
+}
+
+impl From<PSP22Error> for FlashLoanReceiverError {
+    fn from(error: PSP22Error) -> Self {
+        FlashLoanReceiverError::PSP22Error(error)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub enum FlashLoanReceiverAdminError {
+    OwnableError(OwnableError),
+    PSP22Error(PSP22Error),
+}
+
+impl From<OwnableError> for FlashLoanReceiverAdminError {
+    fn from(error: OwnableError) -> Self {
+        FlashLoanReceiverAdminError::OwnableError(error)
+    }
+}
+
+impl From<PSP22Error> for FlashLoanReceiverAdminError {
+    fn from(error: PSP22Error) -> Self {
+        FlashLoanReceiverAdminError::PSP22Error(error)
+    }
 }
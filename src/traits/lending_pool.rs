@@ -0,0 +1,86 @@
+use openbrush::contracts::psp22::PSP22Error;
+use openbrush::traits::{AccountId, Balance};
+
+#[openbrush::wrapper]
+pub type LendingPoolRef = dyn LendingPool;
+
+/// minimal collateralized lending pool. Positions are kept per account and asset.
+#[openbrush::trait_definition]
+pub trait LendingPool {
+    #[ink(message)]
+    /// transfers `amount` of `asset` from the caller and adds it to the collateral of `on_behalf_of`
+    fn deposit(
+        &mut self,
+        asset: AccountId,
+        amount: Balance,
+        on_behalf_of: AccountId,
+    ) -> Result<(), LendingPoolError>;
+
+    #[ink(message)]
+    /// removes `amount` of `asset` from the collateral of the caller and transfers it to `to`. The position must stay healthy.
+    fn withdraw(
+        &mut self,
+        asset: AccountId,
+        amount: Balance,
+        to: AccountId,
+    ) -> Result<(), LendingPoolError>;
+
+    #[ink(message)]
+    /// transfers `amount` of `asset` to the caller and adds it to the debt of the caller. The position must stay healthy.
+    fn borrow(&mut self, asset: AccountId, amount: Balance) -> Result<(), LendingPoolError>;
+
+    #[ink(message)]
+    /// transfers up to `amount` of `asset` from the caller to repay the debt of `on_behalf_of`. Returns the repaid amount.
+    fn repay(
+        &mut self,
+        asset: AccountId,
+        amount: Balance,
+        on_behalf_of: AccountId,
+    ) -> Result<Balance, LendingPoolError>;
+
+    #[ink(message)]
+    /// repays up to `amount` of `debt_asset` debt of the unhealthy `user` and transfers the seized `collateral_asset` with the penalty to the caller.
+    /// Returns the seized amount.
+    ///
+    ///  * `min_collateral` - the liquidation fails if less collateral would be seized
+    fn liquidate(
+        &mut self,
+        user: AccountId,
+        debt_asset: AccountId,
+        collateral_asset: AccountId,
+        amount: Balance,
+        min_collateral: Balance,
+    ) -> Result<Balance, LendingPoolError>;
+
+    #[ink(message)]
+    fn collateral_of(&self, user: AccountId, asset: AccountId) -> Balance;
+
+    #[ink(message)]
+    fn debt_of(&self, user: AccountId, asset: AccountId) -> Balance;
+
+    #[ink(message)]
+    /// returns true if the weighted collateral of `user` covers the weighted debt
+    fn is_healthy(&self, user: AccountId) -> bool;
+}
+
+#[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub enum LendingPoolError {
+    PSP22Error(PSP22Error),
+    /// returned if the asset is not registered in the pool
+    UnknownAsset,
+    /// returned if the position would become unhealthy
+    InsufficientCollateral,
+    /// returned if more than deposited is withdrawn
+    InsufficientDeposit,
+    NoDebt,
+    /// returned on liquidation of a healthy position
+    PositionHealthy,
+    MinimumCollateralNotReached,
+}
+
+impl From<PSP22Error> for LendingPoolError {
+    fn from(error: PSP22Error) -> Self {
+        LendingPoolError::PSP22Error(error)
+    }
+}
@@ -0,0 +1,86 @@
+use ink_prelude::vec::Vec;
+use ink_storage::traits::{PackedLayout, SpreadLayout};
+use openbrush::contracts::ownable::OwnableError;
+use openbrush::traits::{AccountId, Balance, BlockNumber};
+
+#[openbrush::wrapper]
+pub type LoanStatsRef = dyn LoanStats;
+
+/// statistics of loans taken from the contract
+#[openbrush::trait_definition]
+pub trait LoanStats {
+    /// returns cumulative volume, fees and number of loans of `asset`
+    #[ink(message)]
+    fn asset_stats(&self, asset: AccountId) -> AssetStats;
+
+    /// returns number of flash loans called by `caller`
+    #[ink(message)]
+    fn caller_loans(&self, caller: AccountId) -> u64;
+
+    /// returns number of flash loans received by `receiver`
+    #[ink(message)]
+    fn receiver_loans(&self, receiver: AccountId) -> u64;
+
+    /// returns snapshots of the last epochs with loans of `asset`, the newest first.
+    /// At most `MAX_EPOCH_SNAPSHOTS` are kept per asset.
+    #[ink(message)]
+    fn epoch_snapshots(&self, asset: AccountId) -> Vec<EpochSnapshot>;
+
+    #[ink(message)]
+    fn epoch_length(&self) -> BlockNumber;
+
+    /// sets epoch length in blocks, it applies to the next snapshots. Only the owner can call it.
+    #[ink(message)]
+    fn set_epoch_length(&mut self, blocks: BlockNumber) -> Result<(), LoanStatsError>;
+}
+
+#[derive(
+    Debug,
+    Default,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    scale::Encode,
+    scale::Decode,
+    SpreadLayout,
+    PackedLayout,
+)]
+#[cfg_attr(
+    feature = "std",
+    derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+)]
+pub struct AssetStats {
+    /// sum of lent amounts
+    pub volume: Balance,
+    /// sum of fees taken
+    pub fees: Balance,
+    pub loans: u64,
+}
+
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode, SpreadLayout, PackedLayout,
+)]
+#[cfg_attr(
+    feature = "std",
+    derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+)]
+pub struct EpochSnapshot {
+    /// first block of the epoch
+    pub start_block: BlockNumber,
+    pub stats: AssetStats,
+}
+
+#[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub enum LoanStatsError {
+    OwnableError(OwnableError),
+    /// returned if epoch length is 0
+    InvalidEpochLength,
+}
+
+impl From<OwnableError> for LoanStatsError {
+    fn from(error: OwnableError) -> Self {
+        LoanStatsError::OwnableError(error)
+    }
+}
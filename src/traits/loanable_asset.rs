@@ -0,0 +1,89 @@
+use ink_prelude::string::String;
+use ink_storage::traits::{PackedLayout, SpreadLayout};
+use openbrush::contracts::traits::psp34::Id;
+use openbrush::traits::{AccountId, Balance};
+
+use super::{flash_loan::FlashLoanError, pallet_assets::AssetId};
+
+/// kinds of assets that can be lent by a flash loan contract
+#[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub enum AssetKind {
+    PSP22,
+    Native,
+    PSP34,
+    /// multi token standard (PSP35 in openbrush 2.2.0)
+    PSP37,
+    /// asset of pallet-assets accessed through a chain extension
+    PalletAssets,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub struct AssetMetadata {
+    pub kind: AssetKind,
+    pub name: Option<String>,
+    pub symbol: Option<String>,
+    /// None if the asset does not expose decimals
+    pub decimals: Option<u8>,
+}
+
+/// asset that an account stands for, e.g. kept in storage by lenders that lend more than PSP22 tokens
+/// and returned from `FlashLoanInternal::_loanable_asset` with `Asset::from_spec`
+#[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode, SpreadLayout, PackedLayout)]
+#[cfg_attr(
+    feature = "std",
+    derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+)]
+pub enum AssetSpec {
+    /// the account is a PSP22 token
+    PSP22,
+    /// native currency of the chain, the account is only a key
+    Native,
+    /// token `Id` of the PSP34 collection at the account
+    PSP34(Id),
+    /// token `Id` of the PSP37 contract at the account
+    PSP37(Id),
+    /// asset of pallet-assets, the account is only a key
+    PalletAssets(AssetId),
+}
+
+/// how the default flash loan pipeline treats a PSP22 token whose balances do not change by the transferred amount
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode, SpreadLayout, PackedLayout,
+)]
+#[cfg_attr(
+    feature = "std",
+    derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+)]
+pub enum TokenQuirkPolicy {
+    /// the loan fails with `TransferAmountMismatch`
+    Reject,
+    /// the receiver is told the amounts that really arrived and has to repay enough for the contract
+    /// to end up with at least its balance before the loan plus the fee
+    Account,
+}
+
+/// abstraction over an asset that can be lent.
+/// The default flash loan pipeline moves assets only through this trait.
+pub trait LoanableAsset {
+    /// transfers `amount` of the asset from the calling contract to `to`.
+    fn send(&self, to: AccountId, amount: Balance) -> Result<(), FlashLoanError>;
+
+    /// takes `amount` of the asset from `from` back to the calling contract.
+    fn pull_back(&self, from: AccountId, amount: Balance) -> Result<(), FlashLoanError>;
+
+    /// returns false if the asset can not be pulled and `from` has to transfer it back on its own.
+    /// In such case `pull_back` does nothing and repayment is checked by comparing balances.
+    fn can_pull_back(&self) -> bool {
+        true
+    }
+
+    /// returns amount of the asset held by `owner`.
+    /// `NativeAsset` knows only the balance of the calling contract and returns 0 for other accounts,
+    /// so it can not be used to measure balances of the receiver.
+    fn balance_of(&self, owner: AccountId) -> Balance;
+
+    /// returns metadata of the asset.
+    fn metadata(&self) -> AssetMetadata;
+}
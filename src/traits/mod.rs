@@ -1,4 +1,30 @@
+/// constant product AMM pair
+pub mod amm_pair;
+/// registry of assets that can be lent
+pub mod asset_registry;
+/// circuit breaker pausing assets with balance anomalies
+pub mod circuit_breaker;
+/// external contract deciding fees of flash loans
+pub mod fee_oracle;
 /// flash loan trait
 pub mod flash_loan;
+/// policy modules checking flash loans
+pub mod flash_loan_policy;
 /// flash loan receiver
 pub mod flash_loan_receiver;
+/// collateralized lending pool
+pub mod lending_pool;
+/// statistics of loans
+pub mod loan_stats;
+/// assets that can be lent
+pub mod loanable_asset;
+/// pallet-assets chain extension
+pub mod pallet_assets;
+/// external contract deciding if a flash loan is allowed
+pub mod policy_contract;
+/// tracking of lender principal and skimming of untracked tokens
+pub mod reconciliation;
+/// flash loans allowed by signed vouchers
+pub mod voucher;
+/// PSP22 backed by native currency
+pub mod wrapped_native;
@@ -0,0 +1,72 @@
+use ink_env::chain_extension::FromStatusCode;
+
+/// id of an asset in `pallet-assets`
+pub type AssetId = u32;
+
+/// function ids of the pallet-assets chain extension. They must match the ones registered in the runtime.
+///
+///  * `BALANCE` - (asset_id, owner) -> Balance
+///  * `TRANSFER` - (asset_id, target, amount) -> (), transfers from the calling contract
+///  * `TRANSFER_APPROVED` - (asset_id, owner, destination, amount) -> (), transfers from `owner` with approval given to the calling contract
+///  * `APPROVE_TRANSFER` - (asset_id, delegate, amount) -> (), approves `delegate` to transfer from the calling contract
+///  * `METADATA` - asset_id -> (name, symbol, decimals)
+pub mod func_id {
+    pub const BALANCE: u32 = 0x4101;
+    pub const TRANSFER: u32 = 0x4102;
+    pub const TRANSFER_APPROVED: u32 = 0x4103;
+    pub const APPROVE_TRANSFER: u32 = 0x4104;
+    pub const METADATA: u32 = 0x4105;
+}
+
+/// errors returned by the pallet-assets chain extension as status codes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub enum PalletAssetsError {
+    /// 1 - account balance must be greater than or equal to the transfer amount
+    BalanceLow,
+    /// 2 - the account to alter does not exist
+    NoAccount,
+    /// 3 - the signing account has no permission to do the operation
+    NoPermission,
+    /// 4 - the given asset id is unknown
+    Unknown,
+    /// 5 - the origin account is frozen
+    Frozen,
+    /// 6 - no approval exists that would allow the transfer
+    Unapproved,
+    /// 7 - the source account would not survive the transfer
+    WouldDie,
+    /// any other status code
+    UnknownStatusCode(u32),
+}
+
+impl PalletAssetsError {
+    pub fn status_code(&self) -> u32 {
+        match self {
+            PalletAssetsError::BalanceLow => 1,
+            PalletAssetsError::NoAccount => 2,
+            PalletAssetsError::NoPermission => 3,
+            PalletAssetsError::Unknown => 4,
+            PalletAssetsError::Frozen => 5,
+            PalletAssetsError::Unapproved => 6,
+            PalletAssetsError::WouldDie => 7,
+            PalletAssetsError::UnknownStatusCode(code) => *code,
+        }
+    }
+}
+
+impl FromStatusCode for PalletAssetsError {
+    fn from_status_code(status_code: u32) -> Result<(), Self> {
+        match status_code {
+            0 => Ok(()),
+            1 => Err(PalletAssetsError::BalanceLow),
+            2 => Err(PalletAssetsError::NoAccount),
+            3 => Err(PalletAssetsError::NoPermission),
+            4 => Err(PalletAssetsError::Unknown),
+            5 => Err(PalletAssetsError::Frozen),
+            6 => Err(PalletAssetsError::Unapproved),
+            7 => Err(PalletAssetsError::WouldDie),
+            code => Err(PalletAssetsError::UnknownStatusCode(code)),
+        }
+    }
+}
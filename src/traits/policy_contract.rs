@@ -0,0 +1,31 @@
+use ink_prelude::{string::String, vec::Vec};
+use openbrush::traits::{AccountId, Balance, Hash};
+
+#[openbrush::wrapper]
+pub type FlashLoanPolicyRef = dyn FlashLoanPolicyContract;
+
+/// contract a lender delegates its allow/deny decision to, see `PolicyModule::External`
+#[openbrush::trait_definition]
+pub trait FlashLoanPolicyContract {
+    #[ink(message)]
+    /// is called by the lender before a flash loan is sent, returns true if the loan is allowed
+    ///
+    ///  * `caller` - AccountId (aka adrress) of the account that called flash_loan.
+    ///  * `receiver` - AccountId (aka adrress) of the contract that would receive the flash loan.
+    ///  * `assets`, `amounts` - assets and amounts of the loan.
+    ///  * `data_hash` - blake2x256 hash of the `data` passed to flash_loan.
+    fn authorize(
+        &self,
+        caller: AccountId,
+        receiver: AccountId,
+        assets: Vec<AccountId>,
+        amounts: Vec<Balance>,
+        data_hash: Hash,
+    ) -> Result<bool, PolicyContractError>;
+}
+
+#[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub enum PolicyContractError {
+    Custom(String),
+}
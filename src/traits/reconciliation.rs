@@ -0,0 +1,89 @@
+use ink_prelude::vec::Vec;
+use openbrush::contracts::{ownable::OwnableError, psp22::PSP22Error};
+use openbrush::traits::{AccountId, Balance};
+
+use super::flash_loan::FlashLoanError;
+
+#[openbrush::wrapper]
+pub type ReconciliationRef = dyn Reconciliation;
+
+/// tracks principal deposited to the lender and fees it earned, so tokens that reached it otherwise can be found and moved out
+#[openbrush::trait_definition]
+pub trait Reconciliation {
+    /// transfers `amount` of `asset` from the caller to the contract and tracks the received amount as principal.
+    /// Only the owner can call it.
+    #[ink(message)]
+    fn deposit(&mut self, asset: AccountId, amount: Balance) -> Result<(), ReconciliationError>;
+
+    /// returns the PSP22 balance of the contract compared with tracked principal and fees for each of `assets`
+    #[ink(message)]
+    fn reconcile(&self, assets: Vec<AccountId>) -> Vec<AssetReconciliation>;
+
+    /// transfers balance of `asset` above tracked principal and fees to the treasury. Only the owner can call it.
+    /// Fails for assets that were never deposited.
+    #[ink(message)]
+    fn skim(&mut self, asset: AccountId) -> Result<(), ReconciliationError>;
+
+    /// transfers `amount` of `token` that is not lent by the contract to `to`. Only the owner can call it.
+    #[ink(message)]
+    fn rescue(
+        &mut self,
+        token: AccountId,
+        to: AccountId,
+        amount: Balance,
+    ) -> Result<(), ReconciliationError>;
+
+    /// sets the account that receives skimmed tokens. Only the owner can call it.
+    #[ink(message)]
+    fn set_treasury(&mut self, treasury: AccountId) -> Result<(), ReconciliationError>;
+
+    #[ink(message)]
+    fn treasury(&self) -> Option<AccountId>;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub struct AssetReconciliation {
+    pub asset: AccountId,
+    /// PSP22 balance of the contract
+    pub balance: Balance,
+    pub principal: Balance,
+    /// earned fees that were not withdrawn
+    pub fees: Balance,
+    /// `balance - principal - fees`, 0 if the balance is lower
+    pub excess: Balance,
+    /// `principal + fees - balance`, 0 if the balance is higher
+    pub shortfall: Balance,
+}
+
+#[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub enum ReconciliationError {
+    OwnableError(OwnableError),
+    PSP22Error(PSP22Error),
+    FlashLoanError(FlashLoanError),
+    /// returned by `skim` if the treasury was not set
+    TreasuryNotSet,
+    /// returned by `rescue` for tokens lent by the contract
+    NotRescuable,
+    /// returned by `skim` if no principal of the asset was deposited, as the whole balance would be excess
+    PrincipalNotRecorded,
+}
+
+impl From<OwnableError> for ReconciliationError {
+    fn from(error: OwnableError) -> Self {
+        ReconciliationError::OwnableError(error)
+    }
+}
+
+impl From<PSP22Error> for ReconciliationError {
+    fn from(error: PSP22Error) -> Self {
+        ReconciliationError::PSP22Error(error)
+    }
+}
+
+impl From<FlashLoanError> for ReconciliationError {
+    fn from(error: FlashLoanError) -> Self {
+        ReconciliationError::FlashLoanError(error)
+    }
+}
@@ -0,0 +1,84 @@
+use ink_prelude::vec::Vec;
+use ink_storage::traits::{PackedLayout, SpreadLayout};
+use openbrush::contracts::ownable::OwnableError;
+use openbrush::traits::{AccountId, Balance, BlockNumber};
+
+use super::flash_loan::FlashLoanError;
+
+#[openbrush::wrapper]
+pub type VouchersRef = dyn Vouchers;
+
+/// flash loans of callers holding a voucher signed off-chain by the voucher signer of the lender
+#[openbrush::trait_definition]
+pub trait Vouchers {
+    #[ink(message)]
+    /// is used to perform a flash loan allowed by `voucher`, arguments are the same as of flash_loan.
+    /// The voucher can be used once and only by its caller.
+    fn flash_loan_with_voucher(
+        &mut self,
+        receiver: AccountId,
+        assets: Vec<AccountId>,
+        amounts: Vec<Balance>,
+        data: Vec<u8>,
+        voucher: SignedVoucher,
+    ) -> Result<(), FlashLoanError>;
+
+    /// sets the account whose ECDSA key signs vouchers, None stops accepting vouchers. Only the owner can call it.
+    /// It is the blake2x256 hash of the compressed public key, the account id of the key in Substrate.
+    #[ink(message)]
+    fn set_voucher_signer(&mut self, signer: Option<AccountId>) -> Result<(), VoucherError>;
+
+    #[ink(message)]
+    fn voucher_signer(&self) -> Option<AccountId>;
+
+    /// returns true if a voucher of `caller` with `nonce` was used
+    #[ink(message)]
+    fn is_nonce_used(&self, caller: AccountId, nonce: u64) -> bool;
+}
+
+/// permission to take flash loans, it is signed as the blake2x256 hash of its SCALE encoding
+#[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode, SpreadLayout, PackedLayout)]
+#[cfg_attr(
+    feature = "std",
+    derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+)]
+pub struct Voucher {
+    /// lender that accepts the voucher
+    pub lender: AccountId,
+    /// account that can use the voucher
+    pub caller: AccountId,
+    /// assets that can be lent with the largest amount of each
+    pub limits: Vec<(AccountId, Balance)>,
+    /// last block in which the voucher can be used
+    pub expiry: BlockNumber,
+    pub nonce: u64,
+    /// if set, fees are `amount * fee_e6 / 10^6` instead of the fees of the lender
+    pub fee_e6: Option<u128>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub struct SignedVoucher {
+    pub voucher: Voucher,
+    /// ECDSA signature (r, s, recovery id)
+    pub signature: [u8; 65],
+}
+
+#[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub enum VoucherError {
+    OwnableError(OwnableError),
+    FlashLoanError(FlashLoanError),
+}
+
+impl From<OwnableError> for VoucherError {
+    fn from(error: OwnableError) -> Self {
+        VoucherError::OwnableError(error)
+    }
+}
+
+impl From<FlashLoanError> for VoucherError {
+    fn from(error: FlashLoanError) -> Self {
+        VoucherError::FlashLoanError(error)
+    }
+}
@@ -0,0 +1,16 @@
+use openbrush::contracts::psp22::PSP22Error;
+use openbrush::traits::Balance;
+
+#[openbrush::wrapper]
+pub type WrappedNativeRef = dyn WrappedNative;
+
+#[openbrush::trait_definition]
+pub trait WrappedNative {
+    #[ink(message, payable)]
+    /// mints to the caller PSP22 tokens in the amount of transferred native value
+    fn deposit(&mut self) -> Result<(), PSP22Error>;
+
+    #[ink(message)]
+    /// burns `amount` of the caller's PSP22 tokens and transfers the same amount of native value to the caller
+    fn withdraw(&mut self, amount: Balance) -> Result<(), PSP22Error>;
+}
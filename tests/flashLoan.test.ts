@@ -1,24 +1,87 @@
 import { KeyringPair } from "@polkadot/keyring/types";
+import { u8aConcat, u8aToHex } from "@polkadot/util";
+import { blake2AsHex } from "@polkadot/util-crypto";
+import { TypeRegistry } from "@polkadot/types";
 import BN from "bn.js";
 import FlashLoanReceiverMock from "typechain/contracts/flash_loan_receiver_mock";
+import FlashLoanReceiverSimple from "typechain/contracts/flash_loan_receiver_simple";
+import FlashLoanExecutor from "typechain/contracts/flash_loan_executor";
+import AmmPairMock from "typechain/contracts/amm_pair_mock";
+import ArbitrageReceiver from "typechain/contracts/arbitrage_receiver";
+import LendingPoolMock from "typechain/contracts/lending_pool_mock";
+import LiquidationReceiver from "typechain/contracts/liquidation_receiver";
+import RefinanceReceiver from "typechain/contracts/refinance_receiver";
+import MaliciousReceiver from "typechain/contracts/malicious_receiver";
+import AdversarialPSP22 from "typechain/contracts/adversarial_psp22";
 import FlashLoanContract from "typechain/contracts/flash_loan_contract";
 import FlashLoanSimple from "typechain/contracts/flash_loan_simple";
+import FlashLoanRouter from "typechain/contracts/flash_loan_router";
+import LenderFactory from "typechain/contracts/lender_factory";
 import PSP22Mintable from "typechain/contracts/psp22_mintable";
+import WrappedNative from "typechain/contracts/wrapped_native";
 import {
   deployFlashLoanReceiverMock,
   deployPSP22Mintable,
   deployFlashLoanContract,
   deployFlashLoanSimple,
+  deployWrappedNative,
+  deployFlashLoanRouter,
+  deployLenderFactory,
+  getContractObject,
+  deployFlashLoanReceiverSimple,
+  deployFlashLoanExecutor,
+  deployAmmPairMock,
+  deployArbitrageReceiver,
+  deployLendingPoolMock,
+  deployLiquidationReceiver,
+  deployRefinanceReceiver,
+  deployMaliciousReceiver,
+  deployAdversarialPSP22,
+  deployPolicyMock,
+  deployFeeOracleMock,
+  deployPSP34Mintable,
+  deployPSP37Mintable,
+  deployAssetLenderMock,
+  deployAssetReceiverMock,
 } from "./setup/deploymentHelpers";
 import { expect } from "./setup/chai";
-import { getSigners } from "./setup/helpers";
+import { getEcdsaSigners, getSigners } from "./setup/helpers";
 import { ApiProviderWrapper } from "./setup/ApiProviderWrapper";
 import { FlashLoanContractErrorBuilder } from "typechain/types-returns/flash_loan_contract";
 import {
+  AssetRegistryErrorBuilder,
+  CircuitBreakerErrorBuilder,
+  FeeModelBuilder,
+  FeeOracleSettingsErrorBuilder,
   FlashLoanErrorBuilder,
+  LoanStatsErrorBuilder,
+  OwnableErrorBuilder,
+  PolicyChainErrorBuilder,
+  PolicyContractErrorBuilder,
+  PolicyModule,
   PSP22ErrorBuilder,
+  ReconciliationErrorBuilder,
+  TokenQuirkPolicy,
+  Voucher,
 } from "typechain/types-arguments/flash_loan_contract";
 import { FlashLoanReceiverErrorBuilder } from "typechain/types-arguments/flash_loan_receiver_mock";
+import { LenderFactoryErrorBuilder } from "typechain/types-arguments/lender_factory";
+import { RepaymentMode } from "typechain/types-arguments/flash_loan_receiver_simple";
+import { LendingPoolErrorBuilder } from "typechain/types-arguments/lending_pool_mock";
+import { RefinanceActionBuilder } from "typechain/types-arguments/refinance_receiver";
+import { AttackBuilder } from "typechain/types-arguments/malicious_receiver";
+import { TokenQuirkBuilder } from "typechain/types-arguments/adversarial_psp22";
+import { PolicyAnswer } from "typechain/types-arguments/policy_mock";
+import PolicyMock from "typechain/contracts/policy_mock";
+import FeeOracleMock from "typechain/contracts/fee_oracle_mock";
+import PSP34Mintable from "typechain/contracts/psp34_mintable";
+import PSP37Mintable from "typechain/contracts/psp37_mintable";
+import AssetLenderMock from "typechain/contracts/asset_lender_mock";
+import AssetReceiverMock from "typechain/contracts/asset_receiver_mock";
+import {
+  AssetSpecBuilder,
+  IdBuilder,
+} from "typechain/types-arguments/asset_lender_mock";
 
 const E6 = Math.pow(10, 6);
 const E12 = Math.pow(10, 12);
@@ -88,7 +151,7 @@ describe("Flash Loan tests. Preparing Env", () => {
           )
       ).to.eventually.be.rejected.and.to.have.deep.property(
         "_err",
-        FlashLoanErrorBuilder.Custom("NotAllowed")
+        FlashLoanErrorBuilder.PolicyRejected(PolicyModule.allowlist)
       );
     });
 
@@ -104,16 +167,19 @@ describe("Flash Loan tests. Preparing Env", () => {
           )
       ).to.eventually.be.rejected.and.to.have.deep.property(
         "_err",
-        FlashLoanErrorBuilder.Custom("NotAllowed")
+        FlashLoanErrorBuilder.PolicyRejected(PolicyModule.allowlist)
       );
     });
   });
 
-  describe("Owner deploys FlashLoanContract with allowed list = [user] and transfers 1milion of USDC and USDT to it. Then...", () => {
+  describe("Owner deploys FlashLoanContract with allowed list = [user] and fees = [[USDC, 0], [USDT, 0]], and transfers 1milion of USDC and USDT to it. Then...", () => {
     beforeEach("", async () => {
       flashLoanContract = await deployFlashLoanContract(
         owner,
-        [],
+        [
+          [USDC.address, 0],
+          [USDT.address, 0],
+        ],
         [user.address],
         []
       );
@@ -263,6 +329,22 @@ describe("Flash Loan tests. Preparing Env", () => {
       );
     });
 
+    it("User call flash_loan for 1 milion USDC and 1 milion of USDT and succeeds, the fee of each asset is tracked", async () => {
+      await flashLoanContract
+        .withSigner(user)
+        .tx.flashLoan(
+          flashLoanReceiver.address,
+          [USDC.address, USDT.address],
+          [milionUSD, milionUSD],
+          []
+        );
+      const [usdc, usdt] = (
+        await flashLoanContract.query.reconcile([USDC.address, USDT.address])
+      ).value;
+      expect(usdc.fees.toString()).to.equal("0");
+      expect(usdt.fees.toString()).to.equal((milionUSD / 10).toString());
+    });
+
     it("User call flash_loan for 1 miolions USDC and 1 milion of USDT", async () => {
       await expect(
         flashLoanContract
@@ -276,4 +358,2733 @@ describe("Flash Loan tests. Preparing Env", () => {
       ).to.eventually.be.rejected;
     });
   });
+
+  describe("Owner deploys WrappedNative with flash fee = 1_000 (0.1%) and wraps 2 units of native currency. Then...", () => {
+    let WNATIVE: WrappedNative;
+    const oneUnit = E12;
+    beforeEach("", async () => {
+      WNATIVE = await deployWrappedNative(owner, 1000);
+      await WNATIVE.withSigner(owner).tx.deposit({ value: 2 * oneUnit });
+      // receiver borrows wrapped tokens, unwraps them, wraps them back and repays
+      await flashLoanReceiver.tx.setSimulateBalanceToCoverFee(false);
+      await flashLoanReceiver.tx.setUnwrapAndRewrap(true);
+    });
+
+    it("User borrows 1 unit through flash-mint, receiver unwraps, rewraps and repays with fee", async () => {
+      const fee = oneUnit / 1000;
+      await WNATIVE.withSigner(owner).tx.transfer(
+        flashLoanReceiver.address,
+        fee,
+        []
+      );
+      const supplyBefore = (await WNATIVE.query.totalSupply()).value;
+      await expect(
+        WNATIVE.withSigner(user).tx.flashLoan(
+          flashLoanReceiver.address,
+          [WNATIVE.address],
+          [oneUnit],
+          []
+        )
+      ).to.eventually.be.fulfilled;
+      expect(
+        (
+          await WNATIVE.query.balanceOf(flashLoanReceiver.address)
+        ).value.toString()
+      ).to.equal("0");
+      expect((await WNATIVE.query.totalSupply()).value.toString()).to.equal(
+        supplyBefore.toString()
+      );
+    });
+
+    it("User borrows through flash-mint without covering the fee, transaction fails", async () => {
+      await expect(
+        WNATIVE.withSigner(user).query.flashLoan(
+          flashLoanReceiver.address,
+          [WNATIVE.address],
+          [oneUnit],
+          []
+        )
+      ).to.eventually.be.rejected;
+    });
+
+    it("User tries to flash-mint other asset, transaction fails", async () => {
+      await expect(
+        WNATIVE.withSigner(user).query.flashLoan(
+          flashLoanReceiver.address,
+          [USDC.address],
+          [oneUSD],
+          []
+        )
+      ).to.eventually.be.rejected.and.to.have.deep.property(
+        "_err",
+        FlashLoanErrorBuilder.Custom("NotWrappedNative")
+      );
+    });
+
+    it("Owner lends wrapped tokens through FlashLoanContract, user borrows 1 unit, receiver unwraps, rewraps and repays", async () => {
+      flashLoanContract = await deployFlashLoanContract(
+        owner,
+        [[WNATIVE.address, 0]],
+        [user.address],
+        []
+      );
+      await WNATIVE.withSigner(owner).tx.transfer(
+        flashLoanContract.address,
+        2 * oneUnit,
+        []
+      );
+      await expect(
+        flashLoanContract
+          .withSigner(user)
+          .tx.flashLoan(
+            flashLoanReceiver.address,
+            [WNATIVE.address],
+            [oneUnit],
+            []
+          )
+      ).to.eventually.be.fulfilled;
+      expect(
+        (
+          await WNATIVE.query.balanceOf(flashLoanContract.address)
+        ).value.toString()
+      ).to.equal((2 * oneUnit).toString());
+    });
+  });
+
+  describe("Owner deploys two FlashLoanContracts with allowed list = [router] and USDC fees 1_000 (0.1%) and 500 (0.05%), transfers 1milion of USDC to each and deploys FlashLoanRouter with both lenders. Then...", () => {
+    let expensiveLender: FlashLoanContract;
+    let cheapLender: FlashLoanContract;
+    let router: FlashLoanRouter;
+    beforeEach("", async () => {
+      router = await deployFlashLoanRouter(owner, []);
+      expensiveLender = await deployFlashLoanContract(
+        owner,
+        [[USDC.address, 1000]],
+        [router.address],
+        []
+      );
+      cheapLender = await deployFlashLoanContract(
+        owner,
+        [[USDC.address, 500]],
+        [router.address],
+        []
+      );
+      await router.withSigner(owner).tx.addLender(expensiveLender.address);
+      await router.withSigner(owner).tx.addLender(cheapLender.address);
+      for (const lender of [expensiveLender, cheapLender]) {
+        await USDC.withSigner(owner).tx.transfer(
+          lender.address,
+          milionUSD,
+          []
+        );
+      }
+    });
+
+    it("Router quotes 1.5 milion USDC taking 1 milion from the cheaper lender first", async () => {
+      const route = (
+        await router.query.quoteRoute([USDC.address], [1.5 * milionUSD])
+      ).value.ok!;
+      expect(route.length).to.equal(2);
+      expect(route[0].lender.toString()).to.equal(cheapLender.address);
+      expect(route[0].amounts[0].toString()).to.equal(milionUSD.toString());
+      expect(route[1].lender.toString()).to.equal(expensiveLender.address);
+      expect(route[1].amounts[0].toString()).to.equal(
+        (0.5 * milionUSD).toString()
+      );
+      const fees = (
+        await router.query.quoteFees([USDC.address], [1.5 * milionUSD])
+      ).value.ok!;
+      expect(fees[0].toString()).to.equal(
+        (500 * oneUSD + 500 * oneUSD).toString()
+      );
+    });
+
+    it("User call router flash_loan for 2 milions USDC and succeeds, every lender gets its fee", async () => {
+      await expect(
+        router
+          .withSigner(user)
+          .tx.flashLoan(
+            flashLoanReceiver.address,
+            [USDC.address],
+            [2 * milionUSD],
+            []
+          )
+      ).to.eventually.be.fulfilled;
+      expect(
+        (await USDC.query.balanceOf(cheapLender.address)).value.toString()
+      ).to.equal((milionUSD + 500 * oneUSD).toString());
+      expect(
+        (await USDC.query.balanceOf(expensiveLender.address)).value.toString()
+      ).to.equal((milionUSD + 1000 * oneUSD).toString());
+      expect(
+        (await USDC.query.balanceOf(router.address)).value.toString()
+      ).to.equal("0");
+    });
+
+    it("User call router flash_loan for 3 milions USDC, transaction fails", async () => {
+      await expect(
+        router
+          .withSigner(user)
+          .query.flashLoan(
+            flashLoanReceiver.address,
+            [USDC.address],
+            [3 * milionUSD],
+            []
+          )
+      ).to.eventually.be.rejected.and.to.have.deep.property(
+        "_err",
+        FlashLoanErrorBuilder.Custom("InsufficientLiquidity")
+      );
+    });
+
+    it("User call router flash_loan listing USDC twice, transaction fails with Parameters", async () => {
+      await expect(
+        router
+          .withSigner(user)
+          .query.flashLoan(
+            flashLoanReceiver.address,
+            [USDC.address, USDC.address],
+            [milionUSD, 0.5 * milionUSD],
+            []
+          )
+      ).to.eventually.be.rejected.and.to.have.deep.property(
+        "_err",
+        FlashLoanErrorBuilder.Parameters()
+      );
+    });
+  });
+
+  describe("Owner deploys LenderFactory with FlashLoanContract code. Then...", () => {
+    let factory: LenderFactory;
+    beforeEach("", async () => {
+      // uploads the code of FlashLoanContract
+      await deployFlashLoanContract(owner, [], [], []);
+      factory = await deployLenderFactory(owner);
+    });
+
+    it("User deploys a lender for USDC, it is registered by asset, owner and version and owned by the user", async () => {
+      const lenderAddress = (
+        await factory
+          .withSigner(user)
+          .query.deployLender(0, [[USDC.address, 1000]], [user.address], [])
+      ).value.ok!.toString();
+      await expect(
+        factory
+          .withSigner(user)
+          .tx.deployLender(0, [[USDC.address, 1000]], [user.address], [])
+      ).to.eventually.be.fulfilled;
+
+      expect(
+        (await factory.query.lendersByAsset(USDC.address, 0, 10)).value.map((a) =>
+          a.toString()
+        )
+      ).to.deep.equal([lenderAddress]);
+      expect(
+        (await factory.query.lendersByOwner(user.address, 0, 10)).value.map((a) =>
+          a.toString()
+        )
+      ).to.deep.equal([lenderAddress]);
+      expect(
+        (await factory.query.lendersByVersion(0, 0, 10)).value.map((a) =>
+          a.toString()
+        )
+      ).to.deep.equal([lenderAddress]);
+      const listed = (await factory.query.listLenders(0, 10)).value;
+      expect(listed.length).to.equal(1);
+      expect(listed[0][0].toString()).to.equal(lenderAddress);
+      expect(listed[0][1].owner.toString()).to.equal(user.address);
+
+      const lender = await getContractObject(
+        FlashLoanContract,
+        lenderAddress,
+        user
+      );
+      expect((await lender.query.owner()).value.toString()).to.equal(
+        user.address
+      );
+    });
+
+    it("User deploys two lenders for USDC, they are listed by asset page by page", async () => {
+      const deployed: string[] = [];
+      for (let i = 0; i < 2; i++) {
+        deployed.push(
+          (
+            await factory
+              .withSigner(user)
+              .query.deployLender(0, [[USDC.address, 1000]], [user.address], [])
+          ).value.ok!.toString()
+        );
+        await expect(
+          factory
+            .withSigner(user)
+            .tx.deployLender(0, [[USDC.address, 1000]], [user.address], [])
+        ).to.eventually.be.fulfilled;
+      }
+
+      const firstPage = (
+        await factory.query.lendersByAsset(USDC.address, 0, 1)
+      ).value.map((a) => a.toString());
+      const secondPage = (
+        await factory.query.lendersByAsset(USDC.address, 1, 1)
+      ).value.map((a) => a.toString());
+      expect(firstPage).to.deep.equal([deployed[0]]);
+      expect(secondPage).to.deep.equal([deployed[1]]);
+      expect(
+        (await factory.query.lendersByAsset(USDC.address, 2, 1)).value
+      ).to.deep.equal([]);
+    });
+
+    it("User deploys a lender of unknown version, transaction fails", async () => {
+      await expect(
+        factory
+          .withSigner(user)
+          .query.deployLender(1, [[USDC.address, 1000]], [user.address], [])
+      ).to.eventually.be.rejected.and.to.have.deep.property(
+        "_err",
+        LenderFactoryErrorBuilder.UnknownVersion()
+      );
+    });
+  });
+
+  describe("Owner deploys FlashLoanContract with allowed list = [user, owner] and USDC fee 1_000 (0.1%), transfers 1milion of USDC to it and deploys FlashLoanReceiverSimple holding 1_000 USDC for fees. Then...", () => {
+    let receiver: FlashLoanReceiverSimple;
+    beforeEach("", async () => {
+      flashLoanContract = await deployFlashLoanContract(
+        owner,
+        [[USDC.address, 1000]],
+        [user.address, owner.address],
+        []
+      );
+      await USDC.withSigner(owner).tx.transfer(
+        flashLoanContract.address,
+        milionUSD,
+        []
+      );
+      receiver = await deployFlashLoanReceiverSimple(owner);
+      await USDC.withSigner(owner).tx.transfer(
+        receiver.address,
+        1000 * oneUSD,
+        []
+      );
+    });
+
+    it("Owner call flash_loan but the lender is not trusted by the receiver, transaction fails", async () => {
+      await expect(
+        flashLoanContract
+          .withSigner(owner)
+          .query.flashLoan(receiver.address, [USDC.address], [milionUSD], [])
+      ).to.eventually.be.rejected.and.to.have.deep.property(
+        "_err",
+        FlashLoanErrorBuilder.FlashLoanReceiverError(
+          FlashLoanReceiverErrorBuilder.UntrustedLender()
+        )
+      );
+    });
+
+    describe("Owner sets the lender as trusted with Approve repayment mode. Then...", () => {
+      beforeEach("", async () => {
+        await receiver
+          .withSigner(owner)
+          .tx.setTrustedLender(
+            flashLoanContract.address,
+            RepaymentMode.approve
+          );
+      });
+
+      it("User call flash_loan but is not authorized initiator, transaction fails", async () => {
+        await expect(
+          flashLoanContract
+            .withSigner(user)
+            .query.flashLoan(receiver.address, [USDC.address], [milionUSD], [])
+        ).to.eventually.be.rejected.and.to.have.deep.property(
+          "_err",
+          FlashLoanErrorBuilder.FlashLoanReceiverError(
+            FlashLoanReceiverErrorBuilder.UnauthorizedInitiator()
+          )
+        );
+      });
+
+      it("Owner call flash_loan for 1 milion USDC and the receiver repays it with the fee", async () => {
+        await expect(
+          flashLoanContract
+            .withSigner(owner)
+            .tx.flashLoan(receiver.address, [USDC.address], [milionUSD], [])
+        ).to.eventually.be.fulfilled;
+        expect(
+          (await USDC.query.balanceOf(receiver.address)).value.toString()
+        ).to.equal("0");
+        expect(
+          (
+            await USDC.query.balanceOf(flashLoanContract.address)
+          ).value.toString()
+        ).to.equal((milionUSD + 1000 * oneUSD).toString());
+      });
+
+      it("Owner call flash_loan for USDC listed twice, 500_000 USDC each, and the receiver approves the sum of both entries with their fees", async () => {
+        await expect(
+          flashLoanContract
+            .withSigner(owner)
+            .tx.flashLoan(
+              receiver.address,
+              [USDC.address, USDC.address],
+              [milionUSD / 2, milionUSD / 2],
+              []
+            )
+        ).to.eventually.be.fulfilled;
+        expect(
+          (await USDC.query.balanceOf(receiver.address)).value.toString()
+        ).to.equal("0");
+        expect(
+          (
+            await USDC.query.balanceOf(flashLoanContract.address)
+          ).value.toString()
+        ).to.equal((milionUSD + 1000 * oneUSD).toString());
+      });
+
+      it("Owner authorizes user, user call flash_loan for 1 milion USDC and succeeds", async () => {
+        await receiver
+          .withSigner(owner)
+          .tx.setAuthorizedInitiator(user.address, true);
+        await expect(
+          flashLoanContract
+            .withSigner(user)
+            .tx.flashLoan(receiver.address, [USDC.address], [milionUSD], [])
+        ).to.eventually.be.fulfilled;
+      });
+    });
+  });
+
+  describe("Owner deploys FlashLoanContract with allowed list = [user, owner] and USDC fee 1_000 (0.1%), transfers 1milion of USDC to it and deploys FlashLoanExecutor trusting the lender. Then...", () => {
+    let executor: FlashLoanExecutor;
+    const fee = 1000 * oneUSD;
+    // executor mints the fee to itself - PSP22Mintable::mint(executor, fee)
+    const mintFeeCall = async (minDelta: number) => {
+      const nativeApi = await api.getAndWaitForReady();
+      return {
+        callee: USDC.address,
+        selector: Array.from(
+          USDC.nativeContract.abi.findMessage("PSP22Mintable::mint").selector
+        ),
+        input: u8aToHex(
+          u8aConcat(
+            nativeApi.createType("AccountId", executor.address).toU8a(),
+            nativeApi.createType("u128", fee).toU8a()
+          )
+        ),
+        transferredValue: 0,
+        gasLimit: 0,
+        checkResult: true,
+        balanceDeltas: [{ asset: USDC.address, minDelta: minDelta }],
+      };
+    };
+    beforeEach("", async () => {
+      flashLoanContract = await deployFlashLoanContract(
+        owner,
+        [[USDC.address, 1000]],
+        [user.address, owner.address],
+        []
+      );
+      await USDC.withSigner(owner).tx.transfer(
+        flashLoanContract.address,
+        milionUSD,
+        []
+      );
+      executor = await deployFlashLoanExecutor(owner);
+      await executor
+        .withSigner(owner)
+        .tx.setTrustedLender(
+          flashLoanContract.address,
+          RepaymentMode.approve
+        );
+    });
+
+    it("Owner call flash_loan for 1 milion USDC with a call that mints the fee and succeeds", async () => {
+      const data = (
+        await executor.query.encodeEnvelope([await mintFeeCall(fee)])
+      ).value;
+      await expect(
+        flashLoanContract
+          .withSigner(owner)
+          .tx.flashLoan(executor.address, [USDC.address], [milionUSD], data)
+      ).to.eventually.be.fulfilled;
+      expect(
+        (
+          await USDC.query.balanceOf(flashLoanContract.address)
+        ).value.toString()
+      ).to.equal((milionUSD + fee).toString());
+    });
+
+    it("Owner call flash_loan with a balance assertion that does not hold, transaction fails", async () => {
+      const data = (
+        await executor.query.encodeEnvelope([await mintFeeCall(fee + 1)])
+      ).value;
+      await expect(
+        flashLoanContract
+          .withSigner(owner)
+          .query.flashLoan(executor.address, [USDC.address], [milionUSD], data)
+      ).to.eventually.be.rejected;
+    });
+
+    it("User call flash_loan with the executor, transaction fails as only owner can use it", async () => {
+      const data = (
+        await executor.query.encodeEnvelope([await mintFeeCall(fee)])
+      ).value;
+      await expect(
+        flashLoanContract
+          .withSigner(user)
+          .query.flashLoan(executor.address, [USDC.address], [milionUSD], data)
+      ).to.eventually.be.rejected.and.to.have.deep.property(
+        "_err",
+        FlashLoanErrorBuilder.FlashLoanReceiverError(
+          FlashLoanReceiverErrorBuilder.UnauthorizedInitiator()
+        )
+      );
+    });
+  });
+
+  describe("Owner deploys FlashLoanContract with allowed list = [owner] and USDC fee 1_000 (0.1%), transfers 1milion of USDC to it, deploys two USDC/USDT pairs with 0.3% fee: cheap with 1milion USDC / 1milion USDT and expensive with 1milion USDC / 1.2milion USDT, and deploys ArbitrageReceiver trusting the lender. Then...", () => {
+    let cheapPair: AmmPairMock;
+    let expensivePair: AmmPairMock;
+    let arbitrageReceiver: ArbitrageReceiver;
+    const amount = 50_000 * oneUSD;
+    const fee = 50 * oneUSD;
+    beforeEach("", async () => {
+      await USDC.tx.mint(owner.address, 2 * milionUSD);
+      await USDT.tx.mint(owner.address, 2 * milionUSD);
+      flashLoanContract = await deployFlashLoanContract(
+        owner,
+        [[USDC.address, 1000]],
+        [owner.address],
+        []
+      );
+      await USDC.withSigner(owner).tx.transfer(
+        flashLoanContract.address,
+        milionUSD,
+        []
+      );
+      cheapPair = await deployAmmPairMock(owner, USDC.address, USDT.address);
+      expensivePair = await deployAmmPairMock(
+        owner,
+        USDC.address,
+        USDT.address
+      );
+      for (const [pair, usdtReserve] of [
+        [cheapPair, milionUSD],
+        [expensivePair, 1.2 * milionUSD],
+      ] as [AmmPairMock, number][]) {
+        await USDC.withSigner(owner).tx.approve(pair.address, milionUSD);
+        await USDT.withSigner(owner).tx.approve(pair.address, usdtReserve);
+        await pair.withSigner(owner).tx.addLiquidity(milionUSD, usdtReserve);
+      }
+      arbitrageReceiver = await deployArbitrageReceiver(owner);
+      await arbitrageReceiver
+        .withSigner(owner)
+        .tx.setTrustedLender(
+          flashLoanContract.address,
+          RepaymentMode.approve
+        );
+    });
+
+    it("Owner call flash_loan for 50_000 USDC buying USDT on the expensive pair and selling it on the cheap one, the loan is repaid and the receiver keeps the profit", async () => {
+      const expectedBought = (
+        await expensivePair.query.getAmountOut(USDC.address, amount)
+      ).value.ok!;
+      const expectedSold = (
+        await cheapPair.query.getAmountOut(USDT.address, expectedBought)
+      ).value.ok!;
+      const expectedProfit = new BN(expectedSold.toString())
+        .sub(new BN(amount))
+        .sub(new BN(fee));
+      expect(expectedProfit.gt(new BN(1000 * oneUSD))).to.be.true;
+
+      const data = (
+        await arbitrageReceiver.query.encodeParams({
+          buyPair: expensivePair.address,
+          sellPair: cheapPair.address,
+          minProfit: 1000 * oneUSD,
+        })
+      ).value;
+      await expect(
+        flashLoanContract
+          .withSigner(owner)
+          .tx.flashLoan(
+            arbitrageReceiver.address,
+            [USDC.address],
+            [amount],
+            data
+          )
+      ).to.eventually.be.fulfilled;
+
+      expect(
+        (
+          await USDC.query.balanceOf(flashLoanContract.address)
+        ).value.toString()
+      ).to.equal((milionUSD + fee).toString());
+      expect(
+        (
+          await USDC.query.balanceOf(arbitrageReceiver.address)
+        ).value.toString()
+      ).to.equal(expectedProfit.toString());
+      expect(
+        (
+          await USDT.query.balanceOf(arbitrageReceiver.address)
+        ).value.toString()
+      ).to.equal("0");
+
+      await expect(
+        arbitrageReceiver
+          .withSigner(owner)
+          .tx.withdraw(USDC.address, owner.address, expectedProfit)
+      ).to.eventually.be.fulfilled;
+      expect(
+        (
+          await USDC.query.balanceOf(arbitrageReceiver.address)
+        ).value.toString()
+      ).to.equal("0");
+    });
+
+    it("Owner call flash_loan trading in the wrong direction, transaction fails as the trade is a loss and the pools are untouched", async () => {
+      const data = (
+        await arbitrageReceiver.query.encodeParams({
+          buyPair: cheapPair.address,
+          sellPair: expensivePair.address,
+          minProfit: 0,
+        })
+      ).value;
+      await expect(
+        flashLoanContract
+          .withSigner(owner)
+          .query.flashLoan(
+            arbitrageReceiver.address,
+            [USDC.address],
+            [amount],
+            data
+          )
+      ).to.eventually.be.rejected;
+      expect(
+        (await cheapPair.query.getReserves()).value.map((r) => r.toString())
+      ).to.deep.equal([milionUSD.toString(), milionUSD.toString()]);
+    });
+
+    it("Owner call flash_loan asking for more profit than the price difference allows, transaction fails", async () => {
+      const data = (
+        await arbitrageReceiver.query.encodeParams({
+          buyPair: expensivePair.address,
+          sellPair: cheapPair.address,
+          minProfit: 10_000 * oneUSD,
+        })
+      ).value;
+      await expect(
+        flashLoanContract
+          .withSigner(owner)
+          .query.flashLoan(
+            arbitrageReceiver.address,
+            [USDC.address],
+            [amount],
+            data
+          )
+      ).to.eventually.be.rejected;
+    });
+  });
+
+  describe("Owner deploys LendingPoolMock with USDC and WETH (price 1_000 USDC) configured as in rules.json and supplies 1milion of USDC, user deposits 100 WETH and borrows 60_000 USDC. Owner deploys FlashLoanContract with allowed list = [owner] and USDC fee 1_000 (0.1%), a WETH/USDC pair with 1_000 WETH / 700_000 USDC and LiquidationReceiver trusting the lender. Then...", () => {
+    let WETH: PSP22Mintable;
+    let pool: LendingPoolMock;
+    let pair: AmmPairMock;
+    let liquidationReceiver: LiquidationReceiver;
+    const oneWETH = E6;
+    const amount = 30_000 * oneUSD;
+    const fee = 30 * oneUSD;
+    const liquidationData = async () =>
+      (
+        await liquidationReceiver.query.encodeParams({
+          pool: pool.address,
+          user: user.address,
+          collateralAsset: WETH.address,
+          pair: pair.address,
+          minProfit: 100 * oneUSD,
+        })
+      ).value;
+    beforeEach("", async () => {
+      WETH = await deployPSP22Mintable(deployer, "WETH", 6);
+      await WETH.tx.mint(owner.address, 1_000 * oneWETH);
+      await WETH.tx.mint(user.address, 100 * oneWETH);
+      await USDC.tx.mint(owner.address, 2 * milionUSD);
+
+      pool = await deployLendingPoolMock(owner);
+      await pool.withSigner(owner).tx.setAsset(USDC.address, {
+        priceE6: E6,
+        collateralCoefficientE6: 980_000,
+        borrowCoefficientE6: 1_020_000,
+        penaltyE6: 10_000,
+      });
+      await pool.withSigner(owner).tx.setAsset(WETH.address, {
+        priceE6: 1_000 * E6,
+        collateralCoefficientE6: 800_000,
+        borrowCoefficientE6: 1_200_000,
+        penaltyE6: 100_000,
+      });
+      await USDC.withSigner(owner).tx.approve(pool.address, milionUSD);
+      await pool
+        .withSigner(owner)
+        .tx.deposit(USDC.address, milionUSD, owner.address);
+      await WETH.withSigner(user).tx.approve(pool.address, 100 * oneWETH);
+      await pool
+        .withSigner(user)
+        .tx.deposit(WETH.address, 100 * oneWETH, user.address);
+      await pool.withSigner(user).tx.borrow(USDC.address, 60_000 * oneUSD);
+
+      flashLoanContract = await deployFlashLoanContract(
+        owner,
+        [[USDC.address, 1000]],
+        [owner.address],
+        []
+      );
+      await USDC.withSigner(owner).tx.transfer(
+        flashLoanContract.address,
+        milionUSD,
+        []
+      );
+      pair = await deployAmmPairMock(owner, WETH.address, USDC.address);
+      await WETH.withSigner(owner).tx.approve(pair.address, 1_000 * oneWETH);
+      await USDC.withSigner(owner).tx.approve(pair.address, 700_000 * oneUSD);
+      await pair
+        .withSigner(owner)
+        .tx.addLiquidity(1_000 * oneWETH, 700_000 * oneUSD);
+      liquidationReceiver = await deployLiquidationReceiver(owner);
+      await liquidationReceiver
+        .withSigner(owner)
+        .tx.setTrustedLender(
+          flashLoanContract.address,
+          RepaymentMode.approve
+        );
+    });
+
+    it("User tries to borrow more than the collateral allows, transaction fails", async () => {
+      await expect(
+        pool.withSigner(user).query.borrow(USDC.address, 10_000 * oneUSD)
+      ).to.eventually.be.rejected.and.to.have.deep.property(
+        "_err",
+        LendingPoolErrorBuilder.InsufficientCollateral()
+      );
+    });
+
+    it("Owner call flash_loan to liquidate the healthy position, transaction fails", async () => {
+      await expect(
+        flashLoanContract
+          .withSigner(owner)
+          .query.flashLoan(
+            liquidationReceiver.address,
+            [USDC.address],
+            [amount],
+            await liquidationData()
+          )
+      ).to.eventually.be.rejected;
+    });
+
+    it("WETH price drops to 700 USDC, owner call flash_loan for 30_000 USDC, the position is liquidated, the seized WETH is sold on the pair, the loan is repaid and the receiver keeps the profit", async () => {
+      await pool.withSigner(owner).tx.setPrice(WETH.address, 700 * E6);
+      expect((await pool.query.isHealthy(user.address)).value).to.be.false;
+
+      // 30_000 USDC worth of WETH with 10% penalty
+      const seized = new BN(amount)
+        .mul(new BN(E6))
+        .div(new BN(700 * E6))
+        .mul(new BN(1_100_000))
+        .div(new BN(E6));
+      const sold = new BN(
+        (
+          await pair.query.getAmountOut(WETH.address, seized.toString())
+        ).value.ok!.toString()
+      );
+      const expectedProfit = sold.sub(new BN(amount)).sub(new BN(fee));
+      expect(expectedProfit.gt(new BN(1000 * oneUSD))).to.be.true;
+
+      await expect(
+        flashLoanContract
+          .withSigner(owner)
+          .tx.flashLoan(
+            liquidationReceiver.address,
+            [USDC.address],
+            [amount],
+            await liquidationData()
+          )
+      ).to.eventually.be.fulfilled;
+
+      expect(
+        (
+          await USDC.query.balanceOf(flashLoanContract.address)
+        ).value.toString()
+      ).to.equal((milionUSD + fee).toString());
+      expect(
+        (
+          await USDC.query.balanceOf(liquidationReceiver.address)
+        ).value.toString()
+      ).to.equal(expectedProfit.toString());
+      expect(
+        (
+          await pool.query.debtOf(user.address, USDC.address)
+        ).value.toString()
+      ).to.equal((30_000 * oneUSD).toString());
+      expect(
+        (
+          await pool.query.collateralOf(user.address, WETH.address)
+        ).value.toString()
+      ).to.equal(new BN(100 * oneWETH).sub(seized).toString());
+    });
+  });
+
+  describe("Owner deploys two LendingPoolMocks with USDC, USDT and WETH (price 1_000 USD) supplied with 1milion of USDC each, FlashLoanContract with allowed list = [owner], USDC and USDT fees 1_000 (0.1%) and a WETH/USDT pair with 1_000 WETH / 1milion USDT. Owner deploys RefinanceReceiver trusting the lender, deposits 100 WETH for it in pool A and borrows 50_000 USDC through it. Then...", () => {
+    let WETH: PSP22Mintable;
+    let poolA: LendingPoolMock;
+    let poolB: LendingPoolMock;
+    let pair: AmmPairMock;
+    let refinanceReceiver: RefinanceReceiver;
+    const oneWETH = E6;
+    const debt = 50_000 * oneUSD;
+    const stableConfig = {
+      priceE6: E6,
+      collateralCoefficientE6: 980_000,
+      borrowCoefficientE6: 1_020_000,
+      penaltyE6: 10_000,
+    };
+    beforeEach("", async () => {
+      WETH = await deployPSP22Mintable(deployer, "WETH", 6);
+      await WETH.tx.mint(owner.address, 1_100 * oneWETH);
+      await USDC.tx.mint(owner.address, 2 * milionUSD);
+      await USDT.tx.mint(owner.address, milionUSD);
+
+      poolA = await deployLendingPoolMock(owner);
+      poolB = await deployLendingPoolMock(owner);
+      for (const pool of [poolA, poolB]) {
+        await pool.withSigner(owner).tx.setAsset(USDC.address, stableConfig);
+        await pool.withSigner(owner).tx.setAsset(USDT.address, stableConfig);
+        await pool.withSigner(owner).tx.setAsset(WETH.address, {
+          priceE6: 1_000 * E6,
+          collateralCoefficientE6: 800_000,
+          borrowCoefficientE6: 1_200_000,
+          penaltyE6: 100_000,
+        });
+        await USDC.withSigner(owner).tx.approve(pool.address, milionUSD);
+        await pool
+          .withSigner(owner)
+          .tx.deposit(USDC.address, milionUSD, owner.address);
+      }
+
+      flashLoanContract = await deployFlashLoanContract(
+        owner,
+        [
+          [USDC.address, 1000],
+          [USDT.address, 1000],
+        ],
+        [owner.address],
+        []
+      );
+      await USDC.withSigner(owner).tx.transfer(
+        flashLoanContract.address,
+        milionUSD,
+        []
+      );
+      await USDT.withSigner(owner).tx.transfer(
+        flashLoanContract.address,
+        milionUSD,
+        []
+      );
+      pair = await deployAmmPairMock(owner, WETH.address, USDT.address);
+      await WETH.withSigner(owner).tx.approve(pair.address, 1_000 * oneWETH);
+      await USDT.withSigner(owner).tx.approve(pair.address, milionUSD);
+      await pair.withSigner(owner).tx.addLiquidity(1_000 * oneWETH, milionUSD);
+
+      refinanceReceiver = await deployRefinanceReceiver(owner);
+      await refinanceReceiver
+        .withSigner(owner)
+        .tx.setTrustedLender(
+          flashLoanContract.address,
+          RepaymentMode.approve
+        );
+      await WETH.withSigner(owner).tx.approve(poolA.address, 100 * oneWETH);
+      await poolA
+        .withSigner(owner)
+        .tx.deposit(WETH.address, 100 * oneWETH, refinanceReceiver.address);
+      await refinanceReceiver
+        .withSigner(owner)
+        .tx.borrow(poolA.address, USDC.address, debt, owner.address);
+    });
+
+    it("Owner call flash_loan for 50_000 USDC migrating the debt and the WETH collateral to pool B, the position is moved and the debt at pool B includes the fee", async () => {
+      const fee = 50 * oneUSD;
+      const data = (
+        await refinanceReceiver.query.encodeAction(
+          RefinanceActionBuilder.MigrateDebt({
+            fromPool: poolA.address,
+            toPool: poolB.address,
+            collateralAsset: WETH.address,
+            collateralAmount: 100 * oneWETH,
+          })
+        )
+      ).value;
+      await expect(
+        flashLoanContract
+          .withSigner(owner)
+          .tx.flashLoan(
+            refinanceReceiver.address,
+            [USDC.address],
+            [debt],
+            data
+          )
+      ).to.eventually.be.fulfilled;
+
+      expect(
+        (
+          await USDC.query.balanceOf(flashLoanContract.address)
+        ).value.toString()
+      ).to.equal((milionUSD + fee).toString());
+      for (const [pool, expectedDebt, expectedCollateral] of [
+        [poolA, 0, 0],
+        [poolB, debt + fee, 100 * oneWETH],
+      ] as [LendingPoolMock, number, number][]) {
+        expect(
+          (
+            await pool.query.debtOf(refinanceReceiver.address, USDC.address)
+          ).value.toString()
+        ).to.equal(expectedDebt.toString());
+        expect(
+          (
+            await pool.query.collateralOf(
+              refinanceReceiver.address,
+              WETH.address
+            )
+          ).value.toString()
+        ).to.equal(expectedCollateral.toString());
+      }
+    });
+
+    it("Owner call flash_loan repaying only 20_000 USDC while migrating the whole collateral, transaction fails as the rest of the debt at pool A would be uncollateralized", async () => {
+      const data = (
+        await refinanceReceiver.query.encodeAction(
+          RefinanceActionBuilder.MigrateDebt({
+            fromPool: poolA.address,
+            toPool: poolB.address,
+            collateralAsset: WETH.address,
+            collateralAmount: 100 * oneWETH,
+          })
+        )
+      ).value;
+      await expect(
+        flashLoanContract
+          .withSigner(owner)
+          .query.flashLoan(
+            refinanceReceiver.address,
+            [USDC.address],
+            [20_000 * oneUSD],
+            data
+          )
+      ).to.eventually.be.rejected;
+    });
+
+    it("Owner call flash_loan for 60_000 USDT swapping the WETH collateral at pool A to USDT, the WETH is sold on the pair and the rest of the proceeds stays in the receiver", async () => {
+      const amount = 60_000 * oneUSD;
+      const fee = 60 * oneUSD;
+      const sold = new BN(
+        (
+          await pair.query.getAmountOut(WETH.address, 100 * oneWETH)
+        ).value.ok!.toString()
+      );
+      const data = (
+        await refinanceReceiver.query.encodeAction(
+          RefinanceActionBuilder.SwapCollateral({
+            pool: poolA.address,
+            oldCollateral: WETH.address,
+            oldAmount: 100 * oneWETH,
+            pair: pair.address,
+          })
+        )
+      ).value;
+      await expect(
+        flashLoanContract
+          .withSigner(owner)
+          .tx.flashLoan(
+            refinanceReceiver.address,
+            [USDT.address],
+            [amount],
+            data
+          )
+      ).to.eventually.be.fulfilled;
+
+      expect(
+        (
+          await USDT.query.balanceOf(flashLoanContract.address)
+        ).value.toString()
+      ).to.equal((milionUSD + fee).toString());
+      expect(
+        (
+          await poolA.query.collateralOf(
+            refinanceReceiver.address,
+            WETH.address
+          )
+        ).value.toString()
+      ).to.equal("0");
+      expect(
+        (
+          await poolA.query.collateralOf(
+            refinanceReceiver.address,
+            USDT.address
+          )
+        ).value.toString()
+      ).to.equal(amount.toString());
+      expect(
+        (
+          await USDT.query.balanceOf(refinanceReceiver.address)
+        ).value.toString()
+      ).to.equal(sold.sub(new BN(amount + fee)).toString());
+    });
+  });
+
+  describe("Deployer deploys MaliciousReceiver, owner deploys FlashLoanContract with allowed list = [owner, malicious receiver] and USDC and USDT fees 1_000 (0.1%) and transfers 1milion of USDC and USDT to it. Then...", () => {
+    let maliciousReceiver: MaliciousReceiver;
+    const amount = 1_000 * oneUSD;
+    const fee = oneUSD;
+    const expectLenderBalances = async (usdc: number, usdt: number) => {
+      expect(
+        (
+          await USDC.query.balanceOf(flashLoanContract.address)
+        ).value.toString()
+      ).to.equal(usdc.toString());
+      expect(
+        (
+          await USDT.query.balanceOf(flashLoanContract.address)
+        ).value.toString()
+      ).to.equal(usdt.toString());
+    };
+    const flashLoanUSDC = () =>
+      flashLoanContract
+        .withSigner(owner)
+        .tx.flashLoan(
+          maliciousReceiver.address,
+          [USDC.address],
+          [amount],
+          []
+        );
+    beforeEach("", async () => {
+      maliciousReceiver = await deployMaliciousReceiver(deployer);
+      flashLoanContract = await deployFlashLoanContract(
+        owner,
+        [
+          [USDC.address, 1000],
+          [USDT.address, 1000],
+        ],
+        [owner.address, maliciousReceiver.address],
+        []
+      );
+      await USDC.withSigner(owner).tx.transfer(
+        flashLoanContract.address,
+        milionUSD,
+        []
+      );
+      await USDT.withSigner(owner).tx.transfer(
+        flashLoanContract.address,
+        milionUSD,
+        []
+      );
+    });
+
+    it("Without an attack the receiver repays the loan with the fee", async () => {
+      await expect(flashLoanUSDC()).to.eventually.be.fulfilled;
+      await expectLenderBalances(milionUSD + fee, milionUSD);
+    });
+
+    it("Receiver reenters flash_loan, both loans are repaid with fees", async () => {
+      await maliciousReceiver.tx.setAttack(AttackBuilder.ReenterFlashLoan());
+      await expect(flashLoanUSDC()).to.eventually.be.fulfilled;
+      await expectLenderBalances(milionUSD + 2 * fee, milionUSD);
+    });
+
+    it("Receiver calls owner only messages of the lender, transaction fails", async () => {
+      const nativeApi = await api.getAndWaitForReady();
+      const abi = flashLoanContract.nativeContract.abi;
+      for (const [message, input] of [
+        [
+          "withdraw",
+          u8aConcat(
+            nativeApi.createType("AccountId", USDC.address).toU8a(),
+            nativeApi
+              .createType("AccountId", maliciousReceiver.address)
+              .toU8a(),
+            nativeApi.createType("Option<u128>", milionUSD).toU8a()
+          ),
+        ],
+        [
+          "Ownable::transfer_ownership",
+          nativeApi
+            .createType("AccountId", maliciousReceiver.address)
+            .toU8a(),
+        ],
+      ] as [string, Uint8Array][]) {
+        await maliciousReceiver.tx.setAttack(
+          AttackBuilder.CallLender({
+            selector: Array.from(abi.findMessage(message).selector),
+            input: u8aToHex(input),
+          })
+        );
+        await expect(flashLoanUSDC()).to.eventually.be.rejected;
+      }
+      await expectLenderBalances(milionUSD, milionUSD);
+      expect((await flashLoanContract.query.owner()).value).to.equal(
+        owner.address
+      );
+    });
+
+    it("Receiver reenters flash_loan, no loan is in progress and nothing is outstanding after both are repaid", async () => {
+      await maliciousReceiver.tx.setAttack(AttackBuilder.ReenterFlashLoan());
+      await expect(flashLoanUSDC()).to.eventually.be.fulfilled;
+      expect((await flashLoanContract.query.isInFlashLoan()).value).to.equal(
+        false
+      );
+      expect(
+        (
+          await flashLoanContract.query.outstanding(USDC.address)
+        ).value.toString()
+      ).to.equal("0");
+    });
+
+    it("Receiver reenters flash_loan, the loans get distinct ids and both receipts are kept", async () => {
+      await maliciousReceiver.tx.setAttack(AttackBuilder.ReenterFlashLoan());
+      await expect(flashLoanUSDC()).to.eventually.be.fulfilled;
+      const outer = (await flashLoanContract.query.loanReceipt(1)).value!;
+      const nested = (await flashLoanContract.query.loanReceipt(2)).value!;
+      expect(outer.loanId.toString()).to.equal("1");
+      expect(outer.initiator.toString()).to.equal(owner.address);
+      expect(nested.loanId.toString()).to.equal("2");
+      expect(nested.initiator.toString()).to.equal(maliciousReceiver.address);
+      expect((await flashLoanContract.query.loanReceipt(3)).value).to.be.null;
+    });
+
+    it("Owner sets a rate limit of 1_500 USDC, receiver borrows 1_000 USDC and reenters flash_loan, the nested loan exceeds the limit and the transaction fails", async () => {
+      await flashLoanContract
+        .withSigner(owner)
+        .tx.setPolicies([PolicyModule.allowlist, PolicyModule.rateLimit]);
+      await flashLoanContract
+        .withSigner(owner)
+        .tx.setRateLimit(USDC.address, {
+          amount: 1_500 * oneUSD,
+          window: 1_000_000_000,
+        });
+      const borrowUSDC = () =>
+        maliciousReceiver.tx.borrow(
+          flashLoanContract.address,
+          [USDC.address],
+          [amount]
+        );
+      await maliciousReceiver.tx.setAttack(AttackBuilder.ReenterFlashLoan());
+      await expect(borrowUSDC()).to.eventually.be.rejected;
+      expect(
+        (
+          await flashLoanContract.query.rateLimitUsed(
+            maliciousReceiver.address,
+            USDC.address
+          )
+        ).value.toString()
+      ).to.equal("0");
+      await maliciousReceiver.tx.setAttack(null);
+      await expect(borrowUSDC()).to.eventually.be.fulfilled;
+      expect(
+        (
+          await flashLoanContract.query.rateLimitUsed(
+            maliciousReceiver.address,
+            USDC.address
+          )
+        ).value.toString()
+      ).to.equal(amount.toString());
+    });
+
+    it("Owner transfers ownership of the lender to the receiver, receiver calls withdraw during the loan, transaction fails with FlashLoanInProgress", async () => {
+      const nativeApi = await api.getAndWaitForReady();
+      const abi = flashLoanContract.nativeContract.abi;
+      await flashLoanContract
+        .withSigner(owner)
+        .tx.transferOwnership(maliciousReceiver.address);
+      await maliciousReceiver.tx.setAttack(
+        AttackBuilder.CallLender({
+          selector: Array.from(abi.findMessage("withdraw").selector),
+          input: u8aToHex(
+            u8aConcat(
+              nativeApi.createType("AccountId", USDC.address).toU8a(),
+              nativeApi
+                .createType("AccountId", maliciousReceiver.address)
+                .toU8a(),
+              nativeApi
+                .createType("Option<u128>", milionUSD - amount)
+                .toU8a()
+            )
+          ),
+        })
+      );
+      await expect(flashLoanUSDC()).to.eventually.be.rejected;
+      await expectLenderBalances(milionUSD, milionUSD);
+    });
+
+    it("Receiver approves USDT instead of the lent USDC, transaction fails", async () => {
+      await maliciousReceiver.tx.setAttack(
+        AttackBuilder.RepayWithToken(USDT.address)
+      );
+      await expect(flashLoanUSDC()).to.eventually.be.rejected;
+      await expectLenderBalances(milionUSD, milionUSD);
+    });
+
+    it("Receiver transfers back half of the loan instead of approving it, transaction fails", async () => {
+      await maliciousReceiver.tx.setAttack(
+        AttackBuilder.PartialTransfer({ partE6: 500_000 })
+      );
+      await expect(flashLoanUSDC()).to.eventually.be.rejected;
+      await expectLenderBalances(milionUSD, milionUSD);
+    });
+
+    it("Receiver transfers the loan to another account and approves it anyway, transaction fails", async () => {
+      await maliciousReceiver.tx.setAttack(
+        AttackBuilder.TransferElsewhere(random.address)
+      );
+      await expect(flashLoanUSDC()).to.eventually.be.rejected;
+      await expectLenderBalances(milionUSD, milionUSD);
+      expect(
+        (await USDC.query.balanceOf(random.address)).value.toString()
+      ).to.equal("0");
+    });
+
+    it("Receiver burns all the gas, transaction fails", async () => {
+      await maliciousReceiver.tx.setAttack(
+        AttackBuilder.BurnGas({ iterations: 1_000_000 })
+      );
+      await expect(flashLoanUSDC()).to.eventually.be.rejected;
+      await expectLenderBalances(milionUSD, milionUSD);
+    });
+
+    it("Receiver repays USDC and fails on USDT, transaction fails and USDC is not lent either", async () => {
+      await maliciousReceiver.tx.setAttack(AttackBuilder.FailOnNthAsset(1));
+      await expect(
+        flashLoanContract
+          .withSigner(owner)
+          .tx.flashLoan(
+            maliciousReceiver.address,
+            [USDC.address, USDT.address],
+            [amount, amount],
+            []
+          )
+      ).to.eventually.be.rejected;
+      await expectLenderBalances(milionUSD, milionUSD);
+      expect(
+        (
+          await USDC.query.balanceOf(maliciousReceiver.address)
+        ).value.toString()
+      ).to.equal("0");
+    });
+  });
+
+  describe("Deployer deploys AdversarialPSP22 BAD, owner deploys FlashLoanContract with allowed list = [owner, BAD] and BAD fee 1_000 (0.1%) and transfers 1milion of BAD to it. Then...", () => {
+    let BAD: AdversarialPSP22;
+    const amount = 1_000 * oneUSD;
+    const fee = oneUSD;
+    const reenterFlashLoanOnRepay = async () => {
+      const nativeApi = await api.getAndWaitForReady();
+      const abi = flashLoanContract.nativeContract.abi;
+      await BAD.tx.setQuirk(
+        TokenQuirkBuilder.ReentrantOnReceive({
+          callee: flashLoanContract.address,
+          selector: Array.from(
+            abi.findMessage("FlashLoan::flash_loan").selector
+          ),
+          input: u8aToHex(
+            u8aConcat(
+              nativeApi
+                .createType("AccountId", flashLoanReceiver.address)
+                .toU8a(),
+              nativeApi.createType("Vec<AccountId>", [BAD.address]).toU8a(),
+              nativeApi.createType("Vec<u128>", [amount]).toU8a(),
+              nativeApi.createType("Vec<u8>", []).toU8a()
+            )
+          ),
+        })
+      );
+    };
+    const queryFlashLoanBAD = () =>
+      flashLoanContract
+        .withSigner(owner)
+        .query.flashLoan(
+          flashLoanReceiver.address,
+          [BAD.address],
+          [amount],
+          []
+        );
+    beforeEach("", async () => {
+      BAD = await deployAdversarialPSP22(deployer, "BAD", 6);
+      await BAD.tx.mint(owner.address, E12);
+      flashLoanContract = await deployFlashLoanContract(
+        owner,
+        [[BAD.address, 1000]],
+        [owner.address, BAD.address],
+        []
+      );
+      await BAD.withSigner(owner).tx.transfer(
+        flashLoanContract.address,
+        milionUSD,
+        []
+      );
+    });
+
+    it("Without a quirk the loan is repaid with the fee", async () => {
+      await expect(
+        flashLoanContract
+          .withSigner(owner)
+          .tx.flashLoan(
+            flashLoanReceiver.address,
+            [BAD.address],
+            [amount],
+            []
+          )
+      ).to.eventually.be.fulfilled;
+      expect(
+        (
+          await BAD.query.balanceOf(flashLoanContract.address)
+        ).value.toString()
+      ).to.equal((milionUSD + fee).toString());
+    });
+
+    it("BAD takes a fee on transfer, flash_loan fails with TransferAmountMismatch", async () => {
+      await BAD.tx.setQuirk(
+        TokenQuirkBuilder.FeeOnTransfer({ feeE6: 10_000 })
+      );
+      await expect(
+        queryFlashLoanBAD()
+      ).to.eventually.be.rejected.and.to.have.deep.property(
+        "_err",
+        FlashLoanErrorBuilder.TransferAmountMismatch()
+      );
+    });
+
+    it("BAD rebases on transfer, flash_loan fails with TransferAmountMismatch", async () => {
+      await BAD.tx.setQuirk(
+        TokenQuirkBuilder.Rebasing({ rebaseE6: 10_000 })
+      );
+      await expect(
+        queryFlashLoanBAD()
+      ).to.eventually.be.rejected.and.to.have.deep.property(
+        "_err",
+        FlashLoanErrorBuilder.TransferAmountMismatch()
+      );
+    });
+
+    it("BAD reports the same balance for every account, flash_loan fails with TransferAmountMismatch", async () => {
+      await BAD.tx.setQuirk(
+        TokenQuirkBuilder.LyingBalance({ reported: milionUSD })
+      );
+      await expect(
+        queryFlashLoanBAD()
+      ).to.eventually.be.rejected.and.to.have.deep.property(
+        "_err",
+        FlashLoanErrorBuilder.TransferAmountMismatch()
+      );
+    });
+
+    it("BAD transfers do not move funds, flash_loan fails with TransferAmountMismatch", async () => {
+      await BAD.tx.setQuirk(TokenQuirkBuilder.NoOpTransfer());
+      await expect(
+        queryFlashLoanBAD()
+      ).to.eventually.be.rejected.and.to.have.deep.property(
+        "_err",
+        FlashLoanErrorBuilder.TransferAmountMismatch()
+      );
+    });
+
+    it("BAD reenters flash_loan during the transfer, flash_loan fails and the lender balance is unchanged", async () => {
+      const nativeApi = await api.getAndWaitForReady();
+      const abi = flashLoanContract.nativeContract.abi;
+      await BAD.tx.setQuirk(
+        TokenQuirkBuilder.Reentrant({
+          callee: flashLoanContract.address,
+          selector: Array.from(
+            abi.findMessage("FlashLoan::flash_loan").selector
+          ),
+          input: u8aToHex(
+            u8aConcat(
+              nativeApi
+                .createType("AccountId", flashLoanReceiver.address)
+                .toU8a(),
+              nativeApi.createType("Vec<AccountId>", [BAD.address]).toU8a(),
+              nativeApi.createType("Vec<u128>", [amount]).toU8a(),
+              nativeApi.createType("Vec<u8>", []).toU8a()
+            )
+          ),
+        })
+      );
+      await expect(
+        flashLoanContract
+          .withSigner(owner)
+          .tx.flashLoan(
+            flashLoanReceiver.address,
+            [BAD.address],
+            [amount],
+            []
+          )
+      ).to.eventually.be.rejected;
+      await BAD.tx.setQuirk(null);
+      expect(
+        (
+          await BAD.query.balanceOf(flashLoanContract.address)
+        ).value.toString()
+      ).to.equal(milionUSD.toString());
+    });
+
+    it("BAD asks the lender if it is in a flash loan during the transfer, the lender answers true and the loan is repaid", async () => {
+      const abi = flashLoanContract.nativeContract.abi;
+      await BAD.tx.setQuirk(
+        TokenQuirkBuilder.Reentrant({
+          callee: flashLoanContract.address,
+          selector: Array.from(
+            abi.findMessage("FlashLoan::is_in_flash_loan").selector
+          ),
+          input: [],
+        })
+      );
+      await expect(
+        flashLoanContract
+          .withSigner(owner)
+          .tx.flashLoan(
+            flashLoanReceiver.address,
+            [BAD.address],
+            [amount],
+            []
+          )
+      ).to.eventually.be.fulfilled;
+      // bool true is encoded as 1
+      expect(
+        (await BAD.query.reentryOutput()).value!.map((byte) => Number(byte))
+      ).to.deep.equal([1]);
+      expect(
+        (await flashLoanContract.query.isInFlashLoan()).value
+      ).to.equal(false);
+    });
+
+    it("BAD reenters flash_loan while the lender pulls back the repayment, both loans are repaid and nothing is outstanding", async () => {
+      await reenterFlashLoanOnRepay();
+      await expect(
+        flashLoanContract
+          .withSigner(owner)
+          .tx.flashLoan(
+            flashLoanReceiver.address,
+            [BAD.address],
+            [amount],
+            []
+          )
+      ).to.eventually.be.fulfilled;
+      // the nested flash_loan returned Ok(()), encoded as 0
+      expect(
+        (await BAD.query.reentryOutput()).value!.map((byte) => Number(byte))
+      ).to.deep.equal([0]);
+      await BAD.tx.setQuirk(null);
+      expect(
+        (
+          await BAD.query.balanceOf(flashLoanContract.address)
+        ).value.toString()
+      ).to.equal((milionUSD + 2 * fee).toString());
+      expect(
+        (await flashLoanContract.query.isInFlashLoan()).value
+      ).to.equal(false);
+      expect(
+        (
+          await flashLoanContract.query.outstanding(BAD.address)
+        ).value.toString()
+      ).to.equal("0");
+    });
+
+    it("BAD reenters flash_loan while the lender pulls back the repayment, the loans get distinct ids, both receipts are kept and the next loan gets the next id", async () => {
+      await reenterFlashLoanOnRepay();
+      await expect(
+        flashLoanContract
+          .withSigner(owner)
+          .tx.flashLoan(
+            flashLoanReceiver.address,
+            [BAD.address],
+            [amount],
+            []
+          )
+      ).to.eventually.be.fulfilled;
+      await BAD.tx.setQuirk(null);
+      const outer = (await flashLoanContract.query.loanReceipt(1)).value!;
+      const nested = (await flashLoanContract.query.loanReceipt(2)).value!;
+      expect(outer.initiator.toString()).to.equal(owner.address);
+      expect(nested.loanId.toString()).to.equal("2");
+      expect(nested.initiator.toString()).to.equal(BAD.address);
+      await expect(
+        flashLoanContract
+          .withSigner(owner)
+          .tx.flashLoan(
+            flashLoanReceiver.address,
+            [BAD.address],
+            [amount],
+            []
+          )
+      ).to.eventually.be.fulfilled;
+      expect(
+        (
+          await flashLoanContract.query.loanReceipt(2)
+        ).value!.initiator.toString()
+      ).to.equal(BAD.address);
+      expect(
+        (
+          await flashLoanContract.query.loanReceipt(3)
+        ).value!.initiator.toString()
+      ).to.equal(owner.address);
+    });
+
+    it("BAD reports a lower balance of the lender between loans, anyone checks it and BAD is paused as the drop reaches the threshold, flash_loan is rejected by the Pause policy until owner unpauses BAD", async () => {
+      await flashLoanContract
+        .withSigner(owner)
+        .tx.setAnomalyThreshold(BAD.address, amount);
+      await flashLoanContract
+        .withSigner(owner)
+        .tx.flashLoan(flashLoanReceiver.address, [BAD.address], [amount], []);
+      await BAD.tx.setQuirk(
+        TokenQuirkBuilder.LyingBalance({ reported: milionUSD - amount })
+      );
+      await expect(
+        flashLoanContract.withSigner(random).tx.checkAsset(BAD.address)
+      ).to.eventually.be.fulfilled;
+      await BAD.tx.setQuirk(null);
+      expect(
+        (await flashLoanContract.query.anomalies(BAD.address)).value.toString()
+      ).to.equal((amount + fee).toString());
+      expect(
+        (await flashLoanContract.query.isAssetPaused(BAD.address)).value
+      ).to.equal(true);
+      expect(
+        (
+          await flashLoanContract.query.maxFlashLoan(BAD.address)
+        ).value.toString()
+      ).to.equal("0");
+      await expect(
+        queryFlashLoanBAD()
+      ).to.eventually.be.rejected.and.to.have.deep.property(
+        "_err",
+        FlashLoanErrorBuilder.PolicyRejected(PolicyModule.pause)
+      );
+
+      await flashLoanContract.withSigner(owner).tx.unpauseAsset(BAD.address);
+      expect(
+        (await flashLoanContract.query.anomalies(BAD.address)).value.toString()
+      ).to.equal("0");
+      await expect(queryFlashLoanBAD()).to.eventually.be.fulfilled;
+    });
+
+    it("BAD reports a lower balance of the lender between loans and nobody checks it, the next flash_loan finds the drop, pauses BAD and lends nothing, later loans are rejected by the Pause policy", async () => {
+      await flashLoanContract
+        .withSigner(owner)
+        .tx.setAnomalyThreshold(BAD.address, amount);
+      await flashLoanContract
+        .withSigner(owner)
+        .tx.flashLoan(flashLoanReceiver.address, [BAD.address], [amount], []);
+      await BAD.tx.setQuirk(
+        TokenQuirkBuilder.LyingBalance({ reported: milionUSD - amount })
+      );
+      await expect(
+        flashLoanContract
+          .withSigner(owner)
+          .tx.flashLoan(flashLoanReceiver.address, [BAD.address], [amount], [])
+      ).to.eventually.be.fulfilled;
+      expect(
+        (await flashLoanContract.query.isAssetPaused(BAD.address)).value
+      ).to.equal(true);
+      expect(
+        (await flashLoanContract.query.anomalies(BAD.address)).value.toString()
+      ).to.equal((amount + fee).toString());
+      expect((await flashLoanContract.query.loanReceipt(2)).value).to.be.null;
+      await expect(
+        queryFlashLoanBAD()
+      ).to.eventually.be.rejected.and.to.have.deep.property(
+        "_err",
+        FlashLoanErrorBuilder.PolicyRejected(PolicyModule.pause)
+      );
+      await BAD.tx.setQuirk(null);
+      expect(
+        (
+          await BAD.query.balanceOf(flashLoanContract.address)
+        ).value.toString()
+      ).to.equal((milionUSD + fee).toString());
+    });
+
+    it("BAD reports a lower balance of the lender below the threshold, the anomaly is counted but BAD is not paused", async () => {
+      await flashLoanContract
+        .withSigner(owner)
+        .tx.setAnomalyThreshold(BAD.address, 2 * amount);
+      await flashLoanContract.withSigner(random).tx.checkAsset(BAD.address);
+      await BAD.tx.setQuirk(
+        TokenQuirkBuilder.LyingBalance({ reported: milionUSD - amount })
+      );
+      await flashLoanContract.withSigner(random).tx.checkAsset(BAD.address);
+      await BAD.tx.setQuirk(null);
+      expect(
+        (await flashLoanContract.query.anomalies(BAD.address)).value.toString()
+      ).to.equal(amount.toString());
+      expect(
+        (await flashLoanContract.query.isAssetPaused(BAD.address)).value
+      ).to.equal(false);
+      await expect(queryFlashLoanBAD()).to.eventually.be.fulfilled;
+    });
+
+    it("User tries to set the anomaly threshold and pause BAD, transaction fails as he is not the owner", async () => {
+      await expect(
+        flashLoanContract
+          .withSigner(user)
+          .query.setAnomalyThreshold(BAD.address, 0)
+      ).to.eventually.be.rejected.and.to.have.deep.property(
+        "_err",
+        CircuitBreakerErrorBuilder.OwnableError(
+          OwnableErrorBuilder.CallerIsNotOwner()
+        )
+      );
+      await expect(
+        flashLoanContract.withSigner(user).query.pauseAsset(BAD.address)
+      ).to.eventually.be.rejected.and.to.have.deep.property(
+        "_err",
+        CircuitBreakerErrorBuilder.OwnableError(
+          OwnableErrorBuilder.CallerIsNotOwner()
+        )
+      );
+    });
+  });
+
+  describe("Owner deploys FlashLoanContract with allowed list = [user] and fees = [[USDC, 1_000 (0.1%)]], and transfers 1milion of USDC and USDT to it. Then...", () => {
+    const config = (fee: number, maxLoan: number | null = null) => ({
+      enabled: true,
+      feeModel: FeeModelBuilder.Flat({ fee }),
+      maxLoan,
+      quirkPolicy: TokenQuirkPolicy.reject,
+    });
+    const queryFlashLoan = (asset: string, amount: number) =>
+      flashLoanContract
+        .withSigner(user)
+        .query.flashLoan(flashLoanReceiver.address, [asset], [amount], []);
+    beforeEach("", async () => {
+      flashLoanContract = await deployFlashLoanContract(
+        owner,
+        [[USDC.address, 1000]],
+        [user.address],
+        []
+      );
+      await USDC.withSigner(owner).tx.transfer(
+        flashLoanContract.address,
+        milionUSD,
+        []
+      );
+      await USDT.withSigner(owner).tx.transfer(
+        flashLoanContract.address,
+        milionUSD,
+        []
+      );
+    });
+
+    it("USDC is listed with decimals read from the token and its liquidity is returned", async () => {
+      const info = (await flashLoanContract.query.assetInfo(USDC.address))
+        .value!;
+      expect(info.decimals).to.equal(6);
+      expect(info.config.enabled).to.equal(true);
+      const supported = (await flashLoanContract.query.supportedAssets(0, 10))
+        .value;
+      expect(supported.length).to.equal(1);
+      expect(supported[0][0].toString()).to.equal(USDC.address);
+      expect(supported[0][2].toString()).to.equal(milionUSD.toString());
+    });
+
+    it("User call flash_loan for unlisted USDT, transaction fails with AssetNotSupported", async () => {
+      await expect(
+        queryFlashLoan(USDT.address, oneUSD)
+      ).to.eventually.be.rejected.and.to.have.deep.property(
+        "_err",
+        FlashLoanErrorBuilder.AssetNotSupported()
+      );
+      expect(
+        (
+          await flashLoanContract.query.maxFlashLoan(USDT.address)
+        ).value.toString()
+      ).to.equal("0");
+    });
+
+    it("User tries to list USDT, transaction fails as he is not the owner", async () => {
+      await expect(
+        flashLoanContract
+          .withSigner(user)
+          .query.listAsset(USDT.address, config(oneUSD))
+      ).to.eventually.be.rejected.and.to.have.deep.property(
+        "_err",
+        AssetRegistryErrorBuilder.OwnableError(
+          OwnableErrorBuilder.CallerIsNotOwner()
+        )
+      );
+    });
+
+    it("Owner lists USDT with flat fee 1 USDT and cap 1_000 USDT, user borrows up to the cap paying the flat fee", async () => {
+      await expect(
+        flashLoanContract
+          .withSigner(owner)
+          .tx.listAsset(USDT.address, config(oneUSD, 1_000 * oneUSD))
+      ).to.eventually.be.fulfilled;
+      expect(
+        (await flashLoanContract.query.supportedAssets(1, 10)).value.map(
+          ([asset]) => asset.toString()
+        )
+      ).to.deep.equal([USDT.address]);
+      expect(
+        (
+          await flashLoanContract.query.maxFlashLoan(USDT.address)
+        ).value.toString()
+      ).to.equal((1_000 * oneUSD).toString());
+      await expect(
+        queryFlashLoan(USDT.address, 1_001 * oneUSD)
+      ).to.eventually.be.rejected.and.to.have.deep.property(
+        "_err",
+        FlashLoanErrorBuilder.PolicyRejected(PolicyModule.caps)
+      );
+      await expect(
+        flashLoanContract
+          .withSigner(user)
+          .tx.flashLoan(
+            flashLoanReceiver.address,
+            [USDT.address],
+            [1_000 * oneUSD],
+            []
+          )
+      ).to.eventually.be.fulfilled;
+      expect(
+        (await USDT.query.balanceOf(flashLoanContract.address)).value.toString()
+      ).to.equal((milionUSD + oneUSD).toString());
+    });
+
+    it("Owner disables and then delists USDC, user call flash_loan for USDC and fails with AssetNotSupported", async () => {
+      await flashLoanContract
+        .withSigner(owner)
+        .tx.listAsset(USDC.address, { ...config(0), enabled: false });
+      await expect(
+        queryFlashLoan(USDC.address, oneUSD)
+      ).to.eventually.be.rejected.and.to.have.deep.property(
+        "_err",
+        FlashLoanErrorBuilder.AssetNotSupported()
+      );
+      await expect(
+        flashLoanContract.withSigner(owner).tx.delistAsset(USDC.address)
+      ).to.eventually.be.fulfilled;
+      expect((await flashLoanContract.query.assetInfo(USDC.address)).value).to
+        .be.null;
+      expect(
+        (await flashLoanContract.query.supportedAssets(0, 10)).value.length
+      ).to.equal(0);
+      await expect(
+        flashLoanContract.withSigner(owner).query.delistAsset(USDC.address)
+      ).to.eventually.be.rejected.and.to.have.deep.property(
+        "_err",
+        AssetRegistryErrorBuilder.AssetNotListed()
+      );
+    });
+
+    it("Owner lists fee-on-transfer BAD with Account quirk policy, user borrows it, the lender gets back the loan with the fee and the fee that arrived is earned, recorded in the receipt and in stats", async () => {
+      const BAD = await deployAdversarialPSP22(deployer, "BAD", 6);
+      await BAD.tx.mint(owner.address, E12);
+      await BAD.withSigner(owner).tx.transfer(
+        flashLoanContract.address,
+        milionUSD,
+        []
+      );
+      await flashLoanContract.withSigner(owner).tx.listAsset(BAD.address, {
+        enabled: true,
+        feeModel: FeeModelBuilder.Proportional({ feeE6: 1000 }),
+        maxLoan: null,
+        quirkPolicy: TokenQuirkPolicy.account,
+      });
+      await BAD.tx.setQuirk(TokenQuirkBuilder.FeeOnTransfer({ feeE6: 10_000 }));
+      await expect(
+        flashLoanContract
+          .withSigner(user)
+          .tx.flashLoan(
+            flashLoanReceiver.address,
+            [BAD.address],
+            [1_000 * oneUSD],
+            []
+          )
+      ).to.eventually.be.fulfilled;
+      // the receiver gets 990 BAD and repays 1_011.111112 BAD,
+      // 1_001.000001 BAD of it reaches the lender
+      expect(
+        (await BAD.query.balanceOf(flashLoanContract.address)).value.toString()
+      ).to.equal((milionUSD + oneUSD + 1).toString());
+      const earned = (await flashLoanContract.query.listEarned(0, 10)).value;
+      expect(earned.length).to.equal(1);
+      expect(earned[0][1].toString()).to.equal((oneUSD + 1).toString());
+      const receipt = (await flashLoanContract.query.loanReceipt(1)).value!;
+      expect(receipt.fees[0].toString()).to.equal((oneUSD + 1).toString());
+      expect(
+        (
+          await flashLoanContract.query.assetStats(BAD.address)
+        ).value.fees.toString()
+      ).to.equal((oneUSD + 1).toString());
+    });
+
+    it("Owner lists fee-on-transfer BAD with Account quirk policy and deposits it, user borrows it and only the fee that reached the lender is earned", async () => {
+      const BAD = await deployAdversarialPSP22(deployer, "BAD", 6);
+      await BAD.tx.mint(owner.address, E12);
+      await flashLoanContract.withSigner(owner).tx.listAsset(BAD.address, {
+        enabled: true,
+        feeModel: FeeModelBuilder.Proportional({ feeE6: 1000 }),
+        maxLoan: null,
+        quirkPolicy: TokenQuirkPolicy.account,
+      });
+      await BAD.withSigner(owner).tx.approve(
+        flashLoanContract.address,
+        milionUSD
+      );
+      await flashLoanContract
+        .withSigner(owner)
+        .tx.deposit(BAD.address, milionUSD);
+      await BAD.tx.setQuirk(TokenQuirkBuilder.FeeOnTransfer({ feeE6: 10_000 }));
+      await expect(
+        flashLoanContract
+          .withSigner(user)
+          .tx.flashLoan(
+            flashLoanReceiver.address,
+            [BAD.address],
+            [1_000 * oneUSD],
+            []
+          )
+      ).to.eventually.be.fulfilled;
+      // the receiver is told to repay a fee of 21.111112 BAD, 1.000001 BAD of it reaches the lender
+      const earned = (await flashLoanContract.query.listEarned(0, 10)).value;
+      expect(earned.length).to.equal(1);
+      expect(earned[0][1].toString()).to.equal((oneUSD + 1).toString());
+      const reconciliation = (
+        await flashLoanContract.query.reconcile([BAD.address])
+      ).value[0];
+      expect(reconciliation.excess.toString()).to.equal("0");
+      expect(reconciliation.shortfall.toString()).to.equal("0");
+    });
+  });
+
+  describe("Owner deploys FlashLoanContract with allowed list = [user, owner], free list = [random] and USDC fee 1_000 (0.1%), and transfers 1milion of USDC to it. Then...", () => {
+    const toStrings = (accounts: { toString(): string }[]) =>
+      accounts.map((account) => account.toString());
+    beforeEach("", async () => {
+      flashLoanContract = await deployFlashLoanContract(
+        owner,
+        [[USDC.address, 1000]],
+        [user.address, owner.address],
+        [random.address]
+      );
+      await USDC.withSigner(owner).tx.transfer(
+        flashLoanContract.address,
+        milionUSD,
+        []
+      );
+    });
+
+    it("Allowlisted and free accounts are listed page by page", async () => {
+      expect(
+        toStrings((await flashLoanContract.query.listAllowlisted(0, 10)).value)
+      ).to.deep.equal([user.address, owner.address, random.address]);
+      expect(
+        toStrings((await flashLoanContract.query.listAllowlisted(1, 1)).value)
+      ).to.deep.equal([owner.address]);
+      expect(
+        toStrings((await flashLoanContract.query.listAllowlisted(3, 10)).value)
+      ).to.deep.equal([]);
+      expect(
+        toStrings((await flashLoanContract.query.listFree(0, 10)).value)
+      ).to.deep.equal([random.address]);
+    });
+
+    it("Owner deploys another FlashLoanContract with free list = [flashLoanReceiver], user borrows 1_000 USDC from both and only the lender without free receivers earns the fee", async () => {
+      const freeLender = await deployFlashLoanContract(
+        owner,
+        [[USDC.address, 1000]],
+        [user.address],
+        [flashLoanReceiver.address]
+      );
+      await USDC.tx.mint(freeLender.address, milionUSD);
+      // the quote does not depend on the receiver
+      expect(
+        (
+          await freeLender.query.quoteFees([USDC.address], [1000 * oneUSD])
+        ).value.ok![0].toString()
+      ).to.equal(oneUSD.toString());
+
+      await freeLender
+        .withSigner(user)
+        .tx.flashLoan(
+          flashLoanReceiver.address,
+          [USDC.address],
+          [1000 * oneUSD],
+          []
+        );
+      await flashLoanContract
+        .withSigner(user)
+        .tx.flashLoan(
+          flashLoanReceiver.address,
+          [USDC.address],
+          [1000 * oneUSD],
+          []
+        );
+
+      expect(
+        (await USDC.query.balanceOf(freeLender.address)).value.toString()
+      ).to.equal(milionUSD.toString());
+      expect(
+        (await freeLender.query.loanReceipt(1)).value!.fees[0].toString()
+      ).to.equal("0");
+      expect(
+        (await USDC.query.balanceOf(flashLoanContract.address)).value.toString()
+      ).to.equal((milionUSD + oneUSD).toString());
+    });
+
+    it("The policy chain is [Pause, Allowlist, Caps], owner sets caps and credentials and they are listed page by page", async () => {
+      expect((await flashLoanContract.query.policies()).value).to.deep.equal([
+        PolicyModule.pause,
+        PolicyModule.allowlist,
+        PolicyModule.caps,
+      ]);
+      await flashLoanContract
+        .withSigner(owner)
+        .tx.setCap(USDC.address, 1_000 * oneUSD);
+      await flashLoanContract
+        .withSigner(owner)
+        .tx.setCredential(user.address, 100);
+      await flashLoanContract
+        .withSigner(owner)
+        .tx.setCredential(owner.address, 200);
+      expect(
+        (await flashLoanContract.query.listCaps(0, 10)).value.map(
+          ([asset, cap]) => [asset.toString(), cap.toString()]
+        )
+      ).to.deep.equal([[USDC.address, (1_000 * oneUSD).toString()]]);
+      expect(
+        (await flashLoanContract.query.listCredentials(1, 10)).value.map(
+          ([account, expiry]) => [account.toString(), expiry.toString()]
+        )
+      ).to.deep.equal([[owner.address, "200"]]);
+      await flashLoanContract
+        .withSigner(owner)
+        .tx.setCredential(user.address, null);
+      expect(
+        (await flashLoanContract.query.listCredentials(0, 10)).value.map(
+          ([account]) => account.toString()
+        )
+      ).to.deep.equal([owner.address]);
+    });
+
+    it("User takes a flash loan of 1_000 USDC, the earned fee is listed", async () => {
+      expect(
+        (await flashLoanContract.query.listEarned(0, 10)).value.length
+      ).to.equal(0);
+      await flashLoanContract
+        .withSigner(user)
+        .tx.flashLoan(
+          flashLoanReceiver.address,
+          [USDC.address],
+          [1_000 * oneUSD],
+          []
+        );
+      const earned = (await flashLoanContract.query.listEarned(0, 10)).value;
+      expect(earned.length).to.equal(1);
+      expect(earned[0][0].toString()).to.equal(USDC.address);
+      expect(earned[0][1].toString()).to.equal(oneUSD.toString());
+    });
+
+    it("User and owner take flash loans of 1_000 USDC, the asset stats and counters are updated", async () => {
+      const flashLoanUSDC = (signer: KeyringPair) =>
+        flashLoanContract
+          .withSigner(signer)
+          .tx.flashLoan(
+            flashLoanReceiver.address,
+            [USDC.address],
+            [1_000 * oneUSD],
+            []
+          );
+      await flashLoanUSDC(user);
+      await flashLoanUSDC(user);
+      await flashLoanUSDC(owner);
+      const stats = (await flashLoanContract.query.assetStats(USDC.address))
+        .value;
+      expect(stats.volume.toString()).to.equal((3_000 * oneUSD).toString());
+      expect(stats.fees.toString()).to.equal((3 * oneUSD).toString());
+      expect(stats.loans.toString()).to.equal("3");
+      expect(
+        (
+          await flashLoanContract.query.callerLoans(user.address)
+        ).value.toString()
+      ).to.equal("2");
+      expect(
+        (
+          await flashLoanContract.query.callerLoans(owner.address)
+        ).value.toString()
+      ).to.equal("1");
+      expect(
+        (
+          await flashLoanContract.query.receiverLoans(flashLoanReceiver.address)
+        ).value.toString()
+      ).to.equal("3");
+      const snapshots = (
+        await flashLoanContract.query.epochSnapshots(USDC.address)
+      ).value;
+      expect(snapshots.length).to.equal(1);
+      expect(snapshots[0].stats.loans.toString()).to.equal("3");
+    });
+
+    it("Owner sets epoch length to 1 block, every loan starts a new epoch snapshot", async () => {
+      await expect(
+        flashLoanContract.withSigner(owner).query.setEpochLength(0)
+      ).to.eventually.be.rejected.and.to.have.deep.property(
+        "_err",
+        LoanStatsErrorBuilder.InvalidEpochLength()
+      );
+      await flashLoanContract.withSigner(owner).tx.setEpochLength(1);
+      expect((await flashLoanContract.query.epochLength()).value).to.equal(1);
+      for (const amount of [1_000 * oneUSD, 2_000 * oneUSD]) {
+        await flashLoanContract
+          .withSigner(user)
+          .tx.flashLoan(
+            flashLoanReceiver.address,
+            [USDC.address],
+            [amount],
+            []
+          );
+      }
+      const snapshots = (
+        await flashLoanContract.query.epochSnapshots(USDC.address)
+      ).value;
+      expect(snapshots.length).to.equal(2);
+      expect(snapshots[0].stats.volume.toString()).to.equal(
+        (2_000 * oneUSD).toString()
+      );
+      expect(snapshots[1].stats.volume.toString()).to.equal(
+        (1_000 * oneUSD).toString()
+      );
+      expect(snapshots[0].startBlock).to.be.greaterThan(
+        snapshots[1].startBlock
+      );
+    });
+
+    it("User tries to set epoch length, transaction fails as he is not the owner", async () => {
+      await expect(
+        flashLoanContract.withSigner(user).query.setEpochLength(100)
+      ).to.eventually.be.rejected.and.to.have.deep.property(
+        "_err",
+        LoanStatsErrorBuilder.OwnableError(
+          OwnableErrorBuilder.CallerIsNotOwner()
+        )
+      );
+    });
+
+    it("User and owner take flash loans of 1_000 USDC, the receipts are fetched by loan id", async () => {
+      const data = [1, 2, 3];
+      await flashLoanContract
+        .withSigner(user)
+        .tx.flashLoan(
+          flashLoanReceiver.address,
+          [USDC.address],
+          [1_000 * oneUSD],
+          data
+        );
+      await flashLoanContract
+        .withSigner(owner)
+        .tx.flashLoan(
+          flashLoanReceiver.address,
+          [USDC.address],
+          [2_000 * oneUSD],
+          []
+        );
+      const first = (await flashLoanContract.query.loanReceipt(1)).value!;
+      expect(first.loanId.toString()).to.equal("1");
+      expect(first.initiator.toString()).to.equal(user.address);
+      expect(first.receiver.toString()).to.equal(flashLoanReceiver.address);
+      expect(first.assets.map((asset) => asset.toString())).to.deep.equal([
+        USDC.address,
+      ]);
+      expect(first.amounts[0].toString()).to.equal((1_000 * oneUSD).toString());
+      expect(first.fees[0].toString()).to.equal(oneUSD.toString());
+      expect(first.dataHash.toString()).to.equal(
+        blake2AsHex(new Uint8Array(data), 256)
+      );
+      const second = (await flashLoanContract.query.loanReceipt(2)).value!;
+      expect(second.initiator.toString()).to.equal(owner.address);
+      expect(second.block).to.be.greaterThan(first.block);
+      expect((await flashLoanContract.query.loanReceipt(3)).value).to.be.null;
+    });
+  });
+
+  describe("Owner deploys FlashLoanContract with allowed list = [user] and USDC fee 1_000 (0.1%), deposits 1milion of USDC to it and sets random as the treasury. Then...", () => {
+    const reconcileUSDC = async () =>
+      (await flashLoanContract.query.reconcile([USDC.address])).value[0];
+    beforeEach("", async () => {
+      flashLoanContract = await deployFlashLoanContract(
+        owner,
+        [[USDC.address, 1000]],
+        [user.address],
+        []
+      );
+      await USDC.withSigner(owner).tx.approve(
+        flashLoanContract.address,
+        milionUSD
+      );
+      await flashLoanContract
+        .withSigner(owner)
+        .tx.deposit(USDC.address, milionUSD);
+      await flashLoanContract.withSigner(owner).tx.setTreasury(random.address);
+    });
+
+    it("User takes a flash loan of 1_000 USDC, the fee is tracked and owner withdraws it before the principal", async () => {
+      await flashLoanContract
+        .withSigner(user)
+        .tx.flashLoan(
+          flashLoanReceiver.address,
+          [USDC.address],
+          [1_000 * oneUSD],
+          []
+        );
+      let reconciliation = await reconcileUSDC();
+      expect(reconciliation.balance.toString()).to.equal(
+        (milionUSD + oneUSD).toString()
+      );
+      expect(reconciliation.principal.toString()).to.equal(
+        milionUSD.toString()
+      );
+      expect(reconciliation.fees.toString()).to.equal(oneUSD.toString());
+      expect(reconciliation.excess.toString()).to.equal("0");
+
+      await flashLoanContract
+        .withSigner(owner)
+        .tx.withdraw(USDC.address, owner.address, 2 * oneUSD);
+      reconciliation = await reconcileUSDC();
+      expect(reconciliation.principal.toString()).to.equal(
+        (milionUSD - oneUSD).toString()
+      );
+      expect(reconciliation.fees.toString()).to.equal("0");
+      expect(reconciliation.shortfall.toString()).to.equal("0");
+    });
+
+    it("Owner transfers 500 USDC directly to the lender, it is reported as excess and skimmed to the treasury", async () => {
+      await USDC.withSigner(owner).tx.transfer(
+        flashLoanContract.address,
+        500 * oneUSD,
+        []
+      );
+      expect((await reconcileUSDC()).excess.toString()).to.equal(
+        (500 * oneUSD).toString()
+      );
+      const treasuryBalance = (
+        await USDC.query.balanceOf(random.address)
+      ).value.toString();
+      await expect(
+        flashLoanContract.withSigner(owner).tx.skim(USDC.address)
+      ).to.eventually.be.fulfilled;
+      expect(
+        (await USDC.query.balanceOf(random.address)).value.toString()
+      ).to.equal(new BN(treasuryBalance).addn(500 * oneUSD).toString());
+      const reconciliation = await reconcileUSDC();
+      expect(reconciliation.excess.toString()).to.equal("0");
+      expect(reconciliation.balance.toString()).to.equal(
+        milionUSD.toString()
+      );
+    });
+
+    it("Owner transfers 500 USDT directly to the lender, skim of USDT fails with PrincipalNotRecorded as USDT was never deposited", async () => {
+      await USDT.withSigner(owner).tx.transfer(
+        flashLoanContract.address,
+        500 * oneUSD,
+        []
+      );
+      await expect(
+        flashLoanContract.withSigner(owner).query.skim(USDT.address)
+      ).to.eventually.be.rejected.and.to.have.deep.property(
+        "_err",
+        ReconciliationErrorBuilder.PrincipalNotRecorded()
+      );
+    });
+
+    it("Owner skims USDC without excess, nothing is transferred", async () => {
+      const treasuryBalance = (
+        await USDC.query.balanceOf(random.address)
+      ).value.toString();
+      await expect(
+        flashLoanContract.withSigner(owner).tx.skim(USDC.address)
+      ).to.eventually.be.fulfilled;
+      expect(
+        (await USDC.query.balanceOf(random.address)).value.toString()
+      ).to.equal(treasuryBalance);
+    });
+
+    it("Owner withdraws USDC without an amount, the whole balance of the lender is withdrawn", async () => {
+      const ownerBalance = (
+        await USDC.query.balanceOf(owner.address)
+      ).value.toString();
+      await expect(
+        flashLoanContract
+          .withSigner(owner)
+          .tx.withdraw(USDC.address, owner.address, null)
+      ).to.eventually.be.fulfilled;
+      expect(
+        (
+          await USDC.query.balanceOf(flashLoanContract.address)
+        ).value.toString()
+      ).to.equal("0");
+      expect(
+        (await USDC.query.balanceOf(owner.address)).value.toString()
+      ).to.equal(new BN(ownerBalance).add(new BN(milionUSD)).toString());
+    });
+
+    it("User tries to skim USDC, transaction fails as he is not the owner", async () => {
+      await expect(
+        flashLoanContract.withSigner(user).query.skim(USDC.address)
+      ).to.eventually.be.rejected.and.to.have.deep.property(
+        "_err",
+        ReconciliationErrorBuilder.OwnableError(
+          OwnableErrorBuilder.CallerIsNotOwner()
+        )
+      );
+    });
+
+    it("USDT sent to the lender by mistake is rescued by owner, rescuing listed USDC fails with NotRescuable", async () => {
+      await USDT.withSigner(owner).tx.transfer(
+        flashLoanContract.address,
+        oneUSD,
+        []
+      );
+      await expect(
+        flashLoanContract
+          .withSigner(owner)
+          .tx.rescue(USDT.address, user.address, oneUSD)
+      ).to.eventually.be.fulfilled;
+      expect(
+        (
+          await USDT.query.balanceOf(flashLoanContract.address)
+        ).value.toString()
+      ).to.equal("0");
+      await expect(
+        flashLoanContract
+          .withSigner(owner)
+          .query.rescue(USDC.address, owner.address, oneUSD)
+      ).to.eventually.be.rejected.and.to.have.deep.property(
+        "_err",
+        ReconciliationErrorBuilder.NotRescuable()
+      );
+    });
+  });
+
+  describe("Owner deploys FlashLoanContract with allowed list = [user] and USDC fee 1_000 (0.1%), transfers 1milion of USDC to it and sets the policy chain to [Pause, Credential, RateLimit]. Then...", () => {
+    const queryFlashLoan = (amount: number) =>
+      flashLoanContract
+        .withSigner(user)
+        .query.flashLoan(
+          flashLoanReceiver.address,
+          [USDC.address],
+          [amount],
+          []
+        );
+    beforeEach("", async () => {
+      flashLoanContract = await deployFlashLoanContract(
+        owner,
+        [[USDC.address, 1000]],
+        [user.address],
+        []
+      );
+      await USDC.withSigner(owner).tx.transfer(
+        flashLoanContract.address,
+        milionUSD,
+        []
+      );
+      await flashLoanContract
+        .withSigner(owner)
+        .tx.setPolicies([
+          PolicyModule.pause,
+          PolicyModule.credential,
+          PolicyModule.rateLimit,
+        ]);
+    });
+
+    it("Owner pauses loans, user call flash_loan and the chain stops at Pause before checking the credential", async () => {
+      await flashLoanContract.withSigner(owner).tx.setLoansPaused(true);
+      await expect(
+        queryFlashLoan(1_000 * oneUSD)
+      ).to.eventually.be.rejected.and.to.have.deep.property(
+        "_err",
+        FlashLoanErrorBuilder.PolicyRejected(PolicyModule.pause)
+      );
+      await flashLoanContract.withSigner(owner).tx.setLoansPaused(false);
+      await expect(
+        queryFlashLoan(1_000 * oneUSD)
+      ).to.eventually.be.rejected.and.to.have.deep.property(
+        "_err",
+        FlashLoanErrorBuilder.PolicyRejected(PolicyModule.credential)
+      );
+    });
+
+    it("Owner gives user a credential and a rate limit of 1_500 USDC, user borrows 1_000 USDC and the next 1_000 USDC exceed the limit", async () => {
+      await flashLoanContract
+        .withSigner(owner)
+        .tx.setCredential(user.address, 1_000_000_000);
+      await flashLoanContract
+        .withSigner(owner)
+        .tx.setRateLimit(USDC.address, {
+          amount: 1_500 * oneUSD,
+          window: 1_000_000_000,
+        });
+      await expect(
+        flashLoanContract
+          .withSigner(user)
+          .tx.flashLoan(
+            flashLoanReceiver.address,
+            [USDC.address],
+            [1_000 * oneUSD],
+            []
+          )
+      ).to.eventually.be.fulfilled;
+      expect(
+        (
+          await flashLoanContract.query.rateLimitUsed(
+            user.address,
+            USDC.address
+          )
+        ).value.toString()
+      ).to.equal((1_000 * oneUSD).toString());
+      await expect(
+        queryFlashLoan(1_000 * oneUSD)
+      ).to.eventually.be.rejected.and.to.have.deep.property(
+        "_err",
+        FlashLoanErrorBuilder.PolicyRejected(PolicyModule.rateLimit)
+      );
+      await expect(queryFlashLoan(500 * oneUSD)).to.eventually.be.fulfilled;
+    });
+
+    it("User tries to clear the policy chain, transaction fails as he is not the owner", async () => {
+      await expect(
+        flashLoanContract.withSigner(user).query.setPolicies([])
+      ).to.eventually.be.rejected.and.to.have.deep.property(
+        "_err",
+        PolicyChainErrorBuilder.OwnableError(
+          OwnableErrorBuilder.CallerIsNotOwner()
+        )
+      );
+      expect(
+        (await flashLoanContract.query.policies()).value.length
+      ).to.equal(3);
+    });
+  });
+
+  describe("Owner deploys FlashLoanContract with allowed list = [user] and USDC fee 1_000 (0.1%), transfers 1milion of USDC to it, deploys PolicyMock and sets the policy chain to [External]. Then...", () => {
+    let policyMock: PolicyMock;
+    const queryFlashLoan = () =>
+      flashLoanContract
+        .withSigner(user)
+        .query.flashLoan(
+          flashLoanReceiver.address,
+          [USDC.address],
+          [1_000 * oneUSD],
+          []
+        );
+    beforeEach("", async () => {
+      flashLoanContract = await deployFlashLoanContract(
+        owner,
+        [[USDC.address, 1000]],
+        [user.address],
+        []
+      );
+      await USDC.withSigner(owner).tx.transfer(
+        flashLoanContract.address,
+        milionUSD,
+        []
+      );
+      policyMock = await deployPolicyMock(owner);
+      await flashLoanContract
+        .withSigner(owner)
+        .tx.setPolicies([PolicyModule.external]);
+    });
+
+    it("The policy contract is not set, user call flash_loan and fails with PolicyRejected", async () => {
+      await expect(
+        queryFlashLoan()
+      ).to.eventually.be.rejected.and.to.have.deep.property(
+        "_err",
+        FlashLoanErrorBuilder.PolicyRejected(PolicyModule.external)
+      );
+    });
+
+    it("Owner sets the policy contract, user borrows while it allows and fails once it denies him", async () => {
+      await flashLoanContract
+        .withSigner(owner)
+        .tx.setPolicyContract(policyMock.address, 0);
+      expect(
+        (await flashLoanContract.query.policyContract()).value![0].toString()
+      ).to.equal(policyMock.address);
+      await expect(
+        flashLoanContract
+          .withSigner(user)
+          .tx.flashLoan(
+            flashLoanReceiver.address,
+            [USDC.address],
+            [1_000 * oneUSD],
+            []
+          )
+      ).to.eventually.be.fulfilled;
+      await policyMock.tx.setDenied(user.address, true);
+      await expect(
+        queryFlashLoan()
+      ).to.eventually.be.rejected.and.to.have.deep.property(
+        "_err",
+        FlashLoanErrorBuilder.PolicyContractDenied()
+      );
+    });
+
+    it("The policy contract fails, traps or runs out of the gas limit, user call flash_loan and fails with typed errors", async () => {
+      await flashLoanContract
+        .withSigner(owner)
+        .tx.setPolicyContract(policyMock.address, 10_000_000_000);
+      await policyMock.tx.setAnswer(PolicyAnswer.fail);
+      await expect(
+        queryFlashLoan()
+      ).to.eventually.be.rejected.and.to.have.deep.property(
+        "_err",
+        FlashLoanErrorBuilder.PolicyContractError(
+          PolicyContractErrorBuilder.Custom("Failed")
+        )
+      );
+      await policyMock.tx.setAnswer(PolicyAnswer.trap);
+      await expect(
+        queryFlashLoan()
+      ).to.eventually.be.rejected.and.to.have.deep.property(
+        "_err",
+        FlashLoanErrorBuilder.PolicyContractTrapped()
+      );
+      await policyMock.tx.setAnswer(PolicyAnswer.burnGas);
+      await expect(
+        queryFlashLoan()
+      ).to.eventually.be.rejected.and.to.have.deep.property(
+        "_err",
+        FlashLoanErrorBuilder.PolicyContractTrapped()
+      );
+    });
+
+    it("Owner sets a policy contract address without a contract, user call flash_loan and fails with PolicyContractCallFailed", async () => {
+      await flashLoanContract
+        .withSigner(owner)
+        .tx.setPolicyContract(random.address, 0);
+      await expect(
+        queryFlashLoan()
+      ).to.eventually.be.rejected.and.to.have.deep.property(
+        "_err",
+        FlashLoanErrorBuilder.PolicyContractCallFailed()
+      );
+    });
+
+    it("User tries to set the policy contract, transaction fails as he is not the owner", async () => {
+      await expect(
+        flashLoanContract
+          .withSigner(user)
+          .query.setPolicyContract(policyMock.address, 0)
+      ).to.eventually.be.rejected.and.to.have.deep.property(
+        "_err",
+        PolicyChainErrorBuilder.OwnableError(
+          OwnableErrorBuilder.CallerIsNotOwner()
+        )
+      );
+    });
+  });
+
+  describe("Owner deploys FlashLoanContract with allowed list = [user] and USDC fee 1_000 (0.1%), transfers 1milion of USDC to it, deploys FeeOracleMock quoting 2_000 (0.2%) and sets it as the fee oracle with cap 10_000 (1%) and flat fallback fee 5 USDC. Then...", () => {
+    let feeOracleMock: FeeOracleMock;
+    // the lender is deployed for each test, so the loan gets id 1
+    const borrowedFee = async () => {
+      await flashLoanContract
+        .withSigner(user)
+        .tx.flashLoan(
+          flashLoanReceiver.address,
+          [USDC.address],
+          [1_000 * oneUSD],
+          []
+        );
+      return (
+        await flashLoanContract.query.loanReceipt(1)
+      ).value!.fees[0].toString();
+    };
+    beforeEach("", async () => {
+      flashLoanContract = await deployFlashLoanContract(
+        owner,
+        [[USDC.address, 1000]],
+        [user.address],
+        []
+      );
+      await USDC.withSigner(owner).tx.transfer(
+        flashLoanContract.address,
+        milionUSD,
+        []
+      );
+      feeOracleMock = await deployFeeOracleMock(owner, 2000);
+      await flashLoanContract.withSigner(owner).tx.setFeeOracle({
+        oracle: feeOracleMock.address,
+        maxFeeE6: 10000,
+        fallback: FeeModelBuilder.Flat({ fee: 5 * oneUSD }),
+        gasLimit: 0,
+      });
+    });
+
+    it("User borrows 1_000 USDC and pays the fee quoted by the oracle", async () => {
+      expect(await borrowedFee()).to.equal((2 * oneUSD).toString());
+    });
+
+    it("Oracle quotes 50_000 (5%), user borrows 1_000 USDC and pays the capped fee", async () => {
+      await feeOracleMock.tx.setFeeE6(50000);
+      expect(await borrowedFee()).to.equal((10 * oneUSD).toString());
+    });
+
+    it("Oracle fails, user borrows 1_000 USDC and pays the fallback fee", async () => {
+      await feeOracleMock.tx.setFail(true);
+      expect(await borrowedFee()).to.equal((5 * oneUSD).toString());
+    });
+
+    it("Owner removes the fee oracle, user borrows 1_000 USDC and pays the registry fee", async () => {
+      await flashLoanContract.withSigner(owner).tx.setFeeOracle(null);
+      expect(await borrowedFee()).to.equal(oneUSD.toString());
+    });
+
+    it("User tries to set the fee oracle, transaction fails as he is not the owner", async () => {
+      await expect(
+        flashLoanContract.withSigner(user).query.setFeeOracle(null)
+      ).to.eventually.be.rejected.and.to.have.deep.property(
+        "_err",
+        FeeOracleSettingsErrorBuilder.OwnableError(
+          OwnableErrorBuilder.CallerIsNotOwner()
+        )
+      );
+    });
+  });
+
+  describe("Owner deploys FlashLoanContract with allowed list = [user] and USDC fee 1_000 (0.1%), transfers 1milion of USDC to it and sets the ECDSA key of Alice as the voucher signer. Then...", () => {
+    const [voucherSigner, otherSigner] = getEcdsaSigners();
+    const registry = new TypeRegistry();
+    const voucherFor = (caller: string, overrides: Partial<Voucher> = {}) => ({
+      lender: flashLoanContract.address,
+      caller,
+      limits: [[USDC.address, 2_000 * oneUSD]],
+      expiry: 1_000_000_000,
+      nonce: 1,
+      feeE6: 500,
+      ...overrides,
+    });
+    // signs the blake2 hash of the SCALE encoded voucher
+    const sign = (voucher: any, signer: KeyringPair = voucherSigner) => ({
+      voucher,
+      signature: Array.from(
+        signer.sign(
+          registry
+            .createType(
+              "(AccountId, AccountId, Vec<(AccountId, u128)>, u32, u64, Option<u128>)",
+              [
+                voucher.lender,
+                voucher.caller,
+                voucher.limits,
+                voucher.expiry,
+                voucher.nonce,
+                voucher.feeE6,
+              ]
+            )
+            .toU8a()
+        )
+      ),
+    });
+    const queryWithVoucher = (signedVoucher: any, amount = 1_000 * oneUSD) =>
+      flashLoanContract
+        .withSigner(random)
+        .query.flashLoanWithVoucher(
+          flashLoanReceiver.address,
+          [USDC.address],
+          [amount],
+          [],
+          signedVoucher
+        );
+    beforeEach("", async () => {
+      flashLoanContract = await deployFlashLoanContract(
+        owner,
+        [[USDC.address, 1000]],
+        [user.address],
+        []
+      );
+      await USDC.withSigner(owner).tx.transfer(
+        flashLoanContract.address,
+        milionUSD,
+        []
+      );
+      await flashLoanContract
+        .withSigner(owner)
+        .tx.setVoucherSigner(voucherSigner.address);
+    });
+
+    it("Random is not allowed but borrows 1_000 USDC with a voucher paying its fee, the voucher can not be used again", async () => {
+      const signedVoucher = sign(voucherFor(random.address));
+      await expect(
+        flashLoanContract
+          .withSigner(random)
+          .tx.flashLoanWithVoucher(
+            flashLoanReceiver.address,
+            [USDC.address],
+            [1_000 * oneUSD],
+            [],
+            signedVoucher
+          )
+      ).to.eventually.be.fulfilled;
+      const receipt = (await flashLoanContract.query.loanReceipt(1)).value!;
+      expect(receipt.fees[0].toString()).to.equal((oneUSD / 2).toString());
+      expect(
+        (await flashLoanContract.query.isNonceUsed(random.address, 1)).value
+      ).to.equal(true);
+      await expect(
+        queryWithVoucher(signedVoucher)
+      ).to.eventually.be.rejected.and.to.have.deep.property(
+        "_err",
+        FlashLoanErrorBuilder.VoucherReplayed()
+      );
+      await expect(
+        flashLoanContract
+          .withSigner(random)
+          .query.flashLoan(
+            flashLoanReceiver.address,
+            [USDC.address],
+            [1_000 * oneUSD],
+            []
+          )
+      ).to.eventually.be.rejected.and.to.have.deep.property(
+        "_err",
+        FlashLoanErrorBuilder.PolicyRejected(PolicyModule.allowlist)
+      );
+    });
+
+    it("Random uses a voucher signed by another key or meant for user, transaction fails with InvalidVoucher", async () => {
+      await expect(
+        queryWithVoucher(sign(voucherFor(random.address), otherSigner))
+      ).to.eventually.be.rejected.and.to.have.deep.property(
+        "_err",
+        FlashLoanErrorBuilder.InvalidVoucher()
+      );
+      await expect(
+        queryWithVoucher(sign(voucherFor(user.address)))
+      ).to.eventually.be.rejected.and.to.have.deep.property(
+        "_err",
+        FlashLoanErrorBuilder.InvalidVoucher()
+      );
+    });
+
+    it("Random borrows above the limit or with an expired voucher, transaction fails", async () => {
+      await expect(
+        queryWithVoucher(sign(voucherFor(random.address)), 3_000 * oneUSD)
+      ).to.eventually.be.rejected.and.to.have.deep.property(
+        "_err",
+        FlashLoanErrorBuilder.VoucherLimitExceeded()
+      );
+      await expect(
+        queryWithVoucher(sign(voucherFor(random.address, { expiry: 0 })))
+      ).to.eventually.be.rejected.and.to.have.deep.property(
+        "_err",
+        FlashLoanErrorBuilder.VoucherExpired()
+      );
+    });
+  });
+
+  describe("Deployer deploys AssetLenderMock lending native currency, a PSP34 token and a PSP37 token, funds it with 1 unit of native currency, PSP34 token 1 and 1_000 of PSP37 token 1, and deploys AssetReceiverMock. Then...", () => {
+    let assetLender: AssetLenderMock;
+    let assetReceiver: AssetReceiverMock;
+    let NFT: PSP34Mintable;
+    let MULTI: PSP37Mintable;
+    // native currency has no contract, the lender address is used as its key
+    let NATIVE: string;
+    const oneUnit = E12;
+    beforeEach("", async () => {
+      assetLender = await deployAssetLenderMock(deployer);
+      assetReceiver = await deployAssetReceiverMock(deployer);
+      NFT = await deployPSP34Mintable(deployer);
+      MULTI = await deployPSP37Mintable(deployer);
+      NATIVE = assetLender.address;
+      const specs: [string, any][] = [
+        [NATIVE, AssetSpecBuilder.Native()],
+        [NFT.address, AssetSpecBuilder.PSP34(IdBuilder.U8(1))],
+        [MULTI.address, AssetSpecBuilder.PSP37(IdBuilder.U8(1))],
+      ];
+      for (const [asset, spec] of specs) {
+        await assetLender.tx.setAssetSpec(asset, spec);
+        await assetReceiver.tx.setAssetSpec(asset, spec);
+      }
+      await assetLender.withSigner(owner).tx.fund({ value: oneUnit });
+      await NFT.tx.mint(assetLender.address, IdBuilder.U8(1));
+      await MULTI.tx.mint(assetLender.address, [[IdBuilder.U8(1), 1_000]]);
+    });
+
+    it("User borrows half a unit of native currency, the receiver transfers it back", async () => {
+      const balanceBefore = (await assetLender.query.maxFlashLoan(NATIVE))
+        .value;
+      await expect(
+        assetLender
+          .withSigner(user)
+          .tx.flashLoan(assetReceiver.address, [NATIVE], [oneUnit / 2], [])
+      ).to.eventually.be.fulfilled;
+      expect(
+        (await assetLender.query.maxFlashLoan(NATIVE)).value.toString()
+      ).to.equal(balanceBefore.toString());
+    });
+
+    it("Receiver keeps the native currency, transaction fails with NotRepaid", async () => {
+      await assetReceiver.tx.setKeepLoan(true);
+      await expect(
+        assetLender
+          .withSigner(user)
+          .query.flashLoan(assetReceiver.address, [NATIVE], [oneUnit / 2], [])
+      ).to.eventually.be.rejected.and.to.have.deep.property(
+        "_err",
+        FlashLoanErrorBuilder.NotRepaid()
+      );
+    });
+
+    it("User borrows PSP34 token 1, the receiver approves the lender and the token is pulled back", async () => {
+      expect(
+        (await assetLender.query.maxFlashLoan(NFT.address)).value.toString()
+      ).to.equal("1");
+      await expect(
+        assetLender
+          .withSigner(user)
+          .tx.flashLoan(assetReceiver.address, [NFT.address], [1], [])
+      ).to.eventually.be.fulfilled;
+      expect((await NFT.query.ownerOf(IdBuilder.U8(1))).value).to.equal(
+        assetLender.address
+      );
+      await expect(
+        assetLender
+          .withSigner(user)
+          .query.flashLoan(assetReceiver.address, [NFT.address], [2], [])
+      ).to.eventually.be.rejected.and.to.have.deep.property(
+        "_err",
+        FlashLoanErrorBuilder.Parameters()
+      );
+    });
+
+    it("User borrows 400 of PSP37 token 1 together with native currency, both are repaid", async () => {
+      await expect(
+        assetLender
+          .withSigner(user)
+          .tx.flashLoan(
+            assetReceiver.address,
+            [MULTI.address, NATIVE],
+            [400, oneUnit / 2],
+            []
+          )
+      ).to.eventually.be.fulfilled;
+      expect(
+        (
+          await MULTI.query.balanceOf(assetLender.address, IdBuilder.U8(1))
+        ).value.toString()
+      ).to.equal("1000");
+      expect(
+        (
+          await MULTI.query.balanceOf(assetReceiver.address, IdBuilder.U8(1))
+        ).value.toString()
+      ).to.equal("0");
+    });
+  });
 });
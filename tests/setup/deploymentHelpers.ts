@@ -7,9 +7,27 @@ import type { Weight } from "@polkadot/types/interfaces";
 import { readFileSync } from "fs-extra";
 
 import FlashLoanReceiverMock from "typechain/contracts/flash_loan_receiver_mock";
+import FlashLoanReceiverSimple from "typechain/contracts/flash_loan_receiver_simple";
+import FlashLoanExecutor from "typechain/contracts/flash_loan_executor";
+import AmmPairMock from "typechain/contracts/amm_pair_mock";
+import ArbitrageReceiver from "typechain/contracts/arbitrage_receiver";
+import LendingPoolMock from "typechain/contracts/lending_pool_mock";
+import LiquidationReceiver from "typechain/contracts/liquidation_receiver";
+import RefinanceReceiver from "typechain/contracts/refinance_receiver";
+import MaliciousReceiver from "typechain/contracts/malicious_receiver";
+import AdversarialPSP22 from "typechain/contracts/adversarial_psp22";
+import PolicyMock from "typechain/contracts/policy_mock";
+import FeeOracleMock from "typechain/contracts/fee_oracle_mock";
+import PSP34Mintable from "typechain/contracts/psp34_mintable";
+import PSP37Mintable from "typechain/contracts/psp37_mintable";
+import AssetLenderMock from "typechain/contracts/asset_lender_mock";
+import AssetReceiverMock from "typechain/contracts/asset_receiver_mock";
 import FlashLoanContract from "typechain/contracts/flash_loan_contract";
 import FlashLoanSimple from "typechain/contracts/flash_loan_simple";
+import FlashLoanRouter from "typechain/contracts/flash_loan_router";
+import LenderFactory from "typechain/contracts/lender_factory";
 import PSP22Mintable from "typechain/contracts/psp22_mintable";
+import WrappedNative from "typechain/contracts/wrapped_native";
 
 import { apiProviderWrapper } from "./helpers";
 import { AccountId } from "typechain/types-arguments/flash_loan_contract";
@@ -120,6 +138,22 @@ export const deployPSP22Mintable = async (
   );
 };
 
+export const deployWrappedNative = async (
+  deployer: KeyringPair,
+  flashFeeE6: BN | number | string = 0,
+  decimals: number = 12
+) => {
+  return deployWithLog(
+    deployer,
+    WrappedNative,
+    "wrapped_native",
+    "Wrapped Native",
+    "WNATIVE",
+    decimals,
+    flashFeeE6
+  );
+};
+
 export const deployFlashLoanContract = async (
   owner: KeyringPair,
   fees_e6: [AccountId, BN | number | string][],
@@ -135,6 +169,28 @@ export const deployFlashLoanContract = async (
     free
   );
 };
+export const deployFlashLoanRouter = async (
+  owner: KeyringPair,
+  lenders: AccountId[]
+) => {
+  return deployWithLog(owner, FlashLoanRouter, "flash_loan_router", lenders);
+};
+// code of the contract must be already uploaded (e.g. by deploying the contract once)
+export const getCodeHash = (contractName: string): string => {
+  const abi = JSON.parse(
+    readFileSync(`./artifacts/${contractName}.json`).toString()
+  );
+  return abi.source.hash;
+};
+
+export const deployLenderFactory = async (owner: KeyringPair) => {
+  return deployWithLog(
+    owner,
+    LenderFactory,
+    "lender_factory",
+    getCodeHash("flash_loan_contract")
+  );
+};
 export const deployFlashLoanSimple = async (owner: KeyringPair) => {
   return deployWithLog(owner, FlashLoanSimple, "flash_loan_simple");
 };
@@ -147,6 +203,96 @@ export const deployFlashLoanReceiverMock = async (deployer: KeyringPair) => {
   );
 };
 
+export const deployFlashLoanReceiverSimple = async (deployer: KeyringPair) => {
+  return deployWithLog(
+    deployer,
+    FlashLoanReceiverSimple,
+    "flash_loan_receiver_simple"
+  );
+};
+
+export const deployFlashLoanExecutor = async (owner: KeyringPair) => {
+  return deployWithLog(owner, FlashLoanExecutor, "flash_loan_executor");
+};
+
+export const deployAmmPairMock = async (
+  deployer: KeyringPair,
+  token0: string,
+  token1: string,
+  feeE6 = 3000
+) => {
+  return deployWithLog(
+    deployer,
+    AmmPairMock,
+    "amm_pair_mock",
+    token0,
+    token1,
+    feeE6
+  );
+};
+
+export const deployArbitrageReceiver = async (owner: KeyringPair) => {
+  return deployWithLog(owner, ArbitrageReceiver, "arbitrage_receiver");
+};
+
+export const deployLendingPoolMock = async (owner: KeyringPair) => {
+  return deployWithLog(owner, LendingPoolMock, "lending_pool_mock");
+};
+
+export const deployLiquidationReceiver = async (owner: KeyringPair) => {
+  return deployWithLog(owner, LiquidationReceiver, "liquidation_receiver");
+};
+
+export const deployRefinanceReceiver = async (owner: KeyringPair) => {
+  return deployWithLog(owner, RefinanceReceiver, "refinance_receiver");
+};
+
+export const deployMaliciousReceiver = async (deployer: KeyringPair) => {
+  return deployWithLog(deployer, MaliciousReceiver, "malicious_receiver");
+};
+
+export const deployAdversarialPSP22 = async (
+  deployer: KeyringPair,
+  name: string,
+  decimals: number = 6
+) => {
+  return deployWithLog(
+    deployer,
+    AdversarialPSP22,
+    "adversarial_psp22",
+    name,
+    `Reserve ${name} token `,
+    decimals
+  );
+};
+
+export const deployPolicyMock = async (deployer: KeyringPair) => {
+  return deployWithLog(deployer, PolicyMock, "policy_mock");
+};
+
+export const deployFeeOracleMock = async (
+  deployer: KeyringPair,
+  feeE6: number
+) => {
+  return deployWithLog(deployer, FeeOracleMock, "fee_oracle_mock", feeE6);
+};
+
+export const deployPSP34Mintable = async (deployer: KeyringPair) => {
+  return deployWithLog(deployer, PSP34Mintable, "psp34_mintable");
+};
+
+export const deployPSP37Mintable = async (deployer: KeyringPair) => {
+  return deployWithLog(deployer, PSP37Mintable, "psp37_mintable");
+};
+
+export const deployAssetLenderMock = async (deployer: KeyringPair) => {
+  return deployWithLog(deployer, AssetLenderMock, "asset_lender_mock");
+};
+
+export const deployAssetReceiverMock = async (deployer: KeyringPair) => {
+  return deployWithLog(deployer, AssetReceiverMock, "asset_receiver_mock");
+};
+
 export const getContractObject = async <T>(
   constructor: new (
     address: string,
@@ -6,6 +6,9 @@ export const apiProviderWrapper = new ApiProviderWrapper("ws://127.0.0.1:9944");
 export const getSigners = () => {
   return createTestKeyring({ type: "sr25519" }).pairs;
 };
+export const getEcdsaSigners = () => {
+  return createTestKeyring({ type: "ecdsa" }).pairs;
+};
 export const getSignersWithoutOwner = (
   signers: KeyringPair[],
   ownerIndex: number